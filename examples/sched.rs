@@ -24,7 +24,7 @@ fn main() -> Result<()> {
     let mut sched = Scheduler::new();
     log::debug!("scheduler created");
 
-    let handler = sched.schedule("test-task", my_action, recur(ruleset().at_second(0)));
+    let handler = sched.schedule("test-task", my_action, recur(ruleset().at_second(0)))?;
 
     std::thread::sleep(std::time::Duration::from_secs(1));
 
@@ -26,7 +26,7 @@ async fn main() -> Result<()> {
     let mut sched = Scheduler::new();
     log::debug!("scheduler created");
 
-    let handler = sched.schedule("test-task", my_action, recur(ruleset().at_second(0))).await;
+    let handler = sched.schedule("test-task", my_action, recur(ruleset().at_second(0)))?;
 
     sleep(std::time::Duration::from_secs(1)).await;
 
@@ -38,7 +38,7 @@ async fn main() -> Result<()> {
 
         sleep(std::time::Duration::from_secs(60)).await;
 
-        let result = sched.remove(&handler).await;
+        let result = sched.remove(&handler);
 
         if result.is_ok() {
             println!("task {name} removed");
@@ -21,15 +21,13 @@ async fn main() -> Result<()> {
 
     let now = chrono::Local::now();
 
-    let handler = sched
-        .schedule_fut(
-            "test-task",
-            async move {
-                println!("I'm running at {}", &now.format("%Y-%m-%d %H:%M:%S"));
-            },
-            recur(ruleset().at_second(0)),
-        )
-        .await;
+    let handler = sched.schedule_fut(
+        "test-task",
+        async move {
+            println!("I'm running at {}", &now.format("%Y-%m-%d %H:%M:%S"));
+        },
+        recur(ruleset().at_second(0)),
+    )?;
 
     sleep(std::time::Duration::from_secs(1)).await;
 
@@ -41,7 +39,7 @@ async fn main() -> Result<()> {
 
         sleep(std::time::Duration::from_secs(60)).await;
 
-        let result = sched.remove(&handler).await;
+        let result = sched.remove(&handler);
 
         if result.is_ok() {
             println!("task {name} removed");
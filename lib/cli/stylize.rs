@@ -3,5 +3,8 @@ mod stylizer;
 
 pub use stylizer::{stl, stylize, Stylize};
 
+#[cfg(feature = "logger")]
+pub(crate) use stylizer::instructions;
+
 #[cfg(test)]
 mod tests;
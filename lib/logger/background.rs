@@ -0,0 +1,225 @@
+use {
+    super::{sink::SinkConfig, LoggerHandle},
+    std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, Condvar, Mutex, OnceLock,
+        },
+        thread::{self, JoinHandle},
+    },
+};
+
+/// the background writer of the installed logger, if it's asynchronous
+static INSTALLED: OnceLock<Arc<Background>> = OnceLock::new();
+
+/// 🧉 » overflow policy
+///
+/// what an asynchronous logger does with a record when its queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// the logging thread waits until the background writer makes room for the record
+    #[default]
+    Block,
+    /// the new record is dropped
+    DropNewest,
+    /// the oldest record in the queue is dropped to make room for the new one
+    DropOldest,
+}
+
+/// 🧉 » async guard
+///
+/// keeps the background writer of an asynchronous logger running (see
+/// `SetupBuilder::install_async`).
+///
+/// when dropped, the records still in the queue are written and the sinks are flushed, so it
+/// should be kept alive until the end of `main`, e.g. as `let _guard = ...`. Records logged
+/// afterwards are written synchronously, on the thread that logs them.
+#[must_use = "the logger is shut down as soon as the guard is dropped"]
+pub struct AsyncGuard {
+    background: Arc<Background>,
+    handle: LoggerHandle,
+}
+
+impl AsyncGuard {
+    pub(crate) fn new(background: Arc<Background>, handle: LoggerHandle) -> Self {
+        Self { background, handle }
+    }
+
+    /// returns the handle to change the filters of the logger while the program runs
+    pub fn handle(&self) -> &LoggerHandle {
+        &self.handle
+    }
+}
+
+impl Drop for AsyncGuard {
+    fn drop(&mut self) {
+        self.background.shutdown();
+    }
+}
+
+/// the lines of a record, ready to be written to the sinks
+pub(crate) struct Batch {
+    /// the index of the sink, along with the line to write to it
+    pub(crate) lines: Vec<(usize, String)>,
+    /// the number of dropped records this batch reports, added back if it's dropped too
+    pub(crate) reported: u64,
+}
+
+struct State {
+    queue: VecDeque<Batch>,
+    /// whether the writer is writing a batch it took from the queue
+    writing: bool,
+    closed: bool,
+}
+
+/// a bounded queue of batches, written to the sinks by a background thread
+pub(crate) struct Background {
+    state: Mutex<State>,
+    /// notified when a batch is queued or the writer is shut down
+    queued: Condvar,
+    /// notified when the writer takes or finishes a batch
+    written: Condvar,
+    capacity: usize,
+    overflow: Overflow,
+    dropped: AtomicU64,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Background {
+    /// starts the writer thread of the given sinks
+    pub(crate) fn spawn(
+        sinks: Arc<Vec<SinkConfig>>,
+        capacity: usize,
+        overflow: Overflow,
+    ) -> Arc<Self> {
+        let background = Arc::new(Self {
+            state: Mutex::new(State {
+                queue: VecDeque::with_capacity(capacity),
+                writing: false,
+                closed: false,
+            }),
+            queued: Condvar::new(),
+            written: Condvar::new(),
+            capacity,
+            overflow,
+            dropped: AtomicU64::new(0),
+            thread: Mutex::new(None),
+        });
+
+        let writer = Arc::clone(&background);
+        let thread = thread::spawn(move || writer.run(&sinks));
+        *background.thread.lock().unwrap() = Some(thread);
+
+        background
+    }
+
+    /// installs this writer as the one stopped by `logger::shutdown`
+    pub(crate) fn install(self: &Arc<Self>) {
+        let _ = INSTALLED.set(Arc::clone(self));
+    }
+
+    /// returns the number of records dropped since the last call, and resets it
+    pub(crate) fn take_dropped(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    /// queues a batch, following the overflow policy when the queue is full.
+    ///
+    /// returns the batch back if the writer was shut down, so it's written by the caller.
+    pub(crate) fn send(&self, batch: Batch) -> Result<(), Batch> {
+        let mut state = self.state.lock().unwrap();
+
+        while !state.closed && state.queue.len() >= self.capacity {
+            match self.overflow {
+                Overflow::Block => state = self.written.wait(state).unwrap(),
+                Overflow::DropNewest => {
+                    self.dropped.fetch_add(1 + batch.reported, Ordering::Relaxed);
+                    return Ok(());
+                }
+                Overflow::DropOldest => {
+                    if let Some(oldest) = state.queue.pop_front() {
+                        self.dropped.fetch_add(1 + oldest.reported, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+
+        if state.closed {
+            return Err(batch);
+        }
+
+        state.queue.push_back(batch);
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    /// waits until every queued batch has been written
+    pub(crate) fn wait_idle(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.closed && (state.writing || !state.queue.is_empty()) {
+            state = self.written.wait(state).unwrap();
+        }
+    }
+
+    /// writes the queued batches, stops the writer thread and waits for it to finish
+    pub(crate) fn shutdown(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.queued.notify_all();
+        self.written.notify_all();
+
+        let thread = self.thread.lock().unwrap().take();
+        if let Some(thread) = thread.filter(|thread| thread.thread().id() != thread::current().id())
+        {
+            let _ = thread.join();
+        }
+    }
+
+    fn run(&self, sinks: &[SinkConfig]) {
+        loop {
+            let batch = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if let Some(batch) = state.queue.pop_front() {
+                        state.writing = true;
+                        break batch;
+                    }
+
+                    if state.closed {
+                        for sink in sinks {
+                            sink.sink.flush();
+                        }
+                        return;
+                    }
+
+                    state = self.queued.wait(state).unwrap();
+                }
+            };
+            // there's room in the queue for a blocked record
+            self.written.notify_all();
+
+            for (index, line) in &batch.lines {
+                sinks[*index].sink.write_line(line);
+            }
+
+            self.state.lock().unwrap().writing = false;
+            self.written.notify_all();
+        }
+    }
+}
+
+/// **🧉 » shuts down the logger**
+///
+/// writes the records still queued by an asynchronous logger and stops its background writer
+/// (like dropping its `AsyncGuard`), then flushes the sinks. Records logged afterwards are written
+/// synchronously.
+///
+/// useful when the guard can't be kept until the end of the program, e.g. before calling
+/// `std::process::exit`. Synchronous loggers with buffered sinks (like `RollingFileSink`) should
+/// call it before the program exits too, as the installed logger is never dropped.
+pub fn shutdown() {
+    if let Some(background) = INSTALLED.get() {
+        background.shutdown();
+    }
+    log::logger().flush();
+}
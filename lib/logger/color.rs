@@ -0,0 +1,81 @@
+use std::env;
+
+/// 🧉 » color mode
+///
+/// whether the lines written to a sink are styled with ANSI escapes. Without colors, formatters
+/// write the same layout without the escape sequences.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorMode {
+    /// colors are used when the sink is a terminal, unless the environment says otherwise:
+    ///
+    /// - `FORCE_COLOR` or `CLICOLOR_FORCE` (set, and not `0`) force colors, even if the sink is
+    ///   not a terminal (`FORCE_COLOR=0` disables them).
+    /// - `NO_COLOR` (set, and not empty) disables colors.
+    /// - `CLICOLOR=0` disables colors.
+    #[default]
+    Auto,
+    /// colors are always used
+    Always,
+    /// colors are never used
+    Never,
+}
+
+impl ColorMode {
+    /// returns whether a sink uses colors, reading the conventions from the environment
+    pub(crate) fn enabled(self, is_terminal: bool) -> bool {
+        self.enabled_with(is_terminal, |var| env::var(var).ok())
+    }
+
+    fn enabled_with<E: Fn(&str) -> Option<String>>(self, is_terminal: bool, env: E) -> bool {
+        match self {
+            ColorMode::Always => return true,
+            ColorMode::Never => return false,
+            ColorMode::Auto => {}
+        }
+
+        let set = |var| env(var).filter(|value| !value.is_empty());
+
+        if let Some(force) = set("FORCE_COLOR").or_else(|| set("CLICOLOR_FORCE")) {
+            return force != "0" && force != "false";
+        }
+
+        if set("NO_COLOR").is_some() || set("CLICOLOR").is_some_and(|value| value == "0") {
+            return false;
+        }
+
+        is_terminal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auto(is_terminal: bool, vars: &[(&str, &str)]) -> bool {
+        ColorMode::Auto.enabled_with(is_terminal, |var| {
+            vars.iter().find(|(name, _)| *name == var).map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_auto_color_mode() {
+        assert!(auto(true, &[]));
+        assert!(!auto(false, &[]));
+
+        assert!(!auto(true, &[("NO_COLOR", "1")]));
+        assert!(auto(true, &[("NO_COLOR", "")]));
+        assert!(!auto(true, &[("CLICOLOR", "0")]));
+        assert!(auto(true, &[("CLICOLOR", "1")]));
+
+        assert!(auto(false, &[("FORCE_COLOR", "1")]));
+        assert!(auto(false, &[("CLICOLOR_FORCE", "1")]));
+        assert!(auto(false, &[("FORCE_COLOR", "3"), ("NO_COLOR", "1")]));
+        assert!(!auto(true, &[("FORCE_COLOR", "0")]));
+    }
+
+    #[test]
+    fn test_explicit_color_modes() {
+        assert!(ColorMode::Always.enabled_with(false, |_| Some("1".to_string())));
+        assert!(!ColorMode::Never.enabled_with(true, |_| None));
+    }
+}
@@ -1,4 +1,10 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use {
+    eyre::{eyre, Result},
+    std::{
+        fmt::Write,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+};
 
 pub fn noop_datetime() -> String {
     "".to_string()
@@ -6,48 +12,386 @@ pub fn noop_datetime() -> String {
 
 /// Get the current time in the format yyyy-mm-dd hh:mm:ss
 pub fn utc_current_time() -> String {
-    // Get the current system time
-    let now = SystemTime::now();
-
-    // Convert the adjusted time to a formatted string
-    match now.duration_since(UNIX_EPOCH) {
-        Ok(duration) => {
-            let secs = duration.as_secs();
-            let days = secs / (24 * 3600);
-            let mut years = 1970;
-            let mut days_left = days;
-
-            // Adjusting for leap years
-            loop {
-                let days_in_year = if is_leap_year(years) { 366 } else { 365 };
-                if days_left < days_in_year {
-                    break;
+    Timestamp::utc().now()
+}
+
+/// 🧉 » precision of a timestamp
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Precision {
+    #[default]
+    Seconds,
+    Millis,
+    Micros,
+}
+
+/// 🧉 » timestamp
+///
+/// a configurable time function for the logger, given to it with `SetupBuilder::with_timestamp`.
+///
+/// a timestamp has a time zone (utc, a fixed offset, or the local time zone with the
+/// `logger.local-time` feature), a precision, and a format:
+///
+/// - the default one, `2024-03-31 15:44:46` (with `.123` or `.123456` for ms/µs precision).
+/// - rfc 3339, `2024-03-31T15:44:46Z` or `2024-03-31T12:44:46-03:00`.
+/// - a strftime-like pattern (see `with_pattern`).
+///
+/// timestamps can also show the (monotonic) time elapsed since they were created, e.g. `12.345s`.
+///
+/// **Example**
+///
+/// ```rust
+/// use lool::logger::datetime::{Precision, Timestamp};
+///
+/// let timestamp = Timestamp::fixed_offset(-3 * 3600).with_precision(Precision::Millis).rfc3339();
+/// assert_eq!(timestamp.now().len(), "2024-03-31T12:44:46.123-03:00".len());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Timestamp {
+    zone: Zone,
+    precision: Precision,
+    format: TimeFormat,
+    start: Instant,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Zone {
+    Utc,
+    /// seconds east of utc
+    Fixed(i32),
+    #[cfg(feature = "logger.local-time")]
+    Local,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TimeFormat {
+    Default,
+    Rfc3339,
+    Pattern(Vec<PatternItem>),
+    Elapsed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum PatternItem {
+    Literal(String),
+    Year,
+    ShortYear,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    /// the fraction of the second, with the given number of digits
+    Fraction(usize),
+    /// the utc offset as `+hhmm`, or `+hh:mm`
+    Offset {
+        colon: bool,
+    },
+}
+
+/// a date and time in a time zone, broken down into its parts
+struct CivilTime {
+    year: u64,
+    month: u64,
+    day: u64,
+    hour: u64,
+    minute: u64,
+    second: u64,
+    nanos: u32,
+    offset: i32,
+}
+
+impl Timestamp {
+    /// **🧉 » utc timestamp**
+    pub fn utc() -> Self {
+        Self::in_zone(Zone::Utc)
+    }
+
+    /// **🧉 » timestamp with a fixed utc offset, in seconds east of utc**
+    ///
+    /// e.g. `Timestamp::fixed_offset(-3 * 3600)` for UTC-03:00.
+    pub fn fixed_offset(seconds: i32) -> Self {
+        Self::in_zone(Zone::Fixed(seconds))
+    }
+
+    /// **🧉 » timestamp in the local time zone of the system**
+    #[cfg(feature = "logger.local-time")]
+    pub fn local() -> Self {
+        Self::in_zone(Zone::Local)
+    }
+
+    /// **🧉 » elapsed time since the timestamp was created**
+    ///
+    /// uses a monotonic clock, so it's not affected by changes to the system clock. Shows seconds
+    /// (e.g. `12s`, or `12.345s` with ms precision).
+    pub fn elapsed() -> Self {
+        Self {
+            format: TimeFormat::Elapsed,
+            ..Self::utc()
+        }
+    }
+
+    fn in_zone(zone: Zone) -> Self {
+        Self {
+            zone,
+            precision: Precision::Seconds,
+            format: TimeFormat::Default,
+            start: Instant::now(),
+        }
+    }
+
+    /// **🧉 » `with_precision`**
+    ///
+    /// Sets the precision of the default, rfc 3339 and elapsed formats
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// **🧉 » `rfc3339`**
+    ///
+    /// Uses the rfc 3339 format, e.g. `2024-03-31T15:44:46Z`
+    pub fn rfc3339(mut self) -> Self {
+        self.format = TimeFormat::Rfc3339;
+        self
+    }
+
+    /// **🧉 » `with_pattern`**
+    ///
+    /// Uses a strftime-like pattern, e.g. `%d/%m/%Y %H:%M:%S.%3f`, with the following
+    /// specifiers:
+    ///
+    /// - `%Y` (year), `%y` (2-digit year), `%m` (month), `%d` (day).
+    /// - `%H` (hour), `%M` (minute), `%S` (second).
+    /// - `%f`, `%3f`, `%6f` and `%9f`: the fraction of the second (9 digits for `%f`).
+    /// - `%z` (`+hhmm`) and `%:z` (`+hh:mm`): the utc offset.
+    /// - `%F` (`%Y-%m-%d`) and `%T` (`%H:%M:%S`).
+    /// - `%%`: a literal `%`.
+    ///
+    /// Fails on unknown specifiers.
+    pub fn with_pattern<S: AsRef<str>>(mut self, pattern: S) -> Result<Self> {
+        self.format = TimeFormat::Pattern(parse_pattern(pattern.as_ref())?);
+        Ok(self)
+    }
+
+    /// **🧉 » returns the current time, formatted**
+    pub fn now(&self) -> String {
+        match self.format {
+            TimeFormat::Elapsed => format_elapsed(self.start.elapsed(), self.precision),
+            _ => self.format_at(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()),
+        }
+    }
+
+    /// formats a time, given as the duration since UNIX epoch
+    fn format_at(&self, since_epoch: Duration) -> String {
+        let time = CivilTime::new(since_epoch, self.offset());
+        let date = format!("{:04}-{:02}-{:02}", time.year, time.month, time.day);
+        let clock = format!("{:02}:{:02}:{:02}", time.hour, time.minute, time.second);
+
+        match &self.format {
+            TimeFormat::Default => format!("{} {}{}", date, clock, time.fraction(self.precision)),
+            TimeFormat::Rfc3339 => {
+                let offset = match self.zone {
+                    Zone::Utc => "Z".to_string(),
+                    _ => time.offset(true),
+                };
+                format!(
+                    "{}T{}{}{}",
+                    date,
+                    clock,
+                    time.fraction(self.precision),
+                    offset
+                )
+            }
+            TimeFormat::Pattern(items) => time.format(items),
+            TimeFormat::Elapsed => format_elapsed(self.start.elapsed(), self.precision),
+        }
+    }
+
+    /// returns the utc offset of the time zone right now, in seconds east of utc
+    fn offset(&self) -> i32 {
+        match self.zone {
+            Zone::Utc => 0,
+            Zone::Fixed(offset) => offset,
+            #[cfg(feature = "logger.local-time")]
+            Zone::Local => {
+                use chrono::Offset;
+                chrono::Local::now().offset().fix().local_minus_utc()
+            }
+        }
+    }
+}
+
+impl CivilTime {
+    fn new(since_epoch: Duration, offset: i32) -> Self {
+        let secs = (since_epoch.as_secs() as i64 + offset as i64).max(0) as u64;
+        let (year, month, day) = utc_date(secs / (24 * 3600));
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (secs / 3600) % 24,
+            minute: (secs / 60) % 60,
+            second: secs % 60,
+            nanos: since_epoch.subsec_nanos(),
+            offset,
+        }
+    }
+
+    /// returns the fraction of the second, with a leading dot, for the given precision
+    fn fraction(&self, precision: Precision) -> String {
+        match precision {
+            Precision::Seconds => String::new(),
+            Precision::Millis => format!(".{:03}", self.nanos / 1_000_000),
+            Precision::Micros => format!(".{:06}", self.nanos / 1_000),
+        }
+    }
+
+    /// returns the utc offset as `+hhmm`, or `+hh:mm`
+    fn offset(&self, colon: bool) -> String {
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let minutes = self.offset.unsigned_abs() / 60;
+        let separator = if colon { ":" } else { "" };
+
+        format!(
+            "{}{:02}{}{:02}",
+            sign,
+            minutes / 60,
+            separator,
+            minutes % 60
+        )
+    }
+
+    fn format(&self, items: &[PatternItem]) -> String {
+        let mut formatted = String::new();
+
+        for item in items {
+            let _ = match item {
+                PatternItem::Literal(text) => write!(formatted, "{}", text),
+                PatternItem::Year => write!(formatted, "{:04}", self.year),
+                PatternItem::ShortYear => write!(formatted, "{:02}", self.year % 100),
+                PatternItem::Month => write!(formatted, "{:02}", self.month),
+                PatternItem::Day => write!(formatted, "{:02}", self.day),
+                PatternItem::Hour => write!(formatted, "{:02}", self.hour),
+                PatternItem::Minute => write!(formatted, "{:02}", self.minute),
+                PatternItem::Second => write!(formatted, "{:02}", self.second),
+                PatternItem::Fraction(digits) => {
+                    let nanos = format!("{:09}", self.nanos);
+                    write!(formatted, "{}", &nanos[..*digits])
+                }
+                PatternItem::Offset { colon } => write!(formatted, "{}", self.offset(*colon)),
+            };
+        }
+
+        formatted
+    }
+}
+
+/// parses a strftime-like pattern
+fn parse_pattern(pattern: &str) -> Result<Vec<PatternItem>> {
+    let mut items = vec![];
+    let mut literal = String::new();
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+
+        let mut specifier = String::new();
+        let mut item = None;
+        for c in chars.by_ref() {
+            specifier.push(c);
+
+            item = match specifier.as_str() {
+                "3" | "6" | "9" | ":" => continue,
+                "%" => {
+                    literal.push('%');
+                    None
                 }
-                days_left -= days_in_year;
-                years += 1;
+                "Y" => Some(vec![PatternItem::Year]),
+                "y" => Some(vec![PatternItem::ShortYear]),
+                "m" => Some(vec![PatternItem::Month]),
+                "d" => Some(vec![PatternItem::Day]),
+                "H" => Some(vec![PatternItem::Hour]),
+                "M" => Some(vec![PatternItem::Minute]),
+                "S" => Some(vec![PatternItem::Second]),
+                "f" | "9f" => Some(vec![PatternItem::Fraction(9)]),
+                "3f" => Some(vec![PatternItem::Fraction(3)]),
+                "6f" => Some(vec![PatternItem::Fraction(6)]),
+                "z" => Some(vec![PatternItem::Offset { colon: false }]),
+                ":z" => Some(vec![PatternItem::Offset { colon: true }]),
+                "F" => Some(vec![
+                    PatternItem::Year,
+                    PatternItem::Literal("-".to_string()),
+                    PatternItem::Month,
+                    PatternItem::Literal("-".to_string()),
+                    PatternItem::Day,
+                ]),
+                "T" => Some(vec![
+                    PatternItem::Hour,
+                    PatternItem::Literal(":".to_string()),
+                    PatternItem::Minute,
+                    PatternItem::Literal(":".to_string()),
+                    PatternItem::Second,
+                ]),
+                _ => return Err(eyre!("unknown specifier `%{}` in `{}`", specifier, pattern)),
+            };
+            break;
+        }
+
+        if let Some(specifier_items) = item {
+            if !literal.is_empty() {
+                items.push(PatternItem::Literal(std::mem::take(&mut literal)));
             }
+            items.extend(specifier_items);
+        } else if !specifier.ends_with('%') {
+            return Err(eyre!(
+                "unfinished specifier `%{}` in `{}`",
+                specifier,
+                pattern
+            ));
+        }
+    }
+
+    if !literal.is_empty() {
+        items.push(PatternItem::Literal(literal));
+    }
 
-            let (month, day) = days_to_date(days_left, is_leap_year(years));
-
-            let hours = (secs / 3600) % 24;
-            let minutes = (secs / 60) % 60;
-            let seconds = secs % 60;
-
-            format!(
-                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
-                years,
-                month + 1,
-                day,
-                hours,
-                minutes,
-                seconds
-            )
+    Ok(items)
+}
+
+/// formats an elapsed time as seconds, e.g. `12.345s`
+fn format_elapsed(elapsed: Duration, precision: Precision) -> String {
+    match precision {
+        Precision::Seconds => format!("{}s", elapsed.as_secs()),
+        Precision::Millis => format!("{}.{:03}s", elapsed.as_secs(), elapsed.subsec_millis()),
+        Precision::Micros => format!("{}.{:06}s", elapsed.as_secs(), elapsed.subsec_micros()),
+    }
+}
+
+/// returns the (year, month, day) of a number of days since UNIX epoch, with 1-based months
+pub(crate) fn utc_date(days: u64) -> (u64, u64, u64) {
+    let mut years = 1970;
+    let mut days_left = days;
+
+    // Adjusting for leap years
+    loop {
+        let days_in_year = if is_leap_year(years) { 366 } else { 365 };
+        if days_left < days_in_year {
+            break;
         }
-        Err(_) => "0".to_string(),
+        days_left -= days_in_year;
+        years += 1;
     }
+
+    let (month, day) = days_to_date(days_left, is_leap_year(years));
+    (years, month + 1, day)
 }
 
 // Check if a year is a leap year
+#[allow(clippy::manual_is_multiple_of)]
 fn is_leap_year(year: u64) -> bool {
     (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
 }
@@ -65,3 +409,101 @@ fn days_to_date(days: u64, leap: bool) -> (u64, u64) {
 
     (month, days_left + 1) // Adding 1 to day to make it 1-based
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        eyre::{set_hook, DefaultHandler},
+    };
+
+    fn setup_eyre() {
+        let _ = set_hook(Box::new(DefaultHandler::default_with));
+    }
+
+    /// 2024-03-31 15:44:46.123456789 UTC
+    const INSTANT: Duration = Duration::new(1_711_899_886, 123_456_789);
+
+    #[test]
+    fn test_default_format() {
+        assert_eq!(Timestamp::utc().format_at(INSTANT), "2024-03-31 15:44:46");
+        assert_eq!(
+            Timestamp::utc().with_precision(Precision::Millis).format_at(INSTANT),
+            "2024-03-31 15:44:46.123"
+        );
+        assert_eq!(
+            Timestamp::fixed_offset(-16 * 3600)
+                .with_precision(Precision::Micros)
+                .format_at(INSTANT),
+            "2024-03-30 23:44:46.123456"
+        );
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(
+            Timestamp::utc().rfc3339().format_at(INSTANT),
+            "2024-03-31T15:44:46Z"
+        );
+        assert_eq!(
+            Timestamp::fixed_offset(5 * 3600 + 30 * 60)
+                .with_precision(Precision::Millis)
+                .rfc3339()
+                .format_at(INSTANT),
+            "2024-03-31T21:14:46.123+05:30"
+        );
+        assert_eq!(
+            Timestamp::fixed_offset(-3 * 3600).rfc3339().format_at(INSTANT),
+            "2024-03-31T12:44:46-03:00"
+        );
+    }
+
+    #[test]
+    fn test_patterns() {
+        let format = |pattern: &str| {
+            Timestamp::fixed_offset(-3 * 3600).with_pattern(pattern).unwrap().format_at(INSTANT)
+        };
+
+        assert_eq!(format("%d/%m/%y %H:%M:%S"), "31/03/24 12:44:46");
+        assert_eq!(format("%F %T.%3f"), "2024-03-31 12:44:46.123");
+        assert_eq!(format("%6f|%f|%9f"), "123456|123456789|123456789");
+        assert_eq!(format("%Y%m%d %z %:z 100%%"), "20240331 -0300 -03:00 100%");
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        setup_eyre();
+
+        let err = Timestamp::utc().with_pattern("%Y-%q").unwrap_err();
+        assert_eq!(err.to_string(), "unknown specifier `%q` in `%Y-%q`");
+        assert!(Timestamp::utc().with_pattern("%H:%").is_err());
+        assert!(Timestamp::utc().with_pattern("%3").is_err());
+    }
+
+    #[test]
+    fn test_elapsed() {
+        let elapsed = Duration::new(12, 345_678_000);
+
+        assert_eq!(format_elapsed(elapsed, Precision::Seconds), "12s");
+        assert_eq!(format_elapsed(elapsed, Precision::Millis), "12.345s");
+        assert_eq!(format_elapsed(elapsed, Precision::Micros), "12.345678s");
+        assert_eq!(Timestamp::elapsed().now(), "0s");
+    }
+
+    #[cfg(feature = "logger.local-time")]
+    #[test]
+    fn test_local_time() {
+        let now = Timestamp::local().rfc3339().now();
+        let offset = &now["2024-03-31T15:44:46".len()..];
+
+        assert!(offset == "Z" || offset.len() == "+00:00".len(), "{}", now);
+    }
+
+    #[test]
+    fn test_leap_years() {
+        // 2024-02-29 and 2100-03-01 (2100 isn't a leap year)
+        assert_eq!(utc_date(19_782), (2024, 2, 29));
+        assert_eq!(utc_date(47_541), (2100, 3, 1));
+        assert_eq!(utc_current_time().len(), "2024-03-31 15:44:46".len());
+    }
+}
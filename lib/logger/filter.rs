@@ -0,0 +1,300 @@
+use {
+    eyre::{eyre, Result},
+    glob_match::glob_match,
+    log::{LevelFilter, Metadata},
+    std::str::FromStr,
+};
+
+/// **filters of a logger**
+///
+/// the level filters and the ignored context globs, which can be changed after the logger is
+/// installed (see `LoggerHandle`).
+#[derive(Clone, Debug)]
+pub(crate) struct Filters {
+    pub(crate) levels: LevelFilters,
+    pub(crate) ignore_globs: Vec<String>,
+}
+
+impl Filters {
+    /// returns true if the context should be ignored
+    pub(crate) fn should_ignore(&self, ctx: &str) -> bool {
+        self.ignore_globs.iter().any(|glob| glob_match(glob, ctx))
+    }
+}
+
+/// **level filters of a logger**
+///
+/// a global level, plus levels for specific modules/targets. Records are checked against the
+/// most specific match: the longest module path that the record's target is (or is inside of).
+#[derive(Clone, Debug)]
+pub(crate) struct LevelFilters {
+    global: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LevelFilters {
+    pub(crate) fn new(global: LevelFilter) -> Self {
+        Self {
+            global,
+            modules: vec![],
+        }
+    }
+
+    /// sets the level used for the targets without a module level
+    pub(crate) fn set_global(&mut self, level: LevelFilter) {
+        self.global = level;
+    }
+
+    /// sets the level of a module (or target) and everything inside of it
+    pub(crate) fn set_module<S: AsRef<str>>(&mut self, module: S, level: LevelFilter) {
+        let module = module.as_ref().trim_end_matches("::");
+
+        match self.modules.iter_mut().find(|(path, _)| path == module) {
+            Some((_, current)) => *current = level,
+            None => self.modules.push((module.to_string(), level)),
+        }
+    }
+
+    /// removes the level of a module, which then uses the level of its parent modules (or the
+    /// global one)
+    pub(crate) fn remove_module<S: AsRef<str>>(&mut self, module: S) {
+        let module = module.as_ref().trim_end_matches("::");
+        self.modules.retain(|(path, _)| path != module);
+    }
+
+    /// returns the level that applies to a target
+    pub(crate) fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(path, _)| is_inside(target, path))
+            .max_by_key(|(path, _)| path.len())
+            .map_or(self.global, |(_, level)| *level)
+    }
+
+    /// returns whether a record with the given metadata passes the filters
+    pub(crate) fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    /// returns the most verbose level of any filter, to be used as `log::max_level`
+    pub(crate) fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).fold(self.global, Ord::max)
+    }
+}
+
+/// **filter directives**
+///
+/// parsed from a comma-separated list like `info,my_crate::db=trace,hyper=warn,-**/generated/**`
+/// (usually read from an environment variable):
+///
+/// - `level`: the global level.
+/// - `module=level`: the level of a module (or target).
+/// - `module`: the module logs everything (like `module=trace`).
+/// - `-glob`: an ignored context glob, like the ones passed to `SetupBuilder::ignore`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Directives {
+    pub(crate) global: Option<LevelFilter>,
+    pub(crate) modules: Vec<(String, LevelFilter)>,
+    pub(crate) ignore_globs: Vec<String>,
+}
+
+impl Directives {
+    /// adds the directives to some filters, overriding the levels they already have for the same
+    /// modules
+    pub(crate) fn apply(self, filters: &mut Filters) {
+        if let Some(global) = self.global {
+            filters.levels.set_global(global);
+        }
+
+        for (module, level) in self.modules {
+            filters.levels.set_module(module, level);
+        }
+
+        filters.ignore_globs.extend(self.ignore_globs);
+    }
+}
+
+impl FromStr for Directives {
+    type Err = eyre::Report;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut directives = Directives::default();
+
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            if let Some(glob) = directive.strip_prefix('-') {
+                if glob.is_empty() {
+                    return Err(eyre!("invalid directive `{}`: missing glob", directive));
+                }
+
+                directives.ignore_globs.push(glob.to_string());
+                continue;
+            }
+
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    let module = module.trim();
+                    if !is_module_path(module) {
+                        return Err(eyre!(
+                            "invalid directive `{}`: `{}` is not a module path",
+                            directive,
+                            module
+                        ));
+                    }
+
+                    directives.modules.push((module.to_string(), parse_level(directive, level)?));
+                }
+                None => match LevelFilter::from_str(directive) {
+                    Ok(level) => directives.global = Some(level),
+                    Err(_) if is_module_path(directive) => {
+                        directives.modules.push((directive.to_string(), LevelFilter::Trace))
+                    }
+                    Err(_) => return Err(eyre!("invalid directive `{}`", directive)),
+                },
+            }
+        }
+
+        Ok(directives)
+    }
+}
+
+/// parses the level of a `module=level` directive
+fn parse_level(directive: &str, level: &str) -> Result<LevelFilter> {
+    LevelFilter::from_str(level.trim()).map_err(|_| {
+        eyre!(
+            "invalid directive `{}`: unknown level `{}` (expected off, error, warn, info, debug \
+             or trace)",
+            directive,
+            level.trim()
+        )
+    })
+}
+
+/// returns whether `path` looks like a module path or a target (e.g. `my_crate::db`)
+fn is_module_path(path: &str) -> bool {
+    !path.is_empty()
+        && path.split("::").all(|segment| {
+            !segment.is_empty()
+                && segment.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+        })
+}
+
+/// returns whether `target` is the module `path`, or a module inside of it
+fn is_inside(target: &str, path: &str) -> bool {
+    match target.strip_prefix(path) {
+        Some(rest) => rest.is_empty() || rest.starts_with("::"),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        eyre::{set_hook, DefaultHandler},
+        log::Level,
+    };
+
+    fn setup_eyre() {
+        let _ = set_hook(Box::new(DefaultHandler::default_with));
+    }
+
+    #[test]
+    fn test_most_specific_module_wins() {
+        let mut filters = LevelFilters::new(LevelFilter::Warn);
+        filters.set_module("my_crate", LevelFilter::Info);
+        filters.set_module("my_crate::db", LevelFilter::Trace);
+        filters.set_module("my_crate::db::pool", LevelFilter::Off);
+
+        assert_eq!(filters.level_for("other"), LevelFilter::Warn);
+        assert_eq!(filters.level_for("my_crate"), LevelFilter::Info);
+        assert_eq!(filters.level_for("my_crate::http"), LevelFilter::Info);
+        assert_eq!(filters.level_for("my_crate::db::query"), LevelFilter::Trace);
+        assert_eq!(filters.level_for("my_crate::db::pool"), LevelFilter::Off);
+        // only whole path segments match
+        assert_eq!(filters.level_for("my_crate_extra"), LevelFilter::Warn);
+        assert_eq!(filters.level_for("my_crate::dbx"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_max_level() {
+        let mut filters = LevelFilters::new(LevelFilter::Info);
+        assert_eq!(filters.max_level(), LevelFilter::Info);
+
+        filters.set_module("noisy", LevelFilter::Trace);
+        assert_eq!(filters.max_level(), LevelFilter::Trace);
+
+        // setting a module twice replaces its level
+        filters.set_module("noisy::", LevelFilter::Error);
+        assert_eq!(filters.max_level(), LevelFilter::Info);
+        assert_eq!(filters.level_for("noisy"), LevelFilter::Error);
+
+        filters.remove_module("noisy");
+        assert_eq!(filters.level_for("noisy"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_enabled() {
+        let mut filters = LevelFilters::new(LevelFilter::Info);
+        filters.set_module("db", LevelFilter::Trace);
+
+        let metadata = |level, target| Metadata::builder().level(level).target(target).build();
+
+        assert!(filters.enabled(&metadata(Level::Info, "app")));
+        assert!(!filters.enabled(&metadata(Level::Debug, "app")));
+        assert!(filters.enabled(&metadata(Level::Trace, "db::pool")));
+    }
+
+    #[test]
+    fn test_parse_directives() {
+        let directives: Directives =
+            "info, my_crate::db=TRACE,hyper=warn,noisy,-**/generated/**,".parse().unwrap();
+
+        assert_eq!(directives.global, Some(LevelFilter::Info));
+        assert_eq!(
+            directives.modules,
+            [
+                ("my_crate::db".to_string(), LevelFilter::Trace),
+                ("hyper".to_string(), LevelFilter::Warn),
+                ("noisy".to_string(), LevelFilter::Trace),
+            ]
+        );
+        assert_eq!(directives.ignore_globs, ["**/generated/**"]);
+
+        assert_eq!("".parse::<Directives>().unwrap(), Directives::default());
+    }
+
+    #[test]
+    fn test_invalid_directives() {
+        setup_eyre();
+
+        let err = "info,db=loud".parse::<Directives>().unwrap_err();
+        assert!(err.to_string().contains("invalid directive `db=loud`: unknown level `loud`"));
+
+        let err = "=info".parse::<Directives>().unwrap_err();
+        assert!(err.to_string().contains("is not a module path"));
+
+        assert!("my crate".parse::<Directives>().is_err());
+        assert!("-".parse::<Directives>().is_err());
+        assert!("a::::b=info".parse::<Directives>().is_err());
+    }
+
+    #[test]
+    fn test_directives_override_code_levels() {
+        let mut levels = LevelFilters::new(LevelFilter::Warn);
+        levels.set_module("db", LevelFilter::Info);
+        levels.set_module("http", LevelFilter::Debug);
+        let mut filters = Filters {
+            levels,
+            ignore_globs: vec!["vendor/**".to_string()],
+        };
+
+        let directives: Directives = "error,db=trace,-gen/**".parse().unwrap();
+        directives.apply(&mut filters);
+
+        assert_eq!(filters.levels.level_for("app"), LevelFilter::Error);
+        assert_eq!(filters.levels.level_for("db"), LevelFilter::Trace);
+        assert_eq!(filters.levels.level_for("http"), LevelFilter::Debug);
+        assert_eq!(filters.ignore_globs, ["vendor/**", "gen/**"]);
+        assert!(filters.should_ignore("gen/api.rs"));
+    }
+}
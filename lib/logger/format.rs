@@ -0,0 +1,201 @@
+mod json;
+mod template;
+
+pub use {json::JsonFormat, template::TemplateFormat};
+
+use {
+    super::{Level, Record},
+    std::{fmt::Display, path::Path},
+};
+
+const RESET: &str = "\x1b[0m";
+
+/// 🧉 » log entry
+///
+/// a record about to be written, along with the details added by the logger.
+pub struct LogEntry<'a> {
+    /// the record, as received from the `log` macros
+    pub record: &'a Record<'a>,
+    /// the name of the logger, which can be empty
+    pub name: &'a str,
+    /// the time of the record, as returned by the time function of the logger (can be empty)
+    pub time: &'a str,
+    /// whether the sink the line is written to uses colors (see `ColorMode`). Formatters should
+    /// write the same layout without ANSI escapes when it doesn't.
+    pub colored: bool,
+}
+
+/// 🧉 » formatter
+///
+/// turns log entries into the lines written to a sink.
+pub trait Formatter: Send + Sync {
+    /// formats an entry into a line, without the trailing new line
+    fn format(&self, entry: &LogEntry) -> String;
+}
+
+/// 🧉 » default format
+///
+/// the format of the console logger: `name » time | LEVEL | ctx:line - message`, where `ctx` is
+/// the relative path of the file for local records, or the module path for records created by a
+/// dependency.
+///
+/// each part is styled with ANSI escapes when the sink uses colors.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultFormat;
+
+impl Formatter for DefaultFormat {
+    fn format(&self, entry: &LogEntry) -> String {
+        let styled_record = StyledRecord::from(entry);
+
+        let time = if styled_record.time.is_empty() {
+            " ".to_string()
+        } else {
+            format!(" {} ", styled_record.time)
+        };
+
+        let ctx = if entry.name.is_empty() {
+            "".to_string()
+        } else {
+            format!("{} »", entry.name)
+        };
+
+        let ctx_separator = if styled_record.line.is_empty() { "" } else { ":" };
+
+        format!(
+            "{}{}| {} | {}{}{} - {}",
+            ctx,
+            time,
+            styled_record.level,
+            styled_record.ctx,
+            ctx_separator,
+            styled_record.line,
+            styled_record.message
+        )
+    }
+}
+
+struct StyledRecord {
+    time: String,
+    level: String,
+    message: String,
+    /// relative path if local file or <crate_name>::<..>::<mod_name> if
+    /// it's a record created by a dependency
+    ctx: String,
+    line: String,
+}
+
+impl StyledRecord {
+    fn from(entry: &LogEntry) -> Self {
+        let record = entry.record;
+        let paint = |style: &str, text: &dyn Display| match entry.colored && !style.is_empty() {
+            true => format!("{}{}{}", style, text, RESET),
+            false => text.to_string(),
+        };
+
+        let ansi_style_level = match record.level() {
+            Level::Error => "\x1b[31m", // red
+            Level::Warn => "\x1b[33m",  // yellow
+            Level::Info => "\x1b[32m",  // green
+            Level::Debug => "\x1b[95m", // magenta
+            Level::Trace => "\x1b[34m", // blue
+        };
+
+        let time = match entry.time.is_empty() {
+            true => String::new(),
+            false => paint("\x1b[2m", &entry.time), // dim
+        };
+
+        let (ctx, is_local) = record_ctx(record);
+
+        let (file_ansi_color, line_ansi_color) = match is_local {
+            false => ("\x1b[95m", ""),          // magenta
+            true => ("\x1b[34m", "\x1b[34;1m"), // blue, bold blue
+        };
+
+        let line = match is_local {
+            false => String::from(""),
+            true => paint(line_ansi_color, &record.line().unwrap_or(0)),
+        };
+
+        Self {
+            level: paint(ansi_style_level, &format!("{:<5}", record.level())),
+            message: format!("{}", record.args()),
+            ctx: paint(file_ansi_color, &ctx),
+            line,
+            time,
+        }
+    }
+}
+
+/// **returns the context of a record, and whether it's a local record**
+///
+/// the context is the relative path of the file for local records, or the module path for records
+/// created by a dependency (whose files have absolute paths).
+pub(crate) fn record_ctx(record: &Record) -> (String, bool) {
+    let file = Path::new(record.file().unwrap_or("unknown"));
+
+    let ctx = match file.is_absolute() {
+        true => record.module_path().unwrap_or("unknown"),
+        false => file.to_str().unwrap_or("unknown"),
+    };
+
+    (ctx.replace('\\', "/"), !file.is_absolute())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_format() {
+        let record = Record::builder()
+            .level(Level::Warn)
+            .target("app")
+            .module_path(Some("app::db"))
+            .file(Some("src/db.rs"))
+            .line(Some(12))
+            .args(format_args!("slow query"))
+            .build();
+        let entry = LogEntry {
+            record: &record,
+            name: "my-app",
+            time: "2024-03-31 15:44:46",
+            colored: true,
+        };
+
+        assert_eq!(
+            DefaultFormat.format(&entry),
+            "my-app » \x1b[2m2024-03-31 15:44:46\x1b[0m | \x1b[33mWARN \x1b[0m | \
+             \x1b[34msrc/db.rs\x1b[0m:\x1b[34;1m12\x1b[0m - slow query"
+        );
+
+        // the same layout, without colors
+        let entry = LogEntry {
+            colored: false,
+            ..entry
+        };
+        assert_eq!(
+            DefaultFormat.format(&entry),
+            "my-app » 2024-03-31 15:44:46 | WARN  | src/db.rs:12 - slow query"
+        );
+
+        // records of dependencies show their module path, without the line
+        let record = Record::builder()
+            .level(Level::Info)
+            .module_path(Some("hyper::client"))
+            .file(Some("/home/me/.cargo/registry/hyper/src/client.rs"))
+            .args(format_args!("connected"))
+            .build();
+        let entry = LogEntry {
+            record: &record,
+            name: "",
+            time: "",
+            colored: false,
+        };
+
+        assert_eq!(
+            DefaultFormat.format(&entry),
+            " | INFO  | hyper::client - connected"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use {
+    super::{Formatter, LogEntry},
+    log::kv::{self, Key, Value, VisitSource},
+    std::fmt::Write,
+};
+
+/// 🧉 » json format
+///
+/// formats each record as a json object in a single line, e.g.:
+///
+/// ```json
+/// {"timestamp":"2024-03-31 15:44:46","level":"INFO","name":"my-app","target":"my_app::db",
+/// "module_path":"my_app::db","file":"src/db.rs","line":12,"message":"connected",
+/// "fields":{"pool":4}}
+/// ```
+///
+/// the key-value pairs of the record (`info!(pool = 4; "connected")`) are added to a `fields`
+/// object after the message, so they can't be mistaken for the fields of the record itself (a
+/// pair named `level` doesn't replace the level of the record). It's left out when the record
+/// has no key-value pairs. Numbers and booleans are kept as such, any other value is written as
+/// a string. Missing details (like the file of a record) are `null`, and so is the timestamp when
+/// the time function of the logger returns an empty string.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonFormat;
+
+impl JsonFormat {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Formatter for JsonFormat {
+    fn format(&self, entry: &LogEntry) -> String {
+        let record = entry.record;
+        let mut json = String::from("{");
+
+        let timestamp = Some(entry.time).filter(|time| !time.is_empty());
+        push_field(&mut json, "timestamp", &str_or_null(timestamp));
+        push_field(&mut json, "level", &quote(record.level().as_str()));
+        push_field(&mut json, "name", &quote(entry.name));
+        push_field(&mut json, "target", &quote(record.target()));
+        push_field(&mut json, "module_path", &str_or_null(record.module_path()));
+        push_field(&mut json, "file", &str_or_null(record.file()));
+        push_field(
+            &mut json,
+            "line",
+            &record.line().map_or_else(|| "null".to_string(), |line| line.to_string()),
+        );
+        push_field(&mut json, "message", &quote(&record.args().to_string()));
+
+        let mut fields = KvFields(String::from("{"));
+        let _ = record.key_values().visit(&mut fields);
+        if fields.0.len() > 1 {
+            fields.0.push('}');
+            push_field(&mut json, "fields", &fields.0);
+        }
+
+        json.push('}');
+        json
+    }
+}
+
+/// collects the key-value pairs of a record into an unfinished json object
+struct KvFields(String);
+
+impl<'kvs> VisitSource<'kvs> for KvFields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        push_field(&mut self.0, key.as_str(), &json_value(&value));
+        Ok(())
+    }
+}
+
+/// appends a `"key":value` field to an unfinished json object
+fn push_field(json: &mut String, key: &str, value: &str) {
+    if !json.ends_with('{') {
+        json.push(',');
+    }
+
+    json.push_str(&quote(key));
+    json.push(':');
+    json.push_str(value);
+}
+
+/// returns the json representation of a key-value value
+fn json_value(value: &Value) -> String {
+    if let Some(value) = value.to_bool() {
+        return value.to_string();
+    }
+
+    if let Some(value) = value.to_i64() {
+        return value.to_string();
+    }
+
+    if let Some(value) = value.to_u64() {
+        return value.to_string();
+    }
+
+    // json has no representation for NaN or infinite numbers
+    if let Some(value) = value.to_f64().filter(|value| value.is_finite()) {
+        return value.to_string();
+    }
+
+    quote(&value.to_string())
+}
+
+fn str_or_null(value: Option<&str>) -> String {
+    value.map_or_else(|| "null".to_string(), quote)
+}
+
+/// returns a json string with the given content, escaping it as needed
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{8}' => quoted.push_str("\\b"),
+            '\u{c}' => quoted.push_str("\\f"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::logger::{Level, Record},
+    };
+
+    #[test]
+    fn test_json_format() {
+        let kvs: &[(&str, Value)] = &[
+            ("pool", Value::from(4)),
+            ("ratio", Value::from(0.5)),
+            ("cached", Value::from(true)),
+            ("user", Value::from("bob \"the builder\"")),
+        ];
+        let record = Record::builder()
+            .level(Level::Info)
+            .target("my_app::db")
+            .module_path(Some("my_app::db"))
+            .file(Some("src/db.rs"))
+            .line(Some(12))
+            .key_values(&kvs)
+            .args(format_args!("connected"))
+            .build();
+        let entry = LogEntry {
+            record: &record,
+            name: "my-app",
+            time: "2024-03-31 15:44:46",
+            colored: false,
+        };
+
+        assert_eq!(
+            JsonFormat.format(&entry),
+            "{\"timestamp\":\"2024-03-31 15:44:46\",\"level\":\"INFO\",\"name\":\"my-app\",\
+             \"target\":\"my_app::db\",\"module_path\":\"my_app::db\",\"file\":\"src/db.rs\",\
+             \"line\":12,\"message\":\"connected\",\"fields\":{\"pool\":4,\"ratio\":0.5,\
+             \"cached\":true,\"user\":\"bob \\\"the builder\\\"\"}}"
+        );
+    }
+
+    #[test]
+    fn test_pairs_dont_replace_the_record_fields() {
+        let kvs: &[(&str, Value)] =
+            &[("level", Value::from("custom")), ("message", Value::from(1))];
+        let record = Record::builder()
+            .level(Level::Error)
+            .key_values(&kvs)
+            .args(format_args!("failed"))
+            .build();
+        let entry = LogEntry {
+            record: &record,
+            name: "my-app",
+            time: "",
+            colored: false,
+        };
+
+        let json = JsonFormat.format(&entry);
+        assert!(json.contains("\"level\":\"ERROR\""));
+        assert!(json.contains("\"message\":\"failed\""));
+        assert!(json.ends_with("\"fields\":{\"level\":\"custom\",\"message\":1}}"));
+    }
+
+    #[test]
+    fn test_missing_details_are_null() {
+        let record = Record::builder().level(Level::Warn).args(format_args!("no details")).build();
+        let entry = LogEntry {
+            record: &record,
+            name: "",
+            time: "",
+            colored: false,
+        };
+
+        assert_eq!(
+            JsonFormat.format(&entry),
+            "{\"timestamp\":null,\"level\":\"WARN\",\"name\":\"\",\"target\":\"\",\
+             \"module_path\":null,\"file\":null,\"line\":null,\"message\":\"no details\"}"
+        );
+    }
+
+    #[test]
+    fn test_quote_escapes_strings() {
+        assert_eq!(quote("plain"), "\"plain\"");
+        assert_eq!(
+            quote("a \"quoted\" \\path\\\nnew\tline\r"),
+            "\"a \\\"quoted\\\" \\\\path\\\\\\nnew\\tline\\r\""
+        );
+        assert_eq!(
+            quote("\u{1}\u{8}\u{c}\u{1b}[0m"),
+            "\"\\u0001\\b\\f\\u001b[0m\""
+        );
+        assert_eq!(quote("mate 🧉 » ñ"), "\"mate 🧉 » ñ\"");
+    }
+}
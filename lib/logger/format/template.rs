@@ -0,0 +1,374 @@
+use {
+    super::{Formatter, LogEntry},
+    crate::cli::stylize::{instructions, stylize},
+    eyre::{eyre, Result},
+    log::kv::{self, Key, Value, VisitSource},
+};
+
+/// 🧉 » template format
+///
+/// formats records after a template with `{placeholder}`s, e.g.
+/// `{time} [{level:<5:bold}] {target}: {message} {kv}`.
+///
+/// **placeholders**
+///
+/// - `{time}`: the time of the record, as returned by the time function of the logger.
+/// - `{level}`: the level of the record (`INFO`, `WARN`, ...).
+/// - `{name}`: the name of the logger.
+/// - `{target}`: the target of the record (usually its module path).
+/// - `{file}` and `{line}`: where the record was created (empty when unknown).
+/// - `{message}`: the message of the record.
+/// - `{kv}`: the key-value pairs of the record, as `key=value` separated by spaces.
+///
+/// **modifiers**
+///
+/// placeholders can be followed by a `:spec`, a `:style`, or both (`{name:spec:style}`):
+///
+/// - the spec is `[align][width][.max]`: the value is truncated to `max` chars, and then padded to
+///   `width` chars, aligned to the left (`<`, the default), right (`>`) or center (`^`). E.g.
+///   `{level:<5}`, `{line:>4}` or `{target:.20}`.
+/// - the style is a `cli.stylize` instruction, like `red+bold` or `black on yellow`. Attributes
+///   can be given without the leading `+`, e.g. `{level:bold}` or `{time:dim|italic}`. Styles are
+///   only applied when the sink uses colors.
+///
+/// `{{` and `}}` write literal braces.
+#[derive(Clone, Debug)]
+pub struct TemplateFormat {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Part {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Placeholder {
+    field: Field,
+    align: Align,
+    width: Option<usize>,
+    max_width: Option<usize>,
+    style: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Time,
+    Level,
+    Name,
+    Target,
+    File,
+    Line,
+    Message,
+    Kv,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl TemplateFormat {
+    /// **🧉 » parses a template**
+    ///
+    /// fails if the template has unknown placeholders, invalid specs or styles, or unbalanced
+    /// braces.
+    pub fn new<S: AsRef<str>>(template: S) -> Result<Self> {
+        let template = template.as_ref();
+        let parts =
+            parse(template).map_err(|err| eyre!("invalid log format `{}`: {}", template, err))?;
+
+        Ok(Self { parts })
+    }
+}
+
+impl Formatter for TemplateFormat {
+    fn format(&self, entry: &LogEntry) -> String {
+        let mut line = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => line.push_str(text),
+                Part::Placeholder(placeholder) => line.push_str(&placeholder.render(entry)),
+            }
+        }
+
+        line
+    }
+}
+
+impl Placeholder {
+    fn render(&self, entry: &LogEntry) -> String {
+        let record = entry.record;
+
+        let value = match self.field {
+            Field::Time => entry.time.to_string(),
+            Field::Level => record.level().to_string(),
+            Field::Name => entry.name.to_string(),
+            Field::Target => record.target().to_string(),
+            Field::File => record.file().unwrap_or("").to_string(),
+            Field::Line => record.line().map_or_else(String::new, |line| line.to_string()),
+            Field::Message => record.args().to_string(),
+            Field::Kv => {
+                let mut pairs = KvPairs(vec![]);
+                let _ = record.key_values().visit(&mut pairs);
+                pairs.0.join(" ")
+            }
+        };
+
+        let value = match self.max_width {
+            Some(max) => value.chars().take(max).collect(),
+            None => value,
+        };
+
+        let padding = self.width.unwrap_or(0).saturating_sub(value.chars().count());
+        let value = match self.align {
+            Align::Left => format!("{}{}", value, " ".repeat(padding)),
+            Align::Right => format!("{}{}", " ".repeat(padding), value),
+            Align::Center => format!(
+                "{}{}{}",
+                " ".repeat(padding / 2),
+                value,
+                " ".repeat(padding - padding / 2)
+            ),
+        };
+
+        match &self.style {
+            Some(style) if entry.colored && !value.is_empty() => stylize(value, style),
+            _ => value,
+        }
+    }
+}
+
+/// collects the key-value pairs of a record as `key=value` strings
+struct KvPairs(Vec<String>);
+
+impl<'kvs> VisitSource<'kvs> for KvPairs {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        self.0.push(format!("{}={}", key, value));
+        Ok(())
+    }
+}
+
+/// splits a template into literals and placeholders
+fn parse(template: &str) -> Result<Vec<Part>> {
+    let mut parts = vec![];
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some('{') | None => return Err(eyre!("unclosed `{{`")),
+                        Some(c) => placeholder.push(c),
+                    }
+                }
+
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Placeholder(parse_placeholder(&placeholder)?));
+            }
+            '}' => return Err(eyre!("unmatched `}}` (use `}}}}` for a literal brace)")),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+
+    Ok(parts)
+}
+
+/// parses the content of a placeholder: `name[:spec][:style]`
+fn parse_placeholder(placeholder: &str) -> Result<Placeholder> {
+    let (name, modifiers) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+
+    let field = match name.trim() {
+        "time" => Field::Time,
+        "level" => Field::Level,
+        "name" => Field::Name,
+        "target" => Field::Target,
+        "file" => Field::File,
+        "line" => Field::Line,
+        "message" => Field::Message,
+        "kv" => Field::Kv,
+        name => return Err(eyre!("unknown placeholder `{{{}}}`", name)),
+    };
+
+    let mut placeholder = Placeholder {
+        field,
+        align: Align::Left,
+        width: None,
+        max_width: None,
+        style: None,
+    };
+
+    // a style never starts like a spec: with an alignment, a digit or a dot
+    let (spec, style) = match modifiers
+        .starts_with(|c: char| matches!(c, '<' | '>' | '^' | '.') || c.is_ascii_digit())
+    {
+        true => modifiers.split_once(':').unwrap_or((modifiers, "")),
+        false => ("", modifiers),
+    };
+
+    parse_spec(spec, &mut placeholder)?;
+
+    if !style.is_empty() {
+        placeholder.style = Some(parse_style(style)?);
+    }
+
+    Ok(placeholder)
+}
+
+/// validates a `cli.stylize` instruction, accepting bare attributes (`bold`) as `+bold`
+fn parse_style(style: &str) -> Result<String> {
+    match instructions::parse(style) {
+        Ok(_) => Ok(style.to_string()),
+        Err(err) => {
+            let attributes = format!("+{}", style);
+            match instructions::parse(&attributes) {
+                Ok(_) => Ok(attributes),
+                Err(_) => Err(eyre!("invalid style `{}`: {}", style, err)),
+            }
+        }
+    }
+}
+
+/// parses an `[align][width][.max]` spec into the placeholder
+fn parse_spec(spec: &str, placeholder: &mut Placeholder) -> Result<()> {
+    let invalid = || eyre!("invalid spec `{}`, expected `[<|>|^][width][.max]`", spec);
+
+    let rest = match spec.chars().next() {
+        Some('<') => &spec[1..],
+        Some('>') => {
+            placeholder.align = Align::Right;
+            &spec[1..]
+        }
+        Some('^') => {
+            placeholder.align = Align::Center;
+            &spec[1..]
+        }
+        _ => spec,
+    };
+
+    let (width, max_width) = match rest.split_once('.') {
+        Some((width, max_width)) => (width, Some(max_width)),
+        None => (rest, None),
+    };
+
+    if !width.is_empty() {
+        placeholder.width = Some(width.parse().map_err(|_| invalid())?);
+    }
+
+    if let Some(max_width) = max_width {
+        placeholder.max_width = Some(max_width.parse().map_err(|_| invalid())?);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::logger::{Level, Record},
+        eyre::{set_hook, DefaultHandler},
+    };
+
+    fn setup_eyre() {
+        let _ = set_hook(Box::new(DefaultHandler::default_with));
+    }
+
+    fn format(template: &str) -> String {
+        format_with_colors(template, true)
+    }
+
+    fn format_with_colors(template: &str, colored: bool) -> String {
+        let kvs: &[(&str, &str)] = &[("user", "bob"), ("db", "main")];
+        let record = Record::builder()
+            .level(Level::Info)
+            .target("my_app::db")
+            .file(Some("src/db.rs"))
+            .line(Some(12))
+            .key_values(&kvs)
+            .args(format_args!("connected"))
+            .build();
+        let entry = LogEntry {
+            record: &record,
+            name: "my-app",
+            time: "15:44:46",
+            colored,
+        };
+
+        TemplateFormat::new(template).unwrap().format(&entry)
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(
+            format("{name} {time} {level} {target} {file}:{line} - {message} {kv}"),
+            "my-app 15:44:46 INFO my_app::db src/db.rs:12 - connected user=bob db=main"
+        );
+        assert_eq!(format("{{{level}}} {{}}"), "{INFO} {}");
+    }
+
+    #[test]
+    fn test_padding_and_width() {
+        assert_eq!(format("[{level:<6}]"), "[INFO  ]");
+        assert_eq!(format("[{level:>6}]"), "[  INFO]");
+        assert_eq!(format("[{level:^7}]"), "[ INFO  ]");
+        assert_eq!(format("[{line:4}]"), "[12  ]");
+        assert_eq!(format("[{target:.6}]"), "[my_app]");
+        assert_eq!(format("[{target:>8.2}]"), "[      my]");
+    }
+
+    #[test]
+    fn test_styles() {
+        assert_eq!(format("{level:bold}"), stylize("INFO", "+bold"));
+        assert_eq!(format("{level:dim|italic}"), stylize("INFO", "+dim|italic"));
+        assert_eq!(format("{level:<5:red+bold}"), stylize("INFO ", "red+bold"));
+        assert_eq!(
+            format("{name:black on yellow}"),
+            stylize("my-app", "black on yellow")
+        );
+
+        // without colors, the styles are ignored
+        assert_eq!(
+            format_with_colors("[{level:<5:red+bold}]", false),
+            "[INFO ]"
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        setup_eyre();
+
+        let err = TemplateFormat::new("{lvl}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid log format `{lvl}`: unknown placeholder `{lvl}`"
+        );
+
+        assert!(TemplateFormat::new("{level").is_err());
+        assert!(TemplateFormat::new("level}").is_err());
+        assert!(TemplateFormat::new("{level:<x}").is_err());
+        assert!(TemplateFormat::new("{level:<5:not-a-color}").is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use {
+    super::{
+        filter::{Directives, Filters},
+        LevelFilter,
+    },
+    eyre::Result,
+    std::{
+        fmt,
+        sync::{Arc, RwLock, RwLockReadGuard},
+    },
+};
+
+/// 🧉 » logger handle
+///
+/// returned when a logger is installed, to change its levels and ignored contexts while the
+/// program runs, e.g. from an admin command or when a config file is reloaded.
+///
+/// every update is applied at once, under a lock: a record is checked either against the filters
+/// before the update, or against the ones after it, never against a mix. The handle can be cloned
+/// and shared between threads.
+///
+/// **Example**
+///
+/// ```rust,no_run
+/// use lool::logger::{ConsoleLogger, Level, LevelFilter};
+///
+/// # fn main() -> eyre::Result<()> {
+/// let handle = ConsoleLogger::builder().with_level(Level::Info).install()?;
+///
+/// // later on, e.g. when debugging the database
+/// handle.set_module_level("my_crate::db", LevelFilter::Trace);
+/// // or several changes at once, with the syntax of `SetupBuilder::with_env_filter`
+/// handle.apply_directives("warn,my_crate::db=debug,-**/generated/**")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LoggerHandle {
+    filters: Arc<RwLock<Filters>>,
+    /// the most verbose level of any sink, which the filters can't go beyond
+    sinks_level: LevelFilter,
+}
+
+impl LoggerHandle {
+    pub(crate) fn new(filters: Arc<RwLock<Filters>>, sinks_level: LevelFilter) -> Self {
+        Self {
+            filters,
+            sinks_level,
+        }
+    }
+
+    /// **🧉 » `set_level`**
+    ///
+    /// Sets the max log level, used for the records of modules without a level of their own
+    pub fn set_level(&self, level: LevelFilter) {
+        self.update(|filters| filters.levels.set_global(level));
+    }
+
+    /// **🧉 » `set_module_level`**
+    ///
+    /// Sets the max log level of a module (or target), and of every module inside of it,
+    /// replacing the one it had, if any
+    pub fn set_module_level<S: AsRef<str>>(&self, module: S, level: LevelFilter) {
+        self.update(|filters| filters.levels.set_module(module, level));
+    }
+
+    /// **🧉 » `remove_module_level`**
+    ///
+    /// Removes the max log level of a module, which then logs like its parent module (or with
+    /// the global level)
+    pub fn remove_module_level<S: AsRef<str>>(&self, module: S) {
+        self.update(|filters| filters.levels.remove_module(module));
+    }
+
+    /// **🧉 » `ignore`**
+    ///
+    /// Adds a context/context glob to the ignored list
+    pub fn ignore<S: AsRef<str>>(&self, glob: S) {
+        self.update(|filters| filters.ignore_globs.push(glob.as_ref().to_string()));
+    }
+
+    /// **🧉 » `ignore_all`**
+    ///
+    /// Replaces the ignored contexts/context globs. An empty list stops ignoring any context
+    pub fn ignore_all(&self, ctx_ignored_globs: Vec<String>) {
+        self.update(|filters| filters.ignore_globs = ctx_ignored_globs);
+    }
+
+    /// **🧉 » `apply_directives`**
+    ///
+    /// Applies comma-separated filter directives, with the same syntax as
+    /// `SetupBuilder::with_env_filter` (e.g. `info,my_crate::db=trace,-**/generated/**`), all
+    /// of them in a single update.
+    ///
+    /// Fails without changing anything if a directive is invalid.
+    pub fn apply_directives<S: AsRef<str>>(&self, directives: S) -> Result<()> {
+        let directives: Directives = directives.as_ref().parse()?;
+        self.update(|filters| directives.apply(filters));
+        Ok(())
+    }
+
+    /// returns the level that applies to a target (module path)
+    pub fn level_for<S: AsRef<str>>(&self, target: S) -> LevelFilter {
+        self.read().levels.level_for(target.as_ref())
+    }
+
+    /// returns the ignored contexts/context globs
+    pub fn ignored(&self) -> Vec<String> {
+        self.read().ignore_globs.clone()
+    }
+
+    /// applies a change to the filters, and updates `log::max_level` so the `log!` macros skip
+    /// the records no module logs anymore (or stop skipping the ones that some module does)
+    fn update<F: FnOnce(&mut Filters)>(&self, change: F) {
+        let mut filters = self.filters.write().unwrap_or_else(|err| err.into_inner());
+        change(&mut filters);
+
+        // set while holding the lock, so concurrent updates can't leave a stale max level
+        log::set_max_level(filters.levels.max_level().min(self.sinks_level));
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Filters> {
+        self.filters.read().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl fmt::Debug for LoggerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoggerHandle").field("filters", &*self.read()).finish()
+    }
+}
@@ -1,98 +1,60 @@
+mod background;
+mod color;
 pub mod datetime;
-use glob_match::glob_match;
-pub use log::{
-    debug, error, info, set_max_level, trace, warn, Level, LevelFilter, Log, Metadata, Record,
+mod filter;
+pub mod format;
+mod handle;
+pub mod sink;
+
+pub use {
+    background::{shutdown, AsyncGuard, Overflow},
+    color::ColorMode,
+    handle::LoggerHandle,
+    log::{
+        debug, error, info, set_max_level, trace, warn, Level, LevelFilter, Log, Metadata, Record,
+    },
+};
+use {
+    background::{Background, Batch},
+    filter::{Directives, Filters, LevelFilters},
+    format::{record_ctx, DefaultFormat, Formatter, LogEntry, TemplateFormat},
+    sink::{SinkConfig, StdoutSink},
 };
 
 use {
     eyre::{eyre, Result},
-    std::path::Path,
+    std::{
+        env,
+        sync::{Arc, RwLock, RwLockReadGuard},
+    },
 };
 
-const RESET: &str = "\x1b[0m";
-
-struct StyledRecord {
-    time: String,
-    level: String,
-    message: String,
-    /// relative path if local file or <crate_name>::<..>::<mod_name> if
-    /// it's a record created by a dependency
-    ctx: String,
-    raw_ctx: String,
-    line: String,
-}
-
-impl StyledRecord {
-    fn from(record: &Record, get_time: fn() -> String) -> Self {
-        let ansi_style_level = match record.level() {
-            Level::Error => "\x1b[31m", // red
-            Level::Warn => "\x1b[33m",  // yellow
-            Level::Info => "\x1b[32m",  // green
-            Level::Debug => "\x1b[95m", // magenta
-            Level::Trace => "\x1b[34m", // blue
-        };
-
-        let mut time = get_time();
-
-        if !time.is_empty() {
-            time = format!("\x1b[2m{}\x1b[0m", time); // dim
-        }
-
-        let file = Path::new(record.file().unwrap_or("unknown"));
-
-        let (ctx, file_ansi_color, line_ansi_color) = match file.is_absolute() {
-            true => (
-                record.module_path().unwrap_or("unknown"),
-                "\x1b[95m", // blue
-                "",         // bold blue
-            ),
-            false => (
-                file.to_str().unwrap_or("unknown"),
-                "\x1b[34m",   // blue
-                "\x1b[34;1m", // nada
-            ),
-        };
-
-        let line = match file.is_absolute() {
-            true => String::from(""),
-            false => format!("{}{}{}", line_ansi_color, record.line().unwrap_or(0), RESET),
-        };
-
-        Self {
-            level: format!("{}{:<5}{}", ansi_style_level, record.level(), RESET),
-            message: format!("{}", record.args()),
-            ctx: format!("{}{}{}", file_ansi_color, ctx.replace('\\', "/"), RESET),
-            raw_ctx: ctx.replace('\\', "/"),
-            line,
-            time,
-        }
-    }
-}
+type TimeFn = Box<dyn Fn() -> String + Send + Sync>;
 
 /// 🧉 » simple console logger implementation
 /// --
 ///
 /// this is a simple logger implementation (mounted on top of the `log` crate) that logs to stdout
-/// with ANSI colors and datetime stamps.
+/// with ANSI colors (when stdout is a terminal) and datetime stamps. Records can be written to
+/// other sinks instead (see `SetupBuilder::with_sink`).
 pub struct ConsoleLogger {
     name: String,
-    time_fn: fn() -> String,
-    ctx_ignore_globs: Vec<String>,
+    time_fn: TimeFn,
+    /// the level filters and ignored contexts, shared with the `LoggerHandle`s
+    filters: Arc<RwLock<Filters>>,
+    sinks: Arc<Vec<SinkConfig>>,
+    /// used by the sinks without a formatter of their own
+    format: Box<dyn Formatter>,
+    /// the writer of the lines, when the logger is asynchronous
+    background: Option<Arc<Background>>,
 }
 
 impl ConsoleLogger {
     /// **🧉 » sets up the logger with the default settings**
     ///
     /// sets the logger to use the `datetime::utc_current_time` function to get the current time.
-    pub fn default_setup<S: AsRef<str>>(max_level: Level, context: S) -> Result<()> {
-        let logger = Box::new(ConsoleLogger {
-            name: context.as_ref().to_string(),
-            time_fn: datetime::utc_current_time,
-            ctx_ignore_globs: vec![],
-        });
-        log::set_logger(Box::leak(logger) as &'static dyn Log)
-            .map(|()| log::set_max_level(max_level.to_level_filter()))
-            .map_err(move |err| eyre!("failed to set logger: {}", err))
+    pub fn default_setup<S: AsRef<str>>(max_level: Level, context: S) -> Result<LoggerHandle> {
+        Self::setup(max_level, context, datetime::utc_current_time)
     }
 
     /// **🧉 » sets up the logger with the given settings**
@@ -105,15 +67,8 @@ impl ConsoleLogger {
         max_level: Level,
         context: S,
         time_fn: fn() -> String,
-    ) -> Result<()> {
-        let logger = Box::new(ConsoleLogger {
-            name: context.as_ref().to_string(),
-            time_fn,
-            ctx_ignore_globs: vec![],
-        });
-        log::set_logger(Box::leak(logger) as &'static dyn Log)
-            .map(|()| log::set_max_level(max_level.to_level_filter()))
-            .map_err(move |err| eyre!("failed to set logger: {}", err))
+    ) -> Result<LoggerHandle> {
+        Self::builder().with_name(context).with_level(max_level).with_time_fn(time_fn).install()
     }
 
     /// **🧉 » creates a new setup builder**
@@ -125,66 +80,136 @@ impl ConsoleLogger {
         SetupBuilder::default()
     }
 
-    /// returns true if the context should be ignored
-    fn should_ignore(&self, ctx: &str) -> bool {
-        for glob in &self.ctx_ignore_globs {
-            if glob_match(glob, ctx) {
-                return true;
+    /// returns the most verbose level written to any sink, to be used as `log::max_level`
+    fn max_level(&self) -> LevelFilter {
+        self.read_filters().levels.max_level().min(self.sinks_level())
+    }
+
+    /// returns the most verbose level accepted by any sink
+    fn sinks_level(&self) -> LevelFilter {
+        self.sinks.iter().map(|sink| sink.level).max().unwrap_or(LevelFilter::Off)
+    }
+
+    /// returns a handle to change the filters of the logger
+    pub(crate) fn handle(&self) -> LoggerHandle {
+        LoggerHandle::new(Arc::clone(&self.filters), self.sinks_level())
+    }
+
+    fn read_filters(&self) -> RwLockReadGuard<'_, Filters> {
+        // logging goes on even if a thread panicked while updating the filters
+        self.filters.read().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// formats a record for every sink that accepts its level
+    fn lines(&self, record: &Record, time: &str) -> Vec<(usize, String)> {
+        let mut lines = vec![];
+
+        for (index, sink) in self.sinks.iter().enumerate() {
+            if record.level() <= sink.level {
+                let entry = LogEntry {
+                    record,
+                    name: &self.name,
+                    time,
+                    colored: sink.colored,
+                };
+
+                let formatter = sink.formatter.as_ref().unwrap_or(&self.format);
+                lines.push((index, formatter.format(&entry)));
             }
         }
-        false
+
+        lines
+    }
+
+    /// formats the warning about the records dropped by the background writer
+    fn dropped_lines(&self, dropped: u64, time: &str) -> Vec<(usize, String)> {
+        self.lines(
+            &Record::builder()
+                .level(Level::Warn)
+                .target(module_path!())
+                .module_path(Some(module_path!()))
+                .file(Some(file!()))
+                .line(Some(line!()))
+                .args(format_args!(
+                    "{} log records were dropped because the log queue was full",
+                    dropped
+                ))
+                .build(),
+            time,
+        )
+    }
+
+    fn write(&self, lines: Vec<(usize, String)>) {
+        for (index, line) in lines {
+            self.sinks[index].sink.write_line(&line);
+        }
     }
 }
 
 impl Log for ConsoleLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Info
+        metadata.level() <= self.sinks_level() && self.read_filters().levels.enabled(metadata)
     }
 
     fn log(&self, record: &Record) {
-        let styled_record = StyledRecord::from(record, self.time_fn);
+        // a single read of the filters, so a concurrent update applies to the whole check
+        {
+            let filters = self.read_filters();
 
-        // ignore the record if the context is in the ignore list
-        if self.should_ignore(&styled_record.raw_ctx) {
-            return;
+            // the `log!` macros only check the global max level, which is the most verbose level
+            // of any module
+            if record.level() > self.sinks_level() || !filters.levels.enabled(record.metadata()) {
+                return;
+            }
+
+            // ignore the record if the context is in the ignore list
+            if filters.should_ignore(&record_ctx(record).0) {
+                return;
+            }
         }
 
-        let time = if styled_record.time.is_empty() {
-            " ".to_string()
-        } else {
-            format!(" {} ", styled_record.time)
-        };
+        let time = (self.time_fn)();
 
-        let ctx = if self.name.is_empty() {
-            "".to_string()
-        } else {
-            format!("{} »", self.name)
+        let Some(background) = &self.background else {
+            return self.write(self.lines(record, &time));
         };
 
-        let ctx_separator = if styled_record.line.is_empty() { "" } else { ":" };
+        // the warning about dropped records goes before the first record that gets queued
+        let reported = background.take_dropped();
+        let mut lines = match reported {
+            0 => vec![],
+            dropped => self.dropped_lines(dropped, &time),
+        };
+        lines.extend(self.lines(record, &time));
 
-        // print to stdout
-        println!(
-            "{}{}| {} | {}{}{} - {}",
-            ctx,
-            time,
-            styled_record.level,
-            styled_record.ctx,
-            ctx_separator,
-            styled_record.line,
-            styled_record.message
-        );
+        if let Err(batch) = background.send(Batch { lines, reported }) {
+            self.write(batch.lines);
+        }
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        if let Some(background) = &self.background {
+            background.wait_idle();
+
+            let dropped = background.take_dropped();
+            if dropped > 0 {
+                self.write(self.dropped_lines(dropped, &(self.time_fn)()));
+            }
+        }
+
+        for sink in self.sinks.iter() {
+            sink.sink.flush();
+        }
+    }
 }
 
 /// **🧉 » setup builder**
 ///
 /// this struct is used to set up the logger with more flexibility.
 ///
-/// It allows for setting the logger name, the time function, the ignored contexts/globs, and the
-/// max level.
+/// It allows for setting the logger name, the time function, the ignored contexts/globs, the
+/// max level, globally and per module (also from an environment variable, see `with_env_filter`),
+/// and the sinks the records are written to.
 ///
 /// The `install` method is used to build and install the logger. Should be called at the end of the
 /// builder chain.
@@ -193,32 +218,45 @@ impl Log for ConsoleLogger {
 ///
 /// ```rust
 /// use eyre::Result;
-/// use lool::logger::{ConsoleLogger, Level, info};
+/// use lool::logger::{ConsoleLogger, Level, LevelFilter, info, sink::StderrSink};
 ///
 /// fn main() -> Result<()> {
 ///   ConsoleLogger::builder()
 ///     .with_name("test")
-///     .with_level(Level::Trace)
+///     .with_level(Level::Info)
+///     .with_module_level("my_crate::db", LevelFilter::Trace) // more verbose for a module
 ///     .ignore("crate::module::function") // ignore a specific context
+///     .with_sink(StderrSink) // log to stderr instead of stdout
 ///     .install()?;
 ///   
 ///   info!("log line");
+///   Ok(())
 /// }
 /// ```
 pub struct SetupBuilder {
     name: Option<String>,
-    time_fn: Option<fn() -> String>,
+    time_fn: Option<TimeFn>,
     ctx_ignore_globs: Option<Vec<String>>,
     max_level: Option<Level>,
+    module_levels: Vec<(String, LevelFilter)>,
+    env_filter: Option<String>,
+    sinks: Vec<SinkConfig>,
+    format: Option<String>,
+    color: ColorMode,
 }
 
 impl Default for SetupBuilder {
     fn default() -> Self {
         Self {
             name: Some("".to_string()),
-            time_fn: Some(datetime::utc_current_time),
+            time_fn: Some(Box::new(datetime::utc_current_time)),
             ctx_ignore_globs: Some(vec![]),
             max_level: Some(Level::Info),
+            module_levels: vec![],
+            env_filter: None,
+            sinks: vec![],
+            format: None,
+            color: ColorMode::Auto,
         }
     }
 }
@@ -234,17 +272,109 @@ impl SetupBuilder {
 
     /// **🧉 » `with_level`**
     ///
-    /// Sets the max log level
+    /// Sets the max log level, used for the records of modules without a level of their own
     pub fn with_level(mut self, level: Level) -> Self {
         self.max_level = Some(level);
         self
     }
 
+    /// **🧉 » `with_module_level`**
+    ///
+    /// Sets the max log level of a module (or target), and of every module inside of it.
+    ///
+    /// When several modules match a record, the most specific one decides, e.g. with `my_crate` at
+    /// `Info` and `my_crate::db` at `Trace`, trace records from `my_crate::db::pool` are logged.
+    /// Use `LevelFilter::Off` to silence a module.
+    pub fn with_module_level<S: AsRef<str>>(mut self, module: S, level: LevelFilter) -> Self {
+        self.module_levels.push((module.as_ref().to_string(), level));
+        self
+    }
+
+    /// **🧉 » `with_env_filter`**
+    ///
+    /// Reads filter directives from the given environment variable (e.g. `LOOL_LOG`) when the
+    /// logger is installed. Directives are comma-separated, and can be:
+    ///
+    /// - `level`: the global max level, e.g. `info`.
+    /// - `module=level`: the max level of a module, e.g. `my_crate::db=trace`.
+    /// - `module`: logs everything from a module, same as `module=trace`.
+    /// - `-glob`: ignores a context glob, same as `ignore`, e.g. `-**/generated/**`.
+    ///
+    /// The directives are applied on top of the levels set in code, so the environment wins when
+    /// both set the level of the same module. An unset variable is ignored, while an invalid
+    /// directive makes `install` fail.
+    pub fn with_env_filter<S: AsRef<str>>(mut self, var: S) -> Self {
+        self.env_filter = Some(var.as_ref().to_string());
+        self
+    }
+
     /// **🧉 » `with_time_fn`**
     ///
-    /// Sets the time function that will be used to get the current time
-    pub fn with_time_fn(mut self, time_fn: fn() -> String) -> Self {
-        self.time_fn = Some(time_fn);
+    /// Sets the time function that will be used to get the current time. Can be a `fn` or a
+    /// closure capturing its configuration
+    pub fn with_time_fn<F>(mut self, time_fn: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
+        self.time_fn = Some(Box::new(time_fn));
+        self
+    }
+
+    /// **🧉 » `with_timestamp`**
+    ///
+    /// Sets the time function from a `datetime::Timestamp`, e.g. to use local time, an rfc 3339
+    /// format or millisecond precision
+    pub fn with_timestamp(self, timestamp: datetime::Timestamp) -> Self {
+        self.with_time_fn(move || timestamp.now())
+    }
+
+    /// **🧉 » `with_sink`**
+    ///
+    /// Adds a sink the records are written to, either a `Sink` (which writes every record with the
+    /// default format) or a `SinkConfig` with its own minimum level and formatter.
+    ///
+    /// Can be called several times to write the records to several sinks, e.g. info records to
+    /// stderr (colored if it's a terminal) and debug records to a file (without colors):
+    ///
+    /// ```rust,no_run
+    /// # use lool::logger::{ConsoleLogger, Level, LevelFilter};
+    /// # use lool::logger::sink::{FileSink, SinkConfig, StderrSink};
+    /// # fn main() -> eyre::Result<()> {
+    /// ConsoleLogger::builder()
+    ///     .with_level(Level::Debug)
+    ///     .with_sink(SinkConfig::new(StderrSink).with_level(LevelFilter::Info))
+    ///     .with_sink(FileSink::open("app.log")?)
+    ///     .install()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// When no sink is added, the records are written to stdout.
+    pub fn with_sink<S: Into<SinkConfig>>(mut self, sink: S) -> Self {
+        self.sinks.push(sink.into());
+        self
+    }
+
+    /// **🧉 » `with_format`**
+    ///
+    /// Sets the format of the lines, as a template like `{time} [{level:<5:bold}] {message}`, used
+    /// by every sink without a formatter of its own. See `format::TemplateFormat` for the
+    /// placeholders, and their padding, width and style modifiers.
+    ///
+    /// An invalid template makes `install` fail. Without a template, lines use the default
+    /// `name » time | LEVEL | ctx:line - message` format.
+    pub fn with_format<S: AsRef<str>>(mut self, template: S) -> Self {
+        self.format = Some(template.as_ref().to_string());
+        self
+    }
+
+    /// **🧉 » `with_color`**
+    ///
+    /// Sets when the lines are styled with ANSI colors, for every sink without a color mode of its
+    /// own. Defaults to `ColorMode::Auto`, which uses colors only for sinks writing to a terminal,
+    /// honouring the `NO_COLOR`, `CLICOLOR` and `FORCE_COLOR` environment variables.
+    pub fn with_color(mut self, color: ColorMode) -> Self {
+        self.color = color;
         self
     }
 
@@ -275,14 +405,565 @@ impl SetupBuilder {
     /// **🧉 » `install`**
     ///
     /// Builds and installs the logger.
-    pub fn install(self) -> Result<()> {
-        let logger = Box::new(ConsoleLogger {
+    ///
+    /// Returns a `LoggerHandle`, which can change the levels and ignored contexts of the logger
+    /// while the program runs. It can be dropped if that's not needed.
+    ///
+    /// Fails if a logger was already installed, if the env filter has invalid directives, or if
+    /// the format template is invalid.
+    pub fn install(self) -> Result<LoggerHandle> {
+        set_logger(self.build()?)
+    }
+
+    /// **🧉 » `install_async`**
+    ///
+    /// Builds and installs an asynchronous logger: records are formatted on the thread that logs
+    /// them, queued, and written to the sinks by a background thread, so slow sinks don't slow
+    /// down the program.
+    ///
+    /// The queue holds up to `capacity` records. When it's full, the `overflow` policy decides
+    /// whether the logging thread waits, or a record is dropped. Dropped records are reported by a
+    /// warning written before the next record.
+    ///
+    /// Returns a guard that writes the queued records when dropped (see `AsyncGuard`), so it
+    /// should be kept alive until the program ends. Its `handle` method gives the `LoggerHandle`
+    /// of the logger:
+    ///
+    /// ```rust,no_run
+    /// # use lool::logger::{ConsoleLogger, Overflow};
+    /// # fn main() -> eyre::Result<()> {
+    /// let _guard = ConsoleLogger::builder().install_async(1024, Overflow::DropOldest)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Fails like `install`, or if the capacity is zero.
+    pub fn install_async(self, capacity: usize, overflow: Overflow) -> Result<AsyncGuard> {
+        let (logger, guard) = self.build_async(capacity, overflow)?;
+        let background = logger.background.clone();
+
+        set_logger(logger)?;
+        if let Some(background) = background {
+            background.install();
+        }
+
+        Ok(guard)
+    }
+
+    /// builds the logger without installing it
+    pub(crate) fn build(self) -> Result<ConsoleLogger> {
+        let mut levels = LevelFilters::new(self.max_level.unwrap_or(Level::Info).to_level_filter());
+        for (module, level) in self.module_levels {
+            levels.set_module(module, level);
+        }
+
+        let mut filters = Filters {
+            levels,
+            ignore_globs: self.ctx_ignore_globs.unwrap_or_default(),
+        };
+        if let Some(var) = &self.env_filter {
+            env_directives(var)?.apply(&mut filters);
+        }
+
+        let format: Box<dyn Formatter> = match &self.format {
+            Some(template) => Box::new(TemplateFormat::new(template)?),
+            None => Box::new(DefaultFormat),
+        };
+
+        let mut sinks = self.sinks;
+        if sinks.is_empty() {
+            sinks.push(SinkConfig::new(StdoutSink));
+        }
+
+        for sink in &mut sinks {
+            sink.colored = sink.color.unwrap_or(self.color).enabled(sink.sink.is_terminal());
+        }
+
+        Ok(ConsoleLogger {
             name: self.name.unwrap_or("".to_string()),
-            time_fn: self.time_fn.unwrap_or(datetime::utc_current_time),
-            ctx_ignore_globs: self.ctx_ignore_globs.unwrap_or_default(),
-        });
-        log::set_logger(Box::leak(logger) as &'static dyn Log)
-            .map(|()| log::set_max_level(self.max_level.unwrap().to_level_filter()))
-            .map_err(|err| eyre!("failed to set logger: {}", err))
+            time_fn: self.time_fn.unwrap_or_else(|| Box::new(datetime::utc_current_time)),
+            filters: Arc::new(RwLock::new(filters)),
+            sinks: Arc::new(sinks),
+            format,
+            background: None,
+        })
+    }
+
+    /// builds an asynchronous logger without installing it
+    pub(crate) fn build_async(
+        self,
+        capacity: usize,
+        overflow: Overflow,
+    ) -> Result<(ConsoleLogger, AsyncGuard)> {
+        if capacity == 0 {
+            return Err(eyre!("the capacity of the log queue can't be zero"));
+        }
+
+        let mut logger = self.build()?;
+        let background = Background::spawn(Arc::clone(&logger.sinks), capacity, overflow);
+        logger.background = Some(Arc::clone(&background));
+
+        let guard = AsyncGuard::new(background, logger.handle());
+        Ok((logger, guard))
+    }
+}
+
+/// installs a logger as the one used by the `log` macros
+fn set_logger(logger: ConsoleLogger) -> Result<LoggerHandle> {
+    let logger = Box::new(logger);
+    let max_level = logger.max_level();
+    let handle = logger.handle();
+
+    log::set_logger(Box::leak(logger) as &'static dyn Log)
+        .map(|()| log::set_max_level(max_level))
+        .map_err(|err| eyre!("failed to set logger: {}", err))?;
+
+    Ok(handle)
+}
+
+/// reads the filter directives in an environment variable, if it's set
+fn env_directives(var: &str) -> Result<Directives> {
+    match env::var(var) {
+        Ok(spec) => spec.parse().map_err(|err| eyre!("{} in {}", err, var)),
+        Err(env::VarError::NotPresent) => Ok(Directives::default()),
+        Err(env::VarError::NotUnicode(_)) => Err(eyre!("{} is not valid unicode", var)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        eyre::{set_hook, DefaultHandler},
+        format::DefaultFormat,
+        sink::{MemorySink, Sink},
+        std::sync::{
+            mpsc::{channel, Receiver, Sender},
+            Mutex,
+        },
+    };
+
+    fn setup_eyre() {
+        let _ = set_hook(Box::new(DefaultHandler::default_with));
+    }
+
+    /// builds a logger writing its lines into the returned sink
+    fn capturing(builder: SetupBuilder) -> (ConsoleLogger, MemorySink) {
+        let sink = MemorySink::new();
+        let logger =
+            builder.with_time_fn(datetime::noop_datetime).with_sink(sink.clone()).build().unwrap();
+
+        (logger, sink)
+    }
+
+    fn log_to(logger: &ConsoleLogger, level: Level, target: &str, message: &str) {
+        logger.log(
+            &Record::builder()
+                .level(level)
+                .target(target)
+                .module_path(Some(target))
+                .file(Some("lib/logger/mod.rs"))
+                .line(Some(1))
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    /// a sink that blocks its first write until it's opened, to fill the queue of async loggers
+    struct GatedSink {
+        lines: MemorySink,
+        entered: Mutex<Option<Sender<()>>>,
+        open: Mutex<Option<Receiver<()>>>,
+    }
+
+    impl Sink for GatedSink {
+        fn write_line(&self, line: &str) {
+            if let Some(entered) = self.entered.lock().unwrap().take() {
+                entered.send(()).unwrap();
+                self.open.lock().unwrap().take().unwrap().recv().unwrap();
+            }
+            self.lines.write_line(line);
+        }
+    }
+
+    /// builds an async logger with a gated sink, and returns it once the writer is blocked
+    fn blocked_async(
+        capacity: usize,
+        overflow: Overflow,
+    ) -> (ConsoleLogger, MemorySink, Sender<()>) {
+        let (entered, blocked) = channel();
+        let (open, gate) = channel();
+        let lines = MemorySink::new();
+        let sink = GatedSink {
+            lines: lines.clone(),
+            entered: Mutex::new(Some(entered)),
+            open: Mutex::new(Some(gate)),
+        };
+
+        let (logger, guard) = ConsoleLogger::builder()
+            .with_color(ColorMode::Never)
+            .with_format("{message}")
+            .with_sink(sink)
+            .build_async(capacity, overflow)
+            .unwrap();
+        // the logger outlives the test, as an installed logger would
+        std::mem::forget(guard);
+
+        log_to(&logger, Level::Info, "app", "first");
+        blocked.recv().unwrap();
+
+        (logger, lines, open)
+    }
+
+    #[test]
+    fn test_configured_level_is_honoured() {
+        let (logger, lines) = capturing(ConsoleLogger::builder().with_level(Level::Trace));
+
+        let metadata = Metadata::builder().level(Level::Trace).target("app").build();
+        assert!(logger.enabled(&metadata));
+
+        log_to(&logger, Level::Trace, "app", "trace line");
+        log_to(&logger, Level::Debug, "app", "debug line");
+
+        let lines = lines.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("trace line"));
+        assert!(lines[1].ends_with("debug line"));
+    }
+
+    #[test]
+    fn test_module_levels() {
+        let builder = ConsoleLogger::builder()
+            .with_level(Level::Warn)
+            .with_module_level("my_crate", LevelFilter::Info)
+            .with_module_level("my_crate::db", LevelFilter::Trace)
+            .with_module_level("my_crate::db::pool", LevelFilter::Off);
+        let (logger, lines) = capturing(builder);
+
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+
+        log_to(
+            &logger,
+            Level::Info,
+            "other",
+            "dropped: below the global level",
+        );
+        log_to(&logger, Level::Warn, "other", "global warn");
+        log_to(
+            &logger,
+            Level::Debug,
+            "my_crate::http",
+            "dropped: below the crate level",
+        );
+        log_to(&logger, Level::Info, "my_crate::http", "crate info");
+        log_to(&logger, Level::Trace, "my_crate::db::query", "db trace");
+        log_to(
+            &logger,
+            Level::Error,
+            "my_crate::db::pool",
+            "dropped: pool is off",
+        );
+
+        let lines = lines.lines();
+        let messages: Vec<&str> =
+            lines.iter().map(|line| line.rsplit(" - ").next().unwrap()).collect();
+        assert_eq!(messages, ["global warn", "crate info", "db trace"]);
+    }
+
+    #[test]
+    fn test_ignored_contexts_are_dropped() {
+        let builder = ConsoleLogger::builder().with_level(Level::Info).ignore("lib/logger/**");
+        let (logger, lines) = capturing(builder);
+
+        log_to(&logger, Level::Error, "app", "ignored");
+        assert!(lines.lines().is_empty());
+    }
+
+    #[test]
+    fn test_env_filter() {
+        env::set_var(
+            "LOOL_LOG_TEST_ENV_FILTER",
+            "warn,my_crate::db=trace,-**/generated/**",
+        );
+        let builder = ConsoleLogger::builder()
+            .with_level(Level::Info)
+            .with_module_level("my_crate", LevelFilter::Debug)
+            .with_module_level("my_crate::db", LevelFilter::Info)
+            .with_env_filter("LOOL_LOG_TEST_ENV_FILTER");
+        let (logger, _) = capturing(builder);
+
+        assert_eq!(logger.handle().level_for("app"), LevelFilter::Warn);
+        assert_eq!(
+            logger.handle().level_for("my_crate::http"),
+            LevelFilter::Debug
+        );
+        assert_eq!(
+            logger.handle().level_for("my_crate::db"),
+            LevelFilter::Trace
+        );
+        assert!(logger.read_filters().should_ignore("src/generated/api.rs"));
+
+        // an unset variable leaves the levels set in code
+        let builder = ConsoleLogger::builder().with_env_filter("LOOL_LOG_TEST_UNSET");
+        let (logger, _) = capturing(builder);
+        assert_eq!(logger.handle().level_for("app"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn test_invalid_env_filter() {
+        setup_eyre();
+
+        env::set_var("LOOL_LOG_TEST_INVALID", "info,db=verbose");
+
+        let err = ConsoleLogger::builder()
+            .with_env_filter("LOOL_LOG_TEST_INVALID")
+            .install()
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "invalid directive `db=verbose`: unknown level `verbose` (expected off, error, warn, \
+             info, debug or trace) in LOOL_LOG_TEST_INVALID"
+        );
+    }
+
+    #[test]
+    fn test_sinks_have_their_own_level_and_formatter() {
+        let colored = MemorySink::new();
+        let plain = MemorySink::new();
+        let logger = ConsoleLogger::builder()
+            .with_level(Level::Debug)
+            .with_time_fn(datetime::noop_datetime)
+            .with_color(ColorMode::Always)
+            .with_sink(SinkConfig::new(colored.clone()).with_level(LevelFilter::Info))
+            .with_sink(SinkConfig::new(plain.clone()).with_color(ColorMode::Never))
+            .build()
+            .unwrap();
+
+        // the sinks can't be more verbose than the logger
+        assert_eq!(logger.max_level(), LevelFilter::Debug);
+
+        log_to(&logger, Level::Info, "app", "info line");
+        log_to(&logger, Level::Debug, "app", "debug line");
+        log_to(
+            &logger,
+            Level::Trace,
+            "app",
+            "dropped: below the logger level",
+        );
+
+        assert_eq!(colored.lines().len(), 1);
+        assert!(colored.lines()[0].contains("\x1b["));
+        assert_eq!(
+            plain.lines(),
+            [
+                " | INFO  | lib/logger/mod.rs:1 - info line",
+                " | DEBUG | lib/logger/mod.rs:1 - debug line"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_template() {
+        let plain = MemorySink::new();
+        let builder = ConsoleLogger::builder()
+            .with_name("my-app")
+            .with_color(ColorMode::Never)
+            .with_format("{name}: [{level:<5}] {target} - {message}")
+            .with_sink(SinkConfig::new(plain.clone()).with_formatter(DefaultFormat));
+        let (logger, templated) = capturing(builder);
+
+        log_to(&logger, Level::Warn, "app::db", "slow query");
+
+        assert_eq!(templated.lines(), ["my-app: [WARN ] app::db - slow query"]);
+        // sinks with their own formatter don't use the template
+        assert_eq!(
+            plain.lines(),
+            ["my-app » | WARN  | lib/logger/mod.rs:1 - slow query"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_format_template() {
+        setup_eyre();
+
+        let err = ConsoleLogger::builder().with_format("{lvl}").install().unwrap_err();
+        assert!(err.to_string().contains("unknown placeholder `{lvl}`"));
+    }
+
+    #[test]
+    fn test_time_fn_closure() {
+        let timestamp = datetime::Timestamp::utc().with_pattern("%Y").unwrap();
+        let sink = MemorySink::new();
+        let logger = ConsoleLogger::builder()
+            .with_color(ColorMode::Never)
+            .with_format("{time} {message}")
+            .with_timestamp(timestamp)
+            .with_sink(sink.clone())
+            .build()
+            .unwrap();
+
+        log_to(&logger, Level::Info, "app", "line");
+
+        let line = &sink.lines()[0];
+        assert_eq!(line.len(), "2024 line".len());
+        assert!(line.starts_with("20") && line.ends_with(" line"));
+    }
+
+    #[test]
+    fn test_async_logger() {
+        let sink = MemorySink::new();
+        let (logger, guard) = ConsoleLogger::builder()
+            .with_color(ColorMode::Never)
+            .with_format("{message}")
+            .with_sink(sink.clone())
+            .build_async(16, Overflow::Block)
+            .unwrap();
+
+        for i in 0..10 {
+            log_to(&logger, Level::Info, "app", &format!("line {}", i));
+        }
+        logger.flush();
+        assert_eq!(sink.lines().len(), 10);
+        assert_eq!(sink.lines()[9], "line 9");
+
+        // the guard writes the queued records, and later ones are written synchronously
+        log_to(&logger, Level::Info, "app", "queued");
+        drop(guard);
+        log_to(&logger, Level::Info, "app", "after shutdown");
+
+        assert_eq!(sink.lines()[10..], ["queued", "after shutdown"]);
+    }
+
+    #[test]
+    fn test_async_overflow_drop_newest() {
+        let (logger, lines, open) = blocked_async(2, Overflow::DropNewest);
+
+        for message in ["a", "b", "c", "d"] {
+            log_to(&logger, Level::Info, "app", message);
+        }
+        open.send(()).unwrap();
+        log_to(&logger, Level::Info, "app", "e");
+        logger.flush();
+
+        assert_eq!(
+            lines.lines(),
+            [
+                "first",
+                "a",
+                "b",
+                "2 log records were dropped because the log queue was full",
+                "e"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_async_overflow_drop_oldest() {
+        let (logger, lines, open) = blocked_async(2, Overflow::DropOldest);
+
+        for message in ["a", "b", "c", "d"] {
+            log_to(&logger, Level::Info, "app", message);
+        }
+        open.send(()).unwrap();
+        logger.flush();
+
+        // `a` is dropped by `c`, and `b` by `d` (which reports the first drop)
+        assert_eq!(
+            lines.lines(),
+            [
+                "first",
+                "c",
+                "1 log records were dropped because the log queue was full",
+                "d",
+                "1 log records were dropped because the log queue was full"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_async_overflow_block() {
+        let (logger, lines, open) = blocked_async(1, Overflow::Block);
+        let logger = Arc::new(logger);
+
+        log_to(&logger, Level::Info, "app", "a");
+        let blocked = {
+            let logger = Arc::clone(&logger);
+            std::thread::spawn(move || log_to(&logger, Level::Info, "app", "b"))
+        };
+
+        open.send(()).unwrap();
+        blocked.join().unwrap();
+        logger.flush();
+
+        assert_eq!(lines.lines(), ["first", "a", "b"]);
+    }
+
+    #[test]
+    fn test_handle_updates_filters() {
+        setup_eyre();
+
+        let builder = ConsoleLogger::builder().with_level(Level::Info).with_format("{message}");
+        let (logger, lines) = capturing(builder);
+        let handle = logger.handle();
+
+        log_to(&logger, Level::Debug, "app", "dropped: below the level");
+        handle.set_module_level("app", LevelFilter::Debug);
+        log_to(&logger, Level::Debug, "app", "app debug");
+
+        handle.ignore("lib/logger/**");
+        log_to(&logger, Level::Error, "app", "dropped: ignored");
+        handle.ignore_all(vec![]);
+
+        // several changes at once, and none at all if a directive is invalid
+        handle.apply_directives("error,db=trace").unwrap();
+        assert!(handle.apply_directives("warn,db=loud").is_err());
+        assert_eq!(handle.level_for("other"), LevelFilter::Error);
+        assert_eq!(handle.level_for("db::pool"), LevelFilter::Trace);
+        assert_eq!(logger.max_level(), LevelFilter::Trace);
+
+        handle.remove_module_level("app");
+        log_to(&logger, Level::Warn, "app", "dropped: below the new level");
+        log_to(&logger, Level::Error, "app", "app error");
+
+        assert_eq!(lines.lines(), ["app debug", "app error"]);
+    }
+
+    #[test]
+    fn test_handle_updates_while_logging() {
+        let builder = ConsoleLogger::builder().with_level(Level::Info).with_format("{message}");
+        let (logger, lines) = capturing(builder);
+        let logger = Arc::new(logger);
+        let handle = logger.handle();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let logger = Arc::clone(&logger);
+                std::thread::spawn(move || {
+                    for _ in 0..200 {
+                        log_to(&logger, Level::Info, "app", "info line");
+                        log_to(&logger, Level::Debug, "app", "debug line");
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..200 {
+            match i % 2 {
+                0 => handle.apply_directives("debug").unwrap(),
+                _ => handle.apply_directives("info").unwrap(),
+            }
+        }
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let lines = lines.lines();
+        assert_eq!(
+            lines.iter().filter(|line| *line == "info line").count(),
+            800
+        );
+        assert!(lines.iter().all(|line| line == "info line" || line == "debug line"));
     }
 }
@@ -0,0 +1,239 @@
+mod rolling;
+
+pub use rolling::{RollingFileBuilder, RollingFileSink, RollingSuffix};
+
+use {
+    super::{format::Formatter, ColorMode, LevelFilter},
+    eyre::{eyre, Result},
+    std::{
+        fs::{File, OpenOptions},
+        io::{self, IsTerminal, Write},
+        path::Path,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// 🧉 » sink
+///
+/// a destination for the formatted lines of a logger.
+///
+/// sinks are shared by every thread that logs, so they must handle concurrent writes themselves
+/// (e.g. by writing each line with a single call, or behind a lock).
+pub trait Sink: Send + Sync {
+    /// writes a formatted line, which doesn't include the trailing new line
+    fn write_line(&self, line: &str);
+
+    /// flushes the lines buffered by the sink, if any
+    fn flush(&self) {}
+
+    /// returns whether the sink writes to a terminal, which enables colors in `ColorMode::Auto`
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+/// 🧉 » stdout sink
+///
+/// writes lines to the standard output. This is the sink used when none is configured.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+    fn write_line(&self, line: &str) {
+        let _ = writeln!(io::stdout().lock(), "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = io::stdout().flush();
+    }
+
+    fn is_terminal(&self) -> bool {
+        io::stdout().is_terminal()
+    }
+}
+
+/// 🧉 » stderr sink
+///
+/// writes lines to the standard error, keeping stdout free for the output of the program.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StderrSink;
+
+impl Sink for StderrSink {
+    fn write_line(&self, line: &str) {
+        let _ = writeln!(io::stderr().lock(), "{}", line);
+    }
+
+    fn is_terminal(&self) -> bool {
+        io::stderr().is_terminal()
+    }
+}
+
+/// 🧉 » file sink
+///
+/// appends lines to a file, which is created if it doesn't exist.
+#[derive(Debug)]
+pub struct FileSink {
+    file: Mutex<File>,
+}
+
+impl FileSink {
+    /// **🧉 » opens a file to append lines to**
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| eyre!("failed to open log file {}: {}", path.display(), err))?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl Sink for FileSink {
+    fn write_line(&self, line: &str) {
+        // a single write per line, so lines of concurrent processes don't get mixed up
+        let line = format!("{}\n", line);
+        let _ = self.file.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
+/// 🧉 » memory sink
+///
+/// keeps the lines in memory. Clones share the same lines, so a clone can be given to the logger
+/// while the original is used to read them (e.g. in tests).
+#[derive(Clone, Debug, Default)]
+pub struct MemorySink {
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// returns the lines written so far
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+
+    /// removes every line written so far
+    pub fn clear(&self) {
+        self.lines.lock().unwrap().clear();
+    }
+}
+
+impl Sink for MemorySink {
+    fn write_line(&self, line: &str) {
+        self.lines.lock().unwrap().push(line.to_string());
+    }
+}
+
+/// 🧉 » sink config
+///
+/// a sink, along with the minimum level of the records written to it, the formatter of its
+/// lines (the default format of the logger if not set, see `SetupBuilder::with_format`) and its
+/// color mode (the one of the logger if not set, see `SetupBuilder::with_color`).
+///
+/// the level of a sink can only make it stricter than the logger: records have to pass the global
+/// and module levels of the logger before they reach any sink.
+///
+/// **Example**
+///
+/// ```rust
+/// use lool::logger::{
+///     format::JsonFormat,
+///     sink::{SinkConfig, StderrSink},
+///     ColorMode, LevelFilter,
+/// };
+///
+/// let config = SinkConfig::new(StderrSink)
+///     .with_level(LevelFilter::Warn)
+///     .with_formatter(JsonFormat)
+///     .with_color(ColorMode::Never);
+/// ```
+pub struct SinkConfig {
+    pub(crate) sink: Box<dyn Sink>,
+    pub(crate) level: LevelFilter,
+    pub(crate) formatter: Option<Box<dyn Formatter>>,
+    pub(crate) color: Option<ColorMode>,
+    /// whether the sink uses colors, resolved when the logger is built
+    pub(crate) colored: bool,
+}
+
+impl SinkConfig {
+    /// creates the config of a sink that writes every record, with the default format of the
+    /// logger
+    pub fn new<S: Sink + 'static>(sink: S) -> Self {
+        Self {
+            sink: Box::new(sink),
+            level: LevelFilter::Trace,
+            formatter: None,
+            color: None,
+            colored: false,
+        }
+    }
+
+    /// **🧉 » `with_level`**
+    ///
+    /// Sets the minimum level of the records written to the sink
+    pub fn with_level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// **🧉 » `with_formatter`**
+    ///
+    /// Sets the formatter of the lines written to the sink
+    pub fn with_formatter<F: Formatter + 'static>(mut self, formatter: F) -> Self {
+        self.formatter = Some(Box::new(formatter));
+        self
+    }
+
+    /// **🧉 » `with_color`**
+    ///
+    /// Sets the color mode of the sink, overriding the one of the logger
+    pub fn with_color(mut self, color: ColorMode) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+impl<S: Sink + 'static> From<S> for SinkConfig {
+    fn from(sink: S) -> Self {
+        Self::new(sink)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::fs};
+
+    #[test]
+    fn test_memory_sink_clones_share_lines() {
+        let sink = MemorySink::new();
+        let clone = sink.clone();
+
+        clone.write_line("first");
+        clone.write_line("second");
+        assert_eq!(sink.lines(), ["first", "second"]);
+
+        sink.clear();
+        assert!(clone.lines().is_empty());
+    }
+
+    #[test]
+    fn test_file_sink_appends_lines() {
+        let path = std::env::temp_dir().join(format!("lool-file-sink-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        FileSink::open(&path).unwrap().write_line("first");
+        // reopening the file keeps the previous lines
+        FileSink::open(&path).unwrap().write_line("second");
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\n");
+        fs::remove_file(&path).unwrap();
+    }
+}
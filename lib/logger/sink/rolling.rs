@@ -0,0 +1,745 @@
+use {
+    super::Sink,
+    crate::logger::datetime::utc_date,
+    eyre::{eyre, Report, Result},
+    std::{
+        fmt,
+        fs::{self, File, OpenOptions},
+        io::{BufWriter, Write},
+        path::{Path, PathBuf},
+        sync::{
+            mpsc::{self, Receiver, RecvTimeoutError, Sender},
+            Arc, Mutex,
+        },
+        thread::{self, JoinHandle},
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    },
+};
+
+const SECS_PER_DAY: u64 = 24 * 3600;
+
+/// time after which the buffered lines are written, when nothing else wrote them
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 🧉 » suffix of rotated log files
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RollingSuffix {
+    /// `app.log.1` is the most recent rotated file, `app.log.2` the one before it, and so on
+    #[default]
+    Numbered,
+    /// `app.log.2024-03-31` (UTC) is the file of that day. Files rotated more than once a day (by
+    /// size) get a number too: `app.log.2024-03-31.1`, `app.log.2024-03-31.2`, ...
+    Dated,
+}
+
+/// 🧉 » rolling file sink
+///
+/// appends lines to a file that is rotated daily (at midnight UTC) and/or when it reaches a size.
+/// Rotated files are renamed with a numbered or dated suffix, and optionally gzip-compressed
+/// (with the `logger.gzip` feature). Only the most recent rotated files are kept. The rotated
+/// files are moved, compressed and pruned by a worker thread, so logging doesn't wait for them.
+///
+/// writes are buffered, so lines reach the file when the buffer fills up, on rotation, after a
+/// second without being written, or when the sink is flushed. Call `logger::shutdown()` before
+/// the program exits, which writes the pending lines and waits for the pending rotations.
+///
+/// errors that happen after the sink is built (like a failed rotation) are written to stderr, or
+/// given to the handler set with `RollingFileBuilder::with_error_handler`.
+///
+/// **Example**
+///
+/// ```rust,no_run
+/// use lool::logger::sink::{RollingFileSink, RollingSuffix};
+///
+/// # fn main() -> eyre::Result<()> {
+/// let sink = RollingFileSink::builder("logs/app.log")
+///     .with_daily_rotation()
+///     .with_max_size(10 * 1024 * 1024)
+///     .with_suffix(RollingSuffix::Dated)
+///     .with_max_files(7)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct RollingFileSink {
+    path: PathBuf,
+    options: RollingFileBuilder,
+    state: Arc<Mutex<RollingState>>,
+    /// the jobs of the worker, which moves, compresses and prunes the rotated files
+    jobs: Option<Sender<Job>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+struct RollingState {
+    file: BufWriter<File>,
+    /// bytes in the active file, including the buffered ones
+    size: u64,
+    /// the day (since UNIX epoch) the lines in the active file were written
+    day: u64,
+    /// the number of rotations so far, used to name the staged files
+    rotations: u64,
+}
+
+/// a job of the worker of a rolling file sink
+enum Job {
+    /// moves a file staged by a rotation to its rotated path
+    Rotate { staged: PathBuf, day: u64 },
+    /// answers once the previous jobs are done
+    Flush(Sender<()>),
+}
+
+/// called with the errors of a rolling file sink
+#[derive(Clone)]
+struct ErrorHandler(Arc<dyn Fn(&Report) + Send + Sync>);
+
+impl fmt::Debug for ErrorHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ErrorHandler")
+    }
+}
+
+/// **🧉 » rolling file sink builder**
+///
+/// created with `RollingFileSink::builder`. Without any rotation rule, the file is never rotated.
+#[derive(Clone, Debug)]
+pub struct RollingFileBuilder {
+    path: PathBuf,
+    daily: bool,
+    max_size: Option<u64>,
+    suffix: RollingSuffix,
+    max_files: usize,
+    gzip: bool,
+    on_error: ErrorHandler,
+    flush_interval: Duration,
+    today: fn() -> u64,
+}
+
+impl RollingFileSink {
+    /// **🧉 » creates a builder of a rolling file sink writing to `path`**
+    pub fn builder<P: AsRef<Path>>(path: P) -> RollingFileBuilder {
+        RollingFileBuilder {
+            path: path.as_ref().to_path_buf(),
+            daily: false,
+            max_size: None,
+            suffix: RollingSuffix::default(),
+            max_files: 5,
+            gzip: false,
+            // the sink can't log its own errors
+            on_error: ErrorHandler(Arc::new(|err| eprintln!("{}", err))),
+            flush_interval: FLUSH_INTERVAL,
+            today: utc_today,
+        }
+    }
+
+    /// rotates the active file if a rotation rule says so, before writing `len` more bytes
+    fn rotate_if_needed(&self, state: &mut RollingState, len: u64) -> Result<()> {
+        let today = (self.options.today)();
+        let new_day = self.options.daily && today != state.day;
+        let full =
+            self.options.max_size.is_some_and(|max| state.size > 0 && state.size + len > max);
+
+        if new_day || full {
+            self.rotate(state)?;
+            state.day = today;
+        }
+
+        Ok(())
+    }
+
+    /// stages the active file for the worker to rotate, and starts a new one
+    ///
+    /// only the rename of the active file happens here, while the lines wait for the new one.
+    /// Moving the rotated files around and compressing them is left to the worker.
+    fn rotate(&self, state: &mut RollingState) -> Result<()> {
+        state.file.flush()?;
+
+        state.rotations += 1;
+        let staged = self.path.with_file_name(format!(
+            "{}.rotating.{}.{}",
+            file_name(&self.path),
+            std::process::id(),
+            state.rotations
+        ));
+
+        fs::rename(&self.path, &staged)?;
+        state.file = BufWriter::new(open_append(&self.path)?);
+        state.size = 0;
+
+        match self.send(Job::Rotate {
+            staged,
+            day: state.day,
+        }) {
+            true => Ok(()),
+            false => Err(eyre!("the rotation worker is not running")),
+        }
+    }
+
+    /// sends a job to the worker, returning false if it's not running
+    fn send(&self, job: Job) -> bool {
+        self.jobs.as_ref().is_some_and(|jobs| jobs.send(job).is_ok())
+    }
+}
+
+impl RollingFileBuilder {
+    /// **🧉 » `with_daily_rotation`**
+    ///
+    /// Rotates the file when the day (UTC) changes
+    pub fn with_daily_rotation(mut self) -> Self {
+        self.daily = true;
+        self
+    }
+
+    /// **🧉 » `with_max_size`**
+    ///
+    /// Rotates the file before it grows over `bytes` (a line is never split between files)
+    pub fn with_max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// **🧉 » `with_suffix`**
+    ///
+    /// Sets the suffix of the rotated files, numbered by default
+    pub fn with_suffix(mut self, suffix: RollingSuffix) -> Self {
+        self.suffix = suffix;
+        self
+    }
+
+    /// **🧉 » `with_max_files`**
+    ///
+    /// Sets how many rotated files are kept (5 by default), besides the active one
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// **🧉 » `with_gzip`**
+    ///
+    /// Compresses the rotated files with gzip, adding a `.gz` extension to them
+    #[cfg(feature = "logger.gzip")]
+    pub fn with_gzip(mut self) -> Self {
+        self.gzip = true;
+        self
+    }
+
+    /// **🧉 » `with_error_handler`**
+    ///
+    /// Sets the function called with the errors of the sink after it's built, like a failed
+    /// rotation. The sink can't log its own errors, so by default they're written to stderr
+    pub fn with_error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Report) + Send + Sync + 'static,
+    {
+        self.on_error = ErrorHandler(Arc::new(handler));
+        self
+    }
+
+    /// **🧉 » `build`**
+    ///
+    /// Opens (or creates) the active file. Lines are appended to it if it already exists, unless
+    /// it was written on a previous day and the sink rotates daily, in which case it's rotated
+    /// first.
+    pub fn build(self) -> Result<RollingFileSink> {
+        let path = self.path.clone();
+        let open_err = |err| eyre!("failed to open log file {}: {}", path.display(), err);
+
+        let file = open_append(&path).map_err(open_err)?;
+        let metadata = file.metadata().map_err(open_err)?;
+        let day = metadata.modified().map_or_else(|_| (self.today)(), day_of);
+
+        let state = Arc::new(Mutex::new(RollingState {
+            file: BufWriter::new(file),
+            size: metadata.len(),
+            day,
+            rotations: 0,
+        }));
+
+        let (jobs, receiver) = mpsc::channel();
+        let worker = Worker {
+            path: self.path.clone(),
+            options: self.clone(),
+            state: Arc::clone(&state),
+        };
+
+        let sink = RollingFileSink {
+            path: self.path.clone(),
+            options: self,
+            state,
+            jobs: Some(jobs),
+            worker: Some(thread::spawn(move || worker.run(&receiver))),
+        };
+
+        let mut state = sink.state.lock().unwrap();
+        sink.rotate_if_needed(&mut state, 0)
+            .map_err(|err| eyre!("failed to rotate log file {}: {}", path.display(), err))?;
+        drop(state);
+
+        Ok(sink)
+    }
+}
+
+impl Sink for RollingFileSink {
+    fn write_line(&self, line: &str) {
+        let mut state = self.state.lock().unwrap();
+        let len = line.len() as u64 + 1;
+
+        // a failed rotation keeps writing to the active file, so lines aren't lost
+        if let Err(err) = self.rotate_if_needed(&mut state, len) {
+            let err = eyre!("failed to rotate log file {}: {}", self.path.display(), err);
+            (self.options.on_error.0)(&err);
+        }
+
+        if writeln!(state.file, "{}", line).is_ok() {
+            state.size += len;
+        }
+    }
+
+    /// writes the buffered lines, and waits for the pending rotations
+    fn flush(&self) {
+        let _ = self.state.lock().unwrap().file.flush();
+
+        let (done, finished) = mpsc::channel();
+        if self.send(Job::Flush(done)) {
+            let _ = finished.recv();
+        }
+    }
+}
+
+impl Drop for RollingFileSink {
+    fn drop(&mut self) {
+        // the worker finishes the pending jobs once there are no more to come
+        self.jobs.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// **the worker of a rolling file sink**
+///
+/// moves the files staged by the rotations to their rotated paths, compresses and prunes them,
+/// and flushes the buffered lines of the sink when it's been idle for a while.
+struct Worker {
+    path: PathBuf,
+    options: RollingFileBuilder,
+    state: Arc<Mutex<RollingState>>,
+}
+
+impl Worker {
+    fn run(&self, jobs: &Receiver<Job>) {
+        loop {
+            match jobs.recv_timeout(self.options.flush_interval) {
+                Ok(Job::Rotate { staged, day }) => {
+                    if let Err(err) = self.rotate(&staged, day) {
+                        let err =
+                            eyre!("failed to rotate log file {}: {}", self.path.display(), err);
+                        (self.options.on_error.0)(&err);
+                    }
+                }
+                Ok(Job::Flush(done)) => {
+                    let _ = done.send(());
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if let Ok(mut state) = self.state.lock() {
+                        let _ = state.file.flush();
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+
+    /// moves a staged file to its rotated path
+    fn rotate(&self, staged: &Path, day: u64) -> Result<()> {
+        let rotated = match self.options.suffix {
+            RollingSuffix::Numbered => {
+                self.shift_numbered()?;
+                self.rotated_path("1")
+            }
+            RollingSuffix::Dated => self.free_dated_path(day),
+        };
+
+        fs::rename(staged, &rotated)?;
+
+        if self.options.gzip {
+            compress(&rotated)?;
+        }
+
+        self.prune()
+    }
+
+    /// renames `.1` to `.2`, `.2` to `.3`, and so on, dropping the files beyond `max_files`
+    fn shift_numbered(&self) -> Result<()> {
+        let files = self.rotated_files()?;
+
+        for (index, file) in files.iter().enumerate().rev() {
+            if index + 1 >= self.options.max_files {
+                fs::remove_file(file)?;
+                continue;
+            }
+
+            // compressed files keep their `.gz` extension
+            let shifted = self.rotated_path(&(index + 2).to_string());
+            match file.extension().is_some_and(|ext| ext == "gz") {
+                true => fs::rename(file, gz_path(&shifted))?,
+                false => fs::rename(file, shifted)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// returns the first unused dated path for the given day
+    fn free_dated_path(&self, day: u64) -> PathBuf {
+        let (year, month, day) = utc_date(day);
+        let date = format!("{:04}-{:02}-{:02}", year, month, day);
+
+        let taken = |path: &PathBuf| path.exists() || gz_path(path).exists();
+
+        let mut path = self.rotated_path(&date);
+        let mut number = 0;
+        while taken(&path) {
+            number += 1;
+            path = self.rotated_path(&format!("{}.{}", date, number));
+        }
+
+        path
+    }
+
+    /// removes the oldest rotated files, keeping `max_files` of them
+    fn prune(&self) -> Result<()> {
+        for file in self.rotated_files()?.iter().skip(self.options.max_files) {
+            fs::remove_file(file)?;
+        }
+
+        Ok(())
+    }
+
+    /// returns the rotated files, from the most recent to the oldest
+    fn rotated_files(&self) -> Result<Vec<PathBuf>> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let prefix = format!("{}.", file_name(&self.path));
+
+        let mut files: Vec<(RotationKey, PathBuf)> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let suffix = name.strip_prefix(&prefix)?;
+                Some((rotation_key(suffix, self.options.suffix)?, entry.path()))
+            })
+            .collect();
+
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    fn rotated_path(&self, suffix: &str) -> PathBuf {
+        self.path.with_file_name(format!("{}.{}", file_name(&self.path), suffix))
+    }
+}
+
+/// sorts rotated files from the most recent to the oldest
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum RotationKey {
+    Numbered(u64),
+    Dated(std::cmp::Reverse<(String, u64)>),
+}
+
+/// returns the sort key of a rotated file from its suffix, or `None` if it's not a rotated file
+fn rotation_key(suffix: &str, kind: RollingSuffix) -> Option<RotationKey> {
+    let suffix = suffix.strip_suffix(".gz").unwrap_or(suffix);
+
+    match kind {
+        RollingSuffix::Numbered => suffix.parse().ok().map(RotationKey::Numbered),
+        RollingSuffix::Dated => {
+            let (date, number) = match suffix.split_once('.') {
+                Some((date, number)) => (date, number.parse().ok()?),
+                None => (suffix, 0),
+            };
+
+            let is_date = date.len() == 10
+                && date.chars().enumerate().all(|(i, c)| match i {
+                    4 | 7 => c == '-',
+                    _ => c.is_ascii_digit(),
+                });
+
+            is_date.then(|| RotationKey::Dated(std::cmp::Reverse((date.to_string(), number))))
+        }
+    }
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!("{}.gz", file_name(path)))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn utc_today() -> u64 {
+    day_of(SystemTime::now())
+}
+
+fn day_of(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs() / SECS_PER_DAY)
+}
+
+/// compresses a rotated file into `<file>.gz`, removing the original
+#[cfg(feature = "logger.gzip")]
+fn compress(path: &Path) -> Result<()> {
+    use flate2::{write::GzEncoder, Compression};
+
+    let mut encoder = GzEncoder::new(File::create(gz_path(path))?, Compression::default());
+    std::io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?;
+
+    Ok(fs::remove_file(path)?)
+}
+
+#[cfg(not(feature = "logger.gzip"))]
+fn compress(_: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        eyre::{set_hook, DefaultHandler},
+        std::{
+            sync::{
+                atomic::{AtomicU64, Ordering},
+                Arc,
+            },
+            thread,
+        },
+    };
+
+    fn setup_eyre() {
+        let _ = set_hook(Box::new(DefaultHandler::default_with));
+    }
+
+    /// creates an empty directory for the files of a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("lool-rolling-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: PathBuf) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_rotates_by_size_with_numbered_suffixes() {
+        setup_eyre();
+        let dir = test_dir("size");
+        let sink = RollingFileSink::builder(dir.join("app.log"))
+            .with_max_size(12)
+            .with_max_files(2)
+            .build()
+            .unwrap();
+
+        // each line takes 6 bytes, so files hold 2 lines
+        for line in ["line1", "line2", "line3", "line4", "line5", "line6", "line7"] {
+            sink.write_line(line);
+        }
+        sink.flush();
+
+        assert_eq!(read(dir.join("app.log")), "line7\n");
+        assert_eq!(read(dir.join("app.log.1")), "line5\nline6\n");
+        assert_eq!(read(dir.join("app.log.2")), "line3\nline4\n");
+        assert!(!dir.join("app.log.3").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    static TODAY: AtomicU64 = AtomicU64::new(0);
+
+    #[test]
+    fn test_rotates_daily_with_dated_suffixes() {
+        setup_eyre();
+        let dir = test_dir("daily");
+        let mut builder = RollingFileSink::builder(dir.join("app.log"))
+            .with_daily_rotation()
+            .with_max_size(1024)
+            .with_suffix(RollingSuffix::Dated)
+            .with_max_files(2);
+        builder.today = || TODAY.load(Ordering::SeqCst);
+
+        // the file was written today, so it's not rotated when opened
+        fs::write(dir.join("app.log"), "old\n").unwrap();
+        let first_day = utc_today();
+        TODAY.store(first_day, Ordering::SeqCst);
+        let sink = builder.build().unwrap();
+        assert!(!dir.read_dir().unwrap().any(|e| e.unwrap().file_name() != "app.log"));
+
+        sink.write_line("day one");
+        TODAY.store(first_day + 1, Ordering::SeqCst);
+        sink.write_line("day two");
+        TODAY.store(first_day + 2, Ordering::SeqCst);
+        sink.write_line("day three");
+        sink.flush();
+
+        let (year, month, day) = utc_date(first_day + 1);
+        let day_two = format!("app.log.{:04}-{:02}-{:02}", year, month, day);
+        let (year, month, day) = utc_date(first_day);
+        let day_one = format!("app.log.{:04}-{:02}-{:02}", year, month, day);
+
+        assert_eq!(read(dir.join("app.log")), "day three\n");
+        assert_eq!(read(dir.join(day_two)), "day two\n");
+        assert_eq!(read(dir.join(day_one)), "old\nday one\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_dated_suffixes_are_numbered_within_a_day() {
+        setup_eyre();
+        let dir = test_dir("dated-size");
+        let sink = RollingFileSink::builder(dir.join("app.log"))
+            .with_max_size(6)
+            .with_suffix(RollingSuffix::Dated)
+            .with_max_files(2)
+            .build()
+            .unwrap();
+
+        for line in ["line1", "line2", "line3", "line4"] {
+            sink.write_line(line);
+        }
+        sink.flush();
+
+        let (year, month, day) = utc_date(utc_today());
+        let date = format!("app.log.{:04}-{:02}-{:02}", year, month, day);
+
+        assert_eq!(read(dir.join("app.log")), "line4\n");
+        assert_eq!(read(dir.join(format!("{}.2", date))), "line3\n");
+        assert_eq!(read(dir.join(format!("{}.1", date))), "line2\n");
+        // the oldest file has been pruned
+        assert!(!dir.join(date).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_writes() {
+        setup_eyre();
+        let dir = test_dir("concurrent");
+        let sink = Arc::new(
+            RollingFileSink::builder(dir.join("app.log"))
+                .with_max_size(1024)
+                .with_max_files(100)
+                .build()
+                .unwrap(),
+        );
+
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let sink = sink.clone();
+                thread::spawn(move || {
+                    for i in 0..250 {
+                        sink.write_line(&format!("thread {} line {:03}", t, i));
+                    }
+                })
+            })
+            .collect();
+        threads.into_iter().for_each(|thread| thread.join().unwrap());
+        sink.flush();
+
+        let mut lines = 0;
+        for entry in fs::read_dir(&dir).unwrap() {
+            let content = read(entry.unwrap().path());
+            assert!(content.len() <= 1024);
+            assert!(content.lines().all(|line| line.len() == 17));
+            lines += content.lines().count();
+        }
+        assert_eq!(lines, 1000);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_buffered_lines_are_written_after_a_while() {
+        setup_eyre();
+        let dir = test_dir("interval");
+        let mut builder = RollingFileSink::builder(dir.join("app.log"));
+        builder.flush_interval = Duration::from_millis(20);
+        let sink = builder.build().unwrap();
+
+        sink.write_line("buffered");
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(read(dir.join("app.log")), "buffered\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_errors_are_given_to_the_handler() {
+        setup_eyre();
+        let dir = test_dir("errors");
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let handled = errors.clone();
+        let sink = RollingFileSink::builder(dir.join("app.log"))
+            .with_max_size(6)
+            .with_error_handler(move |err| handled.lock().unwrap().push(err.to_string()))
+            .build()
+            .unwrap();
+
+        // the active file can't be rotated once it's gone
+        sink.write_line("line1");
+        fs::remove_file(dir.join("app.log")).unwrap();
+        sink.write_line("line2");
+        sink.flush();
+
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("failed to rotate log file"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "logger.gzip")]
+    #[test]
+    fn test_gzip_rotated_files() {
+        use {flate2::read::GzDecoder, std::io::Read};
+
+        setup_eyre();
+        let dir = test_dir("gzip");
+        let sink = RollingFileSink::builder(dir.join("app.log"))
+            .with_max_size(6)
+            .with_max_files(2)
+            .with_gzip()
+            .build()
+            .unwrap();
+
+        for line in ["line1", "line2", "line3", "line4"] {
+            sink.write_line(line);
+        }
+        sink.flush();
+
+        let unzip = |name: &str| {
+            let mut content = String::new();
+            GzDecoder::new(File::open(dir.join(name)).unwrap())
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+
+        assert_eq!(read(dir.join("app.log")), "line4\n");
+        assert_eq!(unzip("app.log.1.gz"), "line3\n");
+        assert_eq!(unzip("app.log.2.gz"), "line2\n");
+        assert!(!dir.join("app.log.3.gz").exists());
+        assert!(!dir.join("app.log.1").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
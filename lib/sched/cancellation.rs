@@ -0,0 +1,43 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// 🧉 » cancellation token
+///
+/// handed to the actions scheduled with `Scheduler::schedule_cancellable` in the threads
+/// scheduler. The token is cancelled when a run exceeds the timeout of its task (see
+/// `TaskOptions::with_timeout`).
+///
+/// threads can't be killed, so cancellation is cooperative: long running actions should poll
+/// `is_cancelled` and return early once it's `true`.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// 🧉 » returns true if the run has been cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// cancels the run
+    #[cfg_attr(not(feature = "sched.threads"), allow(dead_code))]
+    pub(crate) fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_is_shared_between_clones() {
+        let token = CancellationToken::default();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+}
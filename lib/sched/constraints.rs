@@ -0,0 +1,323 @@
+use {
+    super::rules::SchedulingRule,
+    chrono::{DateTime, Local},
+    std::{collections::VecDeque, time::Duration},
+};
+
+/// time after which a blocked run is retried, when the end of its blackout window is not found
+const BLACKOUT_RETRY_SECS: i64 = 24 * 3600;
+
+/// 🧉 » blocked run policy
+///
+/// what happens to a run of a task when one of its constraints doesn't allow it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BlockedRunPolicy {
+    /// the run doesn't happen, and it's recorded as `RunOutcome::Skipped`
+    #[default]
+    Skip,
+    /// the run waits until the constraints allow it.
+    ///
+    /// occurrences that fall in the meantime are merged into the deferred run. Manual runs are
+    /// never deferred, they are skipped instead.
+    Defer,
+}
+
+/// 🧉 » execution constraint
+///
+/// a condition that is evaluated before each run of a task (scheduled or manual), and that can
+/// block it.
+#[derive(Clone, Debug)]
+pub enum Constraint {
+    /// runs are only allowed while the rule matches (see `SchedulingRule::matches`).
+    ///
+    /// when a task has several allow windows, being in any of them is enough.
+    AllowWindow(SchedulingRule),
+    /// runs are not allowed while the rule matches (see `SchedulingRule::matches`).
+    Blackout(SchedulingRule),
+    /// at most `max_runs` runs are allowed within any `period` of time
+    MaxRuns { max_runs: usize, period: Duration },
+}
+
+/// **a blocked run**
+///
+/// holds why a run was blocked, and the earliest date it could be allowed, if known.
+pub(crate) struct Blocked {
+    pub(crate) reason: String,
+    pub(crate) retry_at: Option<DateTime<Local>>,
+}
+
+/// **constraints of a task**
+///
+/// along with the dates of the latest runs of the task, needed to evaluate the run limits.
+pub(crate) struct TaskConstraints {
+    constraints: Vec<Constraint>,
+    policy: BlockedRunPolicy,
+    runs: VecDeque<DateTime<Local>>,
+}
+
+impl TaskConstraints {
+    pub(crate) fn new(constraints: Vec<Constraint>, policy: BlockedRunPolicy) -> Self {
+        Self {
+            constraints,
+            policy,
+            runs: VecDeque::new(),
+        }
+    }
+
+    /// returns the policy for blocked runs
+    pub(crate) fn policy(&self) -> BlockedRunPolicy {
+        self.policy
+    }
+
+    /// checks if a run is allowed at the given date
+    pub(crate) fn check(&mut self, at: DateTime<Local>) -> Result<(), Blocked> {
+        self.forget_old_runs(at);
+
+        let windows: Vec<&SchedulingRule> = self
+            .constraints
+            .iter()
+            .filter_map(|constraint| match constraint {
+                Constraint::AllowWindow(rule) => Some(rule),
+                _ => None,
+            })
+            .collect();
+
+        if !windows.is_empty() && !windows.iter().any(|rule| rule.matches(at)) {
+            return Err(Blocked {
+                reason: "outside of the allowed windows".to_string(),
+                retry_at: windows.iter().filter_map(|rule| rule.next_from(at)).min(),
+            });
+        }
+
+        for constraint in &self.constraints {
+            match constraint {
+                Constraint::Blackout(rule) if rule.matches(at) => {
+                    return Err(Blocked {
+                        reason: "inside of a blackout window".to_string(),
+                        retry_at: Some(end_of_blackout(rule, at)),
+                    });
+                }
+                Constraint::MaxRuns { max_runs, period } => {
+                    let chrono_period = to_chrono(*period);
+                    // a period longer than the dates go back holds every recorded run
+                    let since = at.checked_sub_signed(chrono_period);
+                    let runs_in_period: Vec<&DateTime<Local>> = self
+                        .runs
+                        .iter()
+                        .filter(|run| since.is_none_or(|since| **run > since))
+                        .collect();
+
+                    if runs_in_period.len() >= *max_runs {
+                        return Err(Blocked {
+                            reason: format!("max runs reached ({} per {:?})", max_runs, period),
+                            retry_at: runs_in_period
+                                .first()
+                                .and_then(|run| run.checked_add_signed(chrono_period)),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// records a run that has just started
+    pub(crate) fn record_run(&mut self, at: DateTime<Local>) {
+        if self.has_run_limits() {
+            self.runs.push_back(at);
+        }
+    }
+
+    fn has_run_limits(&self) -> bool {
+        self.constraints.iter().any(|constraint| matches!(constraint, Constraint::MaxRuns { .. }))
+    }
+
+    /// drops the runs that are older than the longest run limit period
+    fn forget_old_runs(&mut self, at: DateTime<Local>) {
+        let longest_period = self
+            .constraints
+            .iter()
+            .filter_map(|constraint| match constraint {
+                Constraint::MaxRuns { period, .. } => Some(to_chrono(*period)),
+                _ => None,
+            })
+            .max();
+
+        match longest_period {
+            Some(period) => {
+                // no run is old enough to be dropped when the period goes beyond the dates
+                let Some(since) = at.checked_sub_signed(period) else {
+                    return;
+                };
+
+                while self.runs.front().is_some_and(|run| *run <= since) {
+                    self.runs.pop_front();
+                }
+            }
+            None => self.runs.clear(),
+        }
+    }
+}
+
+/// returns the first date after `at` that is not in the blackout window
+///
+/// if the end of the window can't be found, the run is retried after a while.
+fn end_of_blackout(rule: &SchedulingRule, at: DateTime<Local>) -> DateTime<Local> {
+    rule.end_of_match(at).unwrap_or_else(|| at + chrono::Duration::seconds(BLACKOUT_RETRY_SECS))
+}
+
+fn to_chrono(duration: Duration) -> chrono::Duration {
+    chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::sched::{every, once},
+        chrono::TimeZone,
+    };
+
+    #[test]
+    fn test_max_runs() {
+        let at = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let mut constraints = TaskConstraints::new(
+            vec![Constraint::MaxRuns {
+                max_runs: 2,
+                period: Duration::from_secs(3600),
+            }],
+            BlockedRunPolicy::Skip,
+        );
+
+        assert!(constraints.check(at).is_ok());
+        constraints.record_run(at);
+        constraints.record_run(at + chrono::Duration::minutes(10));
+
+        let blocked = constraints.check(at + chrono::Duration::minutes(20)).unwrap_err();
+        assert_eq!(blocked.retry_at, Some(at + chrono::Duration::hours(1)));
+
+        // once the first run is out of the period, a new run is allowed
+        assert!(constraints.check(at + chrono::Duration::minutes(61)).is_ok());
+    }
+
+    #[test]
+    fn test_max_runs_with_a_huge_period() {
+        let at = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let mut constraints = TaskConstraints::new(
+            vec![Constraint::MaxRuns {
+                max_runs: 1,
+                period: Duration::MAX,
+            }],
+            BlockedRunPolicy::Defer,
+        );
+
+        assert!(constraints.check(at).is_ok());
+        constraints.record_run(at);
+
+        // the run is never forgotten, and there's no date to retry at
+        let blocked = constraints.check(at + chrono::Duration::days(365)).unwrap_err();
+        assert_eq!(blocked.retry_at, None);
+    }
+
+    #[test]
+    fn test_blackout() {
+        let at = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let mut constraints =
+            TaskConstraints::new(vec![Constraint::Blackout(once(at))], BlockedRunPolicy::Skip);
+
+        let blocked = constraints.check(at).unwrap_err();
+        assert_eq!(blocked.retry_at, Some(at + chrono::Duration::seconds(1)));
+        assert!(constraints.check(at + chrono::Duration::seconds(1)).is_ok());
+    }
+
+    #[test]
+    fn test_blackout_of_an_interval() {
+        let tick = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap()
+            + chrono::Duration::microseconds(250_300);
+        let mut constraints = TaskConstraints::new(
+            vec![Constraint::Blackout(every(Duration::from_millis(250)).unwrap())],
+            BlockedRunPolicy::Skip,
+        );
+
+        // the window only lasts the millisecond of the tick
+        let blocked = constraints.check(tick).unwrap_err();
+        let end = tick + chrono::Duration::microseconds(700);
+        assert_eq!(blocked.retry_at, Some(end));
+        assert!(constraints.check(end).is_ok());
+    }
+
+    #[cfg(feature = "sched.rule-recurrence")]
+    #[test]
+    fn test_blackout_of_a_rule_set() {
+        use crate::sched::{range, recur, ruleset};
+
+        let mut hours = ruleset();
+        hours.hours_rule(range(2, 4, 1));
+        let mut millis = ruleset();
+        millis.milliseconds_rule(range(0, 499, 1));
+
+        let mut constraints = TaskConstraints::new(
+            vec![Constraint::Blackout(recur(&hours)), Constraint::Blackout(recur(&millis))],
+            BlockedRunPolicy::Defer,
+        );
+
+        let at = Local.with_ymd_and_hms(2024, 4, 7, 3, 15, 0).unwrap();
+        let blocked = constraints.check(at).unwrap_err();
+        assert_eq!(
+            blocked.retry_at,
+            Some(Local.with_ymd_and_hms(2024, 4, 7, 5, 0, 0).unwrap())
+        );
+
+        let at = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let blocked = constraints.check(at + chrono::Duration::milliseconds(100)).unwrap_err();
+        assert_eq!(
+            blocked.retry_at,
+            Some(at + chrono::Duration::milliseconds(500))
+        );
+    }
+
+    #[cfg(feature = "sched.rule-cron")]
+    #[test]
+    fn test_blackout_of_a_cron_pattern() {
+        use crate::sched::cron;
+
+        let mut constraints = TaskConstraints::new(
+            vec![Constraint::Blackout(cron("* 2 * * 0").unwrap())],
+            BlockedRunPolicy::Defer,
+        );
+
+        // sunday, 02:30
+        let at = Local.with_ymd_and_hms(2024, 4, 7, 2, 30, 0).unwrap();
+        let blocked = constraints.check(at).unwrap_err();
+        assert_eq!(
+            blocked.retry_at,
+            Some(Local.with_ymd_and_hms(2024, 4, 7, 3, 0, 0).unwrap())
+        );
+    }
+
+    #[cfg(feature = "sched.rule-recurrence")]
+    #[test]
+    fn test_allow_window() {
+        use crate::sched::{range, recur, ruleset};
+
+        let mut window = ruleset();
+        window.hours_rule(range(2, 4, 1));
+
+        let mut constraints = TaskConstraints::new(
+            vec![Constraint::AllowWindow(recur(&window))],
+            BlockedRunPolicy::Defer,
+        );
+
+        let inside = Local.with_ymd_and_hms(2024, 4, 7, 3, 15, 0).unwrap();
+        assert!(constraints.check(inside).is_ok());
+
+        let outside = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let blocked = constraints.check(outside).unwrap_err();
+        assert_eq!(
+            blocked.retry_at,
+            Some(Local.with_ymd_and_hms(2024, 4, 8, 2, 0, 0).unwrap())
+        );
+    }
+}
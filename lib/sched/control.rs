@@ -0,0 +1,277 @@
+mod client;
+mod protocol;
+mod server;
+
+pub use {
+    client::ControlClient,
+    protocol::{ControlRequest, ControlResponse, TaskInfo, TaskRef, TaskState},
+    server::{serve_control, ControlServer},
+};
+
+#[cfg(unix)]
+use std::{
+    fs,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+};
+
+use {
+    eyre::{eyre, Result},
+    std::{
+        fmt,
+        io::{self, Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        str::FromStr,
+        time::Duration,
+    },
+};
+
+/// 🧉 » control endpoint
+///
+/// where a control server listens, and where its clients connect to.
+///
+/// tcp endpoints must be loopback addresses, since the control protocol has no authentication.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ControlEndpoint {
+    /// a loopback tcp address, like `127.0.0.1:7070`
+    Tcp(SocketAddr),
+    /// a unix domain socket
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl ControlEndpoint {
+    /// binds a listener to the endpoint
+    pub(crate) fn bind(&self) -> Result<ControlListener> {
+        match self {
+            ControlEndpoint::Tcp(addr) => {
+                if !addr.ip().is_loopback() {
+                    return Err(eyre!(
+                        "the control server can only listen at loopback addresses, not at {}",
+                        addr
+                    ));
+                }
+
+                Ok(ControlListener::Tcp(TcpListener::bind(addr)?))
+            }
+
+            #[cfg(unix)]
+            ControlEndpoint::Unix(path) => {
+                match fs::symlink_metadata(path) {
+                    // a socket nobody answers at is a leftover from a server that didn't shut down
+                    Ok(metadata) if metadata.file_type().is_socket() => {
+                        if UnixStream::connect(path).is_err() {
+                            fs::remove_file(path)?;
+                        }
+                    }
+                    Ok(_) => {
+                        return Err(eyre!(
+                            "can't listen at {}: the path exists and is not a unix socket",
+                            path.display()
+                        ));
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(err.into()),
+                }
+
+                Ok(ControlListener::Unix(UnixListener::bind(path)?))
+            }
+        }
+    }
+
+    /// connects to the endpoint
+    pub(crate) fn connect(&self) -> io::Result<ControlStream> {
+        match self {
+            ControlEndpoint::Tcp(addr) => Ok(ControlStream::Tcp(TcpStream::connect(addr)?)),
+            #[cfg(unix)]
+            ControlEndpoint::Unix(path) => Ok(ControlStream::Unix(UnixStream::connect(path)?)),
+        }
+    }
+}
+
+impl FromStr for ControlEndpoint {
+    type Err = eyre::Report;
+
+    /// parses an endpoint: socket addresses (like `127.0.0.1:7070`) are tcp endpoints, anything
+    /// else is the path of a unix domain socket
+    fn from_str(value: &str) -> Result<Self> {
+        if let Ok(addr) = value.parse::<SocketAddr>() {
+            return Ok(ControlEndpoint::Tcp(addr));
+        }
+
+        #[cfg(unix)]
+        return Ok(ControlEndpoint::Unix(PathBuf::from(value)));
+
+        #[cfg(not(unix))]
+        Err(eyre!("invalid control endpoint `{}`", value))
+    }
+}
+
+impl fmt::Display for ControlEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlEndpoint::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(unix)]
+            ControlEndpoint::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// **a listener bound to a control endpoint**
+pub(crate) enum ControlListener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl ControlListener {
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            ControlListener::Tcp(listener) => listener.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            ControlListener::Unix(listener) => listener.set_nonblocking(nonblocking),
+        }
+    }
+
+    pub(crate) fn accept(&self) -> io::Result<ControlStream> {
+        match self {
+            ControlListener::Tcp(listener) => {
+                listener.accept().map(|(stream, _)| ControlStream::Tcp(stream))
+            }
+            #[cfg(unix)]
+            ControlListener::Unix(listener) => {
+                listener.accept().map(|(stream, _)| ControlStream::Unix(stream))
+            }
+        }
+    }
+
+    /// returns the endpoint the listener is bound to (with the actual port for tcp)
+    pub(crate) fn endpoint(&self, requested: &ControlEndpoint) -> io::Result<ControlEndpoint> {
+        match self {
+            ControlListener::Tcp(listener) => Ok(ControlEndpoint::Tcp(listener.local_addr()?)),
+            #[cfg(unix)]
+            ControlListener::Unix(_) => Ok(requested.clone()),
+        }
+    }
+}
+
+/// **a connection to a control endpoint**
+pub(crate) enum ControlStream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl ControlStream {
+    pub(crate) fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            ControlStream::Tcp(stream) => stream.try_clone().map(ControlStream::Tcp),
+            #[cfg(unix)]
+            ControlStream::Unix(stream) => stream.try_clone().map(ControlStream::Unix),
+        }
+    }
+
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            ControlStream::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            ControlStream::Unix(stream) => stream.set_nonblocking(nonblocking),
+        }
+    }
+
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            ControlStream::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            ControlStream::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for ControlStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ControlStream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            ControlStream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for ControlStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ControlStream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            ControlStream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ControlStream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            ControlStream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        eyre::{set_hook, DefaultHandler},
+    };
+
+    fn setup_eyre() {
+        let _ = set_hook(Box::new(DefaultHandler::default_with));
+    }
+
+    #[test]
+    fn test_parse_endpoint() {
+        let endpoint: ControlEndpoint = "127.0.0.1:7070".parse().unwrap();
+        assert_eq!(
+            endpoint,
+            ControlEndpoint::Tcp(([127, 0, 0, 1], 7070).into())
+        );
+
+        #[cfg(unix)]
+        {
+            let endpoint: ControlEndpoint = "/tmp/lool.sock".parse().unwrap();
+            assert_eq!(endpoint, ControlEndpoint::Unix("/tmp/lool.sock".into()));
+        }
+    }
+
+    #[test]
+    fn test_tcp_endpoints_must_be_loopback() {
+        setup_eyre();
+        let endpoint = ControlEndpoint::Tcp(([0, 0, 0, 0], 0).into());
+        assert!(endpoint.bind().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_endpoints_only_replace_stale_sockets() {
+        setup_eyre();
+
+        let dir = std::env::temp_dir().join(format!("lool-control-bind-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // a regular file is left untouched
+        let file = dir.join("not-a-socket");
+        fs::write(&file, "keep me").unwrap();
+        let err = ControlEndpoint::Unix(file.clone()).bind().err().unwrap();
+        assert!(err.to_string().contains("is not a unix socket"));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "keep me");
+
+        // a socket nobody listens at is replaced
+        let socket = dir.join("stale.sock");
+        drop(UnixListener::bind(&socket).unwrap());
+        assert!(ControlEndpoint::Unix(socket).bind().is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+use {
+    super::{ControlEndpoint, ControlRequest, ControlResponse, ControlStream, TaskInfo, TaskRef},
+    eyre::{eyre, Result},
+    std::{
+        io::{BufRead, BufReader, Write},
+        time::Duration,
+    },
+};
+
+/// how long the client waits for the response to a request
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 🧉 » control client
+///
+/// sends requests to a control server (see `serve_control`) over a single connection.
+///
+/// **Example**
+///
+/// ```rust,ignore
+/// let mut client = ControlClient::connect(&"/run/my-daemon.sock".parse()?)?;
+/// for task in client.list()? {
+///     println!("{} {} {:?}", task.id, task.name, task.state);
+/// }
+/// client.stop("backup")?;
+/// ```
+pub struct ControlClient {
+    reader: BufReader<ControlStream>,
+    writer: ControlStream,
+}
+
+impl ControlClient {
+    /// 🧉 » connect to a control server
+    pub fn connect(endpoint: &ControlEndpoint) -> Result<Self> {
+        let stream = endpoint.connect().map_err(|err| {
+            eyre!(
+                "error connecting to control server at {}: {}",
+                endpoint,
+                err
+            )
+        })?;
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+
+        Ok(Self {
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+        })
+    }
+
+    /// 🧉 » send a request and wait for its response
+    pub fn send(&mut self, request: &ControlRequest) -> Result<ControlResponse> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.writer.flush()?;
+
+        let mut response = String::new();
+        if self.reader.read_line(&mut response)? == 0 {
+            return Err(eyre!("the control server closed the connection"));
+        }
+
+        serde_json::from_str(&response).map_err(|err| eyre!("invalid response: {}", err))
+    }
+
+    /// 🧉 » list the tasks of the scheduler
+    pub fn list(&mut self) -> Result<Vec<TaskInfo>> {
+        match self.send(&ControlRequest::List)? {
+            ControlResponse::Tasks { tasks } => Ok(tasks),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    /// 🧉 » stop a task
+    pub fn stop<T: Into<TaskRef>>(&mut self, task: T) -> Result<()> {
+        let task = task.into();
+        self.expect_ok(&ControlRequest::Stop { task })
+    }
+
+    /// 🧉 » resume a task
+    pub fn resume<T: Into<TaskRef>>(&mut self, task: T) -> Result<()> {
+        let task = task.into();
+        self.expect_ok(&ControlRequest::Resume { task })
+    }
+
+    /// 🧉 » remove a task
+    pub fn remove<T: Into<TaskRef>>(&mut self, task: T) -> Result<()> {
+        let task = task.into();
+        self.expect_ok(&ControlRequest::Remove { task })
+    }
+
+    /// 🧉 » run a task now, without waiting for it to finish
+    pub fn trigger<T: Into<TaskRef>>(&mut self, task: T) -> Result<()> {
+        let task = task.into();
+        self.expect_ok(&ControlRequest::Trigger { task })
+    }
+
+    fn expect_ok(&mut self, request: &ControlRequest) -> Result<()> {
+        match self.send(request)? {
+            ControlResponse::Ok => Ok(()),
+            response => Err(unexpected(response)),
+        }
+    }
+}
+
+/// converts an error (or an unexpected) response to an error
+fn unexpected(response: ControlResponse) -> eyre::Report {
+    match response {
+        ControlResponse::Error { message } => eyre!(message),
+        response => eyre!("unexpected response: {:?}", response),
+    }
+}
@@ -0,0 +1,189 @@
+use {
+    crate::sched::task_handler::TaskHandler,
+    eyre::{eyre, Result},
+    serde::{Deserialize, Serialize},
+    std::fmt,
+};
+
+/// 🧉 » task reference
+///
+/// identifies a task in a control request, either by name or by id. In json, names are strings
+/// and ids are numbers (`"task": "backup"` or `"task": 3`).
+///
+/// when several tasks share a name, the name refers to the latest scheduled one (like
+/// `Scheduler::get`).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TaskRef {
+    Id(u64),
+    Name(String),
+}
+
+impl TaskRef {
+    /// finds the referenced task among `tasks`
+    pub(crate) fn find(&self, tasks: &[TaskHandler]) -> Result<TaskHandler> {
+        let found = match self {
+            TaskRef::Id(id) => tasks.iter().find(|task| task.id().value() == *id),
+            TaskRef::Name(name) => tasks.iter().rev().find(|task| task.name() == name),
+        };
+
+        found.cloned().ok_or_else(|| eyre!("task {} was not found", self))
+    }
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskRef::Id(id) => write!(f, "#{}", id),
+            TaskRef::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<&str> for TaskRef {
+    fn from(name: &str) -> Self {
+        TaskRef::Name(name.to_string())
+    }
+}
+
+impl From<u64> for TaskRef {
+    fn from(id: u64) -> Self {
+        TaskRef::Id(id)
+    }
+}
+
+/// 🧉 » control request
+///
+/// a command sent to a control server, serialized as a single json line like
+/// `{"command":"stop","task":"backup"}`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// lists every task in the scheduler (`Scheduler::tasks`)
+    List,
+    /// stops a task (`Scheduler::stop`)
+    Stop { task: TaskRef },
+    /// resumes a stopped task (`Scheduler::resume`)
+    Resume { task: TaskRef },
+    /// removes a task for good (`Scheduler::remove`)
+    Remove { task: TaskRef },
+    /// runs a task now, without waiting for it to finish (`Scheduler::trigger`)
+    Trigger { task: TaskRef },
+}
+
+/// 🧉 » task state
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Active,
+    Stopped,
+    Removed,
+}
+
+/// 🧉 » task info
+///
+/// summary of a task, as listed by a control server. Dates are in RFC 3339 format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub id: u64,
+    pub name: String,
+    pub state: TaskState,
+    pub running: bool,
+    pub tags: Vec<String>,
+    pub last_run: Option<String>,
+    pub next_run: Option<String>,
+}
+
+impl From<&TaskHandler> for TaskInfo {
+    fn from(handler: &TaskHandler) -> Self {
+        let state = if handler.is_removed() {
+            TaskState::Removed
+        } else if handler.is_stopped() {
+            TaskState::Stopped
+        } else {
+            TaskState::Active
+        };
+
+        Self {
+            id: handler.id().value(),
+            name: handler.name().to_string(),
+            state,
+            running: handler.is_running(),
+            tags: handler.tags().iter().cloned().collect(),
+            last_run: handler.get_last_run().map(|date| date.to_rfc3339()),
+            next_run: handler.get_next_run().map(|date| date.to_rfc3339()),
+        }
+    }
+}
+
+/// 🧉 » control response
+///
+/// the answer of a control server to a request, serialized as a single json line like
+/// `{"status":"ok"}`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ControlResponse {
+    /// the command succeeded
+    Ok,
+    /// the tasks of the scheduler, answering a `List` request
+    Tasks { tasks: Vec<TaskInfo> },
+    /// the command failed
+    Error { message: String },
+}
+
+impl ControlResponse {
+    /// creates a `Tasks` response from some task handlers
+    pub(crate) fn tasks(handlers: &[TaskHandler]) -> Self {
+        ControlResponse::Tasks {
+            tasks: handlers.iter().map(TaskInfo::from).collect(),
+        }
+    }
+
+    /// creates an `Error` response
+    pub(crate) fn error<M: ToString>(message: M) -> Self {
+        ControlResponse::Error {
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<Result<()>> for ControlResponse {
+    fn from(result: Result<()>) -> Self {
+        match result {
+            Ok(()) => ControlResponse::Ok,
+            Err(err) => ControlResponse::error(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_json() {
+        let request = ControlRequest::Stop {
+            task: "backup".into(),
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(json, r#"{"command":"stop","task":"backup"}"#);
+
+        let request: ControlRequest =
+            serde_json::from_str(r#"{"command":"trigger","task":3}"#).unwrap();
+        assert_eq!(request, ControlRequest::Trigger { task: 3.into() });
+
+        let request: ControlRequest = serde_json::from_str(r#"{"command":"list"}"#).unwrap();
+        assert_eq!(request, ControlRequest::List);
+
+        assert!(serde_json::from_str::<ControlRequest>(r#"{"command":"explode"}"#).is_err());
+    }
+
+    #[test]
+    fn test_response_json() {
+        let json = serde_json::to_string(&ControlResponse::Ok).unwrap();
+        assert_eq!(json, r#"{"status":"ok"}"#);
+
+        let json = serde_json::to_string(&ControlResponse::error("nope")).unwrap();
+        assert_eq!(json, r#"{"status":"error","message":"nope"}"#);
+    }
+}
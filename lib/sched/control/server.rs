@@ -0,0 +1,227 @@
+use {
+    super::{ControlEndpoint, ControlRequest, ControlResponse, ControlStream},
+    eyre::{eyre, Result},
+    log::debug,
+    std::{
+        io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread::{self, JoinHandle},
+        time::Duration,
+    },
+};
+
+/// how long the server waits between two checks for new connections, or for the stop flag
+/// while a connection is idle
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// max length of a request, without its trailing new line
+const MAX_REQUEST_LEN: usize = 64 * 1024;
+
+/// 🧉 » control server
+///
+/// answers control requests sent as line-delimited json. Returned by `serve_control`.
+///
+/// the server runs in its own thread until it's shut down or dropped. Unix sockets are removed
+/// when the server stops.
+pub struct ControlServer {
+    endpoint: ControlEndpoint,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ControlServer {
+    /// 🧉 » returns the endpoint the server is listening at
+    ///
+    /// useful when binding to port `0`, to get the port picked by the OS.
+    pub fn endpoint(&self) -> &ControlEndpoint {
+        &self.endpoint
+    }
+
+    /// 🧉 » stop the server
+    ///
+    /// open connections are closed once they finish answering their current request.
+    pub fn shutdown(mut self) {
+        self.stop_thread();
+    }
+
+    fn stop_thread(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+
+            #[cfg(unix)]
+            if let ControlEndpoint::Unix(path) = &self.endpoint {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+/// 🧉 » serve a control interface
+///
+/// listens at `endpoint` and answers each request line with a response line, both in json (see
+/// `ControlRequest` and `ControlResponse`). Each connection can send any number of requests.
+///
+/// requests are passed to `handler`, usually a closure forwarding them to `Scheduler::control`.
+/// Connections are served in their own threads, but the handler is called for one request at a
+/// time.
+///
+/// **Example**
+///
+/// ```rust,ignore
+/// let sched = Arc::new(Mutex::new(Scheduler::new()));
+/// let control = sched.clone();
+/// let endpoint = "/run/my-daemon.sock".parse()?;
+/// let server = serve_control(endpoint, move |request| control.lock().unwrap().control(request))?;
+/// ```
+pub fn serve_control<F>(endpoint: ControlEndpoint, handler: F) -> Result<ControlServer>
+where
+    F: FnMut(ControlRequest) -> ControlResponse + Send + 'static,
+{
+    let listener = endpoint
+        .bind()
+        .map_err(|err| eyre!("error binding control server at {}: {}", endpoint, err))?;
+    let endpoint = listener.endpoint(&endpoint)?;
+
+    // a non-blocking listener lets the thread check the stop flag between connections
+    listener.set_nonblocking(true)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let handler = Arc::new(Mutex::new(handler));
+    let thread_endpoint = endpoint.clone();
+
+    let thread = thread::spawn(move || {
+        while !thread_stop.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok(stream) => {
+                    let handler = handler.clone();
+                    let stop = thread_stop.clone();
+
+                    thread::spawn(move || {
+                        if let Err(err) = serve_connection(stream, handler, stop) {
+                            debug!("error serving control connection: {}", err);
+                        }
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(err) => debug!("error accepting control connection: {}", err),
+            }
+        }
+
+        debug!("control server at {} has stopped", thread_endpoint);
+    });
+
+    debug!("control server listening at {}", endpoint);
+
+    Ok(ControlServer {
+        endpoint,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+/// **answers the requests of a connection until it's closed or the server stops**
+fn serve_connection<F>(
+    stream: ControlStream,
+    handler: Arc<Mutex<F>>,
+    stop: Arc<AtomicBool>,
+) -> io::Result<()>
+where
+    F: FnMut(ControlRequest) -> ControlResponse,
+{
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = vec![];
+
+    while !stop.load(Ordering::SeqCst) {
+        // reads up to the new line of a request of the max length
+        let limit = (MAX_REQUEST_LEN + 1).saturating_sub(line.len()) as u64;
+        let read = match (&mut reader).take(limit).read_until(b'\n', &mut line) {
+            Ok(read) => read,
+            // the bytes read so far stay in `line` until the rest of it arrives
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                continue
+            }
+            Err(err) => return Err(err),
+        };
+
+        if line.len() > MAX_REQUEST_LEN && !line.ends_with(b"\n") {
+            let response =
+                ControlResponse::error(format!("request too long (max {} bytes)", MAX_REQUEST_LEN));
+            return write_response(&mut writer, &response);
+        }
+
+        if !line.trim_ascii().is_empty() {
+            let response = match serde_json::from_slice::<ControlRequest>(&line) {
+                Ok(request) => (handler.lock().unwrap())(request),
+                Err(err) => ControlResponse::error(format!("invalid request: {}", err)),
+            };
+
+            write_response(&mut writer, &response)?;
+        }
+
+        line.clear();
+
+        // the connection was closed
+        if read == 0 {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// writes a response as a line of json
+fn write_response(writer: &mut ControlStream, response: &ControlResponse) -> io::Result<()> {
+    let mut response = serde_json::to_vec(response).map_err(io::Error::other)?;
+    response.push(b'\n');
+    writer.write_all(&response)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::net::{Ipv4Addr, TcpStream},
+    };
+
+    #[test]
+    fn test_requests_too_long_close_the_connection() {
+        let endpoint = ControlEndpoint::Tcp((Ipv4Addr::LOCALHOST, 0).into());
+        let server = serve_control(endpoint, |_| ControlResponse::error("unexpected")).unwrap();
+        let addr = match server.endpoint() {
+            ControlEndpoint::Tcp(addr) => *addr,
+            #[cfg(unix)]
+            ControlEndpoint::Unix(_) => unreachable!(),
+        };
+
+        // a request that never ends
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(&vec![b'x'; MAX_REQUEST_LEN + 1]).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(
+            response.contains("request too long"),
+            "response: {}",
+            response
+        );
+
+        server.shutdown();
+    }
+}
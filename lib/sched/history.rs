@@ -0,0 +1,173 @@
+use {
+    super::metrics::RunMetrics,
+    chrono::{DateTime, Local},
+    std::{
+        any::Any,
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// max number of records kept in the run history of each task
+pub(crate) const HISTORY_CAPACITY: usize = 64;
+
+/// 🧉 » run trigger
+///
+/// what caused a run of a task
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunTrigger {
+    /// the run was caused by one of the task's scheduling rules
+    Scheduled,
+    /// the run was requested manually (`Scheduler::trigger` or `TaskHandler::run_now`)
+    Manual,
+}
+
+/// 🧉 » run outcome
+///
+/// how a run of a task ended
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// the action ran until completion
+    Completed,
+    /// the action didn't run, for the given reason
+    Skipped(String),
+    /// the action didn't finish within the timeout of the task
+    TimedOut,
+    /// the action panicked, with the panic message
+    Failed(String),
+}
+
+impl RunOutcome {
+    /// returns the outcome of an action that panicked with the given payload
+    pub(crate) fn panicked(payload: &(dyn Any + Send)) -> Self {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "the action panicked".to_string(),
+            },
+        };
+
+        RunOutcome::Failed(message)
+    }
+}
+
+/// 🧉 » run record
+///
+/// an entry in the run history of a task
+#[derive(Clone, Debug)]
+pub struct RunRecord {
+    /// what caused the run
+    pub trigger: RunTrigger,
+    /// the date the run was planned for (for manual runs, the date it was requested)
+    pub planned_at: DateTime<Local>,
+    /// the date the action actually started
+    pub started_at: DateTime<Local>,
+    /// the date the action finished
+    pub finished_at: DateTime<Local>,
+    /// how the run ended
+    pub outcome: RunOutcome,
+}
+
+/// **run history**
+///
+/// bounded list of the latest runs of a task, shared between the scheduler and the task handlers.
+///
+/// it also keeps the metrics of every run of the task, which are not bounded by the capacity of
+/// the history.
+#[derive(Clone, Default)]
+pub(crate) struct RunHistory {
+    records: Arc<Mutex<VecDeque<RunRecord>>>,
+    metrics: Arc<Mutex<RunMetrics>>,
+}
+
+impl RunHistory {
+    /// adds a record to the history, dropping the oldest one if the history is full
+    pub(crate) fn push(&self, record: RunRecord) {
+        self.metrics.lock().unwrap().observe(&record);
+        let mut records = self.records.lock().unwrap();
+
+        if records.len() == HISTORY_CAPACITY {
+            records.pop_front();
+        }
+
+        records.push_back(record);
+    }
+
+    /// records a run that has just finished, returning its outcome
+    pub(crate) fn record(
+        &self,
+        trigger: RunTrigger,
+        planned_at: DateTime<Local>,
+        started_at: DateTime<Local>,
+        outcome: RunOutcome,
+    ) -> RunOutcome {
+        self.push(RunRecord {
+            trigger,
+            planned_at,
+            started_at,
+            finished_at: Local::now(),
+            outcome: outcome.clone(),
+        });
+
+        outcome
+    }
+
+    /// returns a copy of the records, from the oldest to the newest
+    pub(crate) fn records(&self) -> Vec<RunRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// returns the newest record, if any
+    pub(crate) fn last(&self) -> Option<RunRecord> {
+        self.records.lock().unwrap().back().cloned()
+    }
+
+    /// returns a copy of the metrics of the task
+    pub(crate) fn metrics(&self) -> RunMetrics {
+        self.metrics.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(trigger: RunTrigger) -> RunRecord {
+        let now = Local::now();
+        RunRecord {
+            trigger,
+            planned_at: now,
+            started_at: now,
+            finished_at: now,
+            outcome: RunOutcome::Completed,
+        }
+    }
+
+    #[test]
+    fn test_history_keeps_order() {
+        let history = RunHistory::default();
+        history.push(record(RunTrigger::Scheduled));
+        history.push(record(RunTrigger::Manual));
+
+        let records = history.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].trigger, RunTrigger::Scheduled);
+        assert_eq!(records[1].trigger, RunTrigger::Manual);
+        assert_eq!(history.last().unwrap().trigger, RunTrigger::Manual);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let history = RunHistory::default();
+
+        for _ in 0..HISTORY_CAPACITY {
+            history.push(record(RunTrigger::Scheduled));
+        }
+        history.push(record(RunTrigger::Manual));
+
+        let records = history.records();
+        assert_eq!(records.len(), HISTORY_CAPACITY);
+        assert_eq!(records.last().unwrap().trigger, RunTrigger::Manual);
+    }
+}
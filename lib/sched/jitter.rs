@@ -0,0 +1,183 @@
+use {
+    super::utils::fnv1a,
+    chrono::{DateTime, Local},
+    std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+        time::Duration,
+    },
+};
+
+/// 🧉 » jitter mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JitterMode {
+    /// each occurrence gets its own random delay within the window
+    Random,
+    /// the task name is hashed into the window, so the task always gets the same delay, even
+    /// across restarts
+    Hashed,
+}
+
+/// 🧉 » jitter
+///
+/// delays each run of a task by an amount of time within `[0, window)`, so tasks sharing the same
+/// rules don't all fire at the same instant.
+///
+/// the window should be shorter than the interval between two occurrences of the task, otherwise
+/// some occurrences could be skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Jitter {
+    window: Duration,
+    mode: JitterMode,
+}
+
+impl Jitter {
+    /// 🧉 » random jitter
+    ///
+    /// each occurrence is delayed by a random amount of time within the window
+    pub fn random(window: Duration) -> Self {
+        Self {
+            window,
+            mode: JitterMode::Random,
+        }
+    }
+
+    /// 🧉 » hashed jitter
+    ///
+    /// every occurrence is delayed by the same amount of time, derived from the task name
+    pub fn hashed(window: Duration) -> Self {
+        Self {
+            window,
+            mode: JitterMode::Hashed,
+        }
+    }
+
+    /// 🧉 » window
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// 🧉 » mode
+    pub fn mode(&self) -> JitterMode {
+        self.mode
+    }
+
+    /// creates the jitter of a specific task
+    pub(crate) fn for_task(&self, name: &str) -> TaskJitter {
+        let seed = match self.mode {
+            JitterMode::Hashed => fnv1a(name.as_bytes()),
+            JitterMode::Random => {
+                let mut hasher = RandomState::new().build_hasher();
+                hasher.write(name.as_bytes());
+                hasher.finish()
+            }
+        };
+
+        TaskJitter {
+            window_ms: self.window.as_millis().min(i64::MAX as u128) as i64,
+            seed,
+            per_occurrence: self.mode == JitterMode::Random,
+        }
+    }
+}
+
+/// **jitter of a task**
+///
+/// a `Jitter` bound to a task, able to compute the delay of each occurrence of the task.
+///
+/// the delay only depends on the seed and the occurrence, so asking for the delay of the same
+/// occurrence twice always gives the same result.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TaskJitter {
+    window_ms: i64,
+    seed: u64,
+    per_occurrence: bool,
+}
+
+impl TaskJitter {
+    /// returns the window of the jitter
+    pub(crate) fn window(&self) -> chrono::Duration {
+        chrono::Duration::milliseconds(self.window_ms)
+    }
+
+    /// returns the delay for the given occurrence
+    pub(crate) fn offset(&self, occurrence: DateTime<Local>) -> chrono::Duration {
+        if self.window_ms <= 0 {
+            return chrono::Duration::zero();
+        }
+
+        let value = if self.per_occurrence {
+            mix(self.seed ^ occurrence.timestamp_millis() as u64)
+        } else {
+            mix(self.seed)
+        };
+
+        chrono::Duration::milliseconds((value % self.window_ms as u64) as i64)
+    }
+
+    /// returns the jittered date of the given occurrence
+    pub(crate) fn apply(&self, occurrence: DateTime<Local>) -> DateTime<Local> {
+        occurrence + self.offset(occurrence)
+    }
+}
+
+/// splitmix64 finalizer, spreads the bits of `value`
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, chrono::TimeZone};
+
+    #[test]
+    fn test_hashed_jitter_is_stable() {
+        let date = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let other_date = Local.with_ymd_and_hms(2024, 4, 7, 17, 0, 0).unwrap();
+        let jitter = Jitter::hashed(Duration::from_secs(60));
+
+        let a = jitter.for_task("my-task");
+        let b = jitter.for_task("my-task");
+
+        assert_eq!(a.offset(date), b.offset(date));
+        assert_eq!(a.offset(date), a.offset(other_date));
+        assert!(a.offset(date) < chrono::Duration::seconds(60));
+    }
+
+    #[test]
+    fn test_hashed_jitter_spreads_tasks() {
+        let date = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let jitter = Jitter::hashed(Duration::from_secs(3600));
+
+        let offsets: Vec<_> =
+            (0..10).map(|i| jitter.for_task(&format!("task-{i}")).offset(date)).collect();
+
+        assert!(offsets.iter().any(|offset| *offset != offsets[0]));
+    }
+
+    #[test]
+    fn test_random_jitter_is_stable_per_occurrence() {
+        let date = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let jitter = Jitter::random(Duration::from_secs(60)).for_task("my-task");
+
+        assert_eq!(jitter.offset(date), jitter.offset(date));
+
+        for minute in 0..60 {
+            let occurrence = date + chrono::Duration::minutes(minute);
+            let offset = jitter.offset(occurrence);
+            assert!(offset >= chrono::Duration::zero());
+            assert!(offset < chrono::Duration::seconds(60));
+        }
+    }
+
+    #[test]
+    fn test_zero_window() {
+        let date = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let jitter = Jitter::random(Duration::ZERO).for_task("my-task");
+
+        assert_eq!(jitter.apply(date), date);
+    }
+}
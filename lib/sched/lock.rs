@@ -0,0 +1,323 @@
+use {
+    super::utils::fnv1a,
+    chrono::{DateTime, Local},
+    eyre::{eyre, Result},
+    log::debug,
+    std::{
+        fs::{self, OpenOptions},
+        io::{ErrorKind, Write},
+        path::{Path, PathBuf},
+        process,
+        time::Duration,
+    },
+};
+
+/// content of a lock file whose holder has finished the run
+const RELEASED: &str = "released";
+
+/// 🧉 » lock provider
+///
+/// a lock that is acquired before each scheduled run of a task, so that only one scheduler (e.g.
+/// one of several processes running the same binary) runs each occurrence.
+///
+/// the lock is identified by the task name and the date of the occurrence. Once an occurrence has
+/// been acquired by a holder, it should not be acquired again by anyone else, even after it has
+/// been released.
+pub trait LockProvider: Send + Sync {
+    /// tries to acquire the lock of an occurrence of a task.
+    ///
+    /// returns `Ok(true)` if the lock was acquired and the task should run, or `Ok(false)` if
+    /// the occurrence is (or was) held by someone else.
+    fn try_acquire(&self, task: &str, occurrence: DateTime<Local>) -> Result<bool>;
+
+    /// releases the lock of an occurrence of a task, once the run is finished.
+    fn release(&self, task: &str, occurrence: DateTime<Local>) -> Result<()>;
+}
+
+/// 🧉 » file lock provider
+///
+/// a `LockProvider` based on lock files stored in a directory shared by all the processes.
+///
+/// each occurrence of a task gets its own lock file, created atomically by the first process that
+/// tries to run it. The file holds a lease: if the holder crashes and never releases the lock,
+/// other processes can take the occurrence over once the lease has expired. Because of that, the
+/// lease should be longer than the longest expected run of the tasks.
+///
+/// takeovers are serialized with an advisory lock (`flock`) on a `.takeover` file next to the lock
+/// file, so only one process can take an abandoned occurrence over.
+///
+/// lock files of past occurrences are removed once their lease has expired.
+pub struct FileLockProvider {
+    dir: PathBuf,
+    lease: Duration,
+}
+
+impl FileLockProvider {
+    /// 🧉 » create a new `FileLockProvider`
+    ///
+    /// lock files are stored in `dir`, which is created if it doesn't exist. The default lease is
+    /// one hour.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .map_err(|err| eyre!("failed to create lock directory {}: {}", dir.display(), err))?;
+
+        Ok(Self {
+            dir,
+            lease: Duration::from_secs(3600),
+        })
+    }
+
+    /// 🧉 » set the duration of the lease of each lock
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// returns the prefix of the lock files of a task
+    fn task_prefix(&self, task: &str) -> String {
+        let safe_name: String = task
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        format!("{}-{:016x}@", safe_name, fnv1a(task.as_bytes()))
+    }
+
+    /// returns the path of the lock file of an occurrence
+    fn lock_path(&self, task: &str, occurrence: DateTime<Local>) -> PathBuf {
+        let file_name = format!(
+            "{}{}.lock",
+            self.task_prefix(task),
+            occurrence.timestamp_millis()
+        );
+        self.dir.join(file_name)
+    }
+
+    /// returns the content of a lock file held by this process
+    fn holder(&self) -> String {
+        let expires_at = Local::now().timestamp_millis() + self.lease.as_millis() as i64;
+        format!("{} {}", process::id(), expires_at)
+    }
+
+    /// creates the lock file, failing if it already exists
+    fn create(&self, path: &Path) -> Result<bool> {
+        let file = OpenOptions::new().write(true).create_new(true).open(path);
+
+        match file {
+            Ok(mut file) => {
+                file.write_all(self.holder().as_bytes())?;
+                Ok(true)
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(false),
+            Err(err) => Err(eyre!(
+                "failed to create lock file {}: {}",
+                path.display(),
+                err
+            )),
+        }
+    }
+
+    /// returns true if the lock file was left by a holder that never released it and whose lease
+    /// has expired
+    fn is_abandoned(&self, path: &Path) -> bool {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => return false,
+        };
+
+        if content == RELEASED {
+            return false;
+        }
+
+        match content.split_whitespace().nth(1).and_then(|exp| exp.parse::<i64>().ok()) {
+            Some(expires_at) => expires_at < Local::now().timestamp_millis(),
+            // a lock file that can't be parsed is either being written or corrupted
+            None => false,
+        }
+    }
+
+    /// returns true if the lease of a lock file has expired, based on its modification date
+    fn is_expired(&self, path: &Path) -> bool {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|elapsed| elapsed > self.lease)
+    }
+
+    /// removes the expired lock files of past occurrences of a task
+    fn cleanup(&self, task: &str, current: &Path) {
+        let prefix = self.task_prefix(task);
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_task_lock =
+                entry.file_name().to_str().is_some_and(|name| name.starts_with(&prefix));
+
+            if is_task_lock && path != current && self.is_expired(&path) {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+impl LockProvider for FileLockProvider {
+    fn try_acquire(&self, task: &str, occurrence: DateTime<Local>) -> Result<bool> {
+        let path = self.lock_path(task, occurrence);
+
+        if self.create(&path)? {
+            self.cleanup(task, &path);
+            return Ok(true);
+        }
+
+        if !self.is_abandoned(&path) {
+            return Ok(false);
+        }
+
+        // the holder crashed. Several processes may have seen the abandoned file, so the takeover
+        // is done by whoever gets the advisory lock first: the others find the lock file taken
+        // over once they get it
+        let takeover_path = path.with_extension("lock.takeover");
+        let takeover = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&takeover_path)
+            .map_err(|err| {
+                eyre!(
+                    "failed to open takeover file {}: {}",
+                    takeover_path.display(),
+                    err
+                )
+            })?;
+        takeover.lock()?;
+
+        if !self.is_abandoned(&path) {
+            return Ok(false);
+        }
+
+        fs::write(&path, self.holder())
+            .map_err(|err| eyre!("failed to take lock file {} over: {}", path.display(), err))?;
+        Ok(true)
+    }
+
+    fn release(&self, task: &str, occurrence: DateTime<Local>) -> Result<()> {
+        let path = self.lock_path(task, occurrence);
+
+        // the file is kept, so the occurrence can't be acquired again
+        fs::write(&path, RELEASED)
+            .map_err(|err| eyre!("failed to release lock file {}: {}", path.display(), err))
+    }
+}
+
+/// **acquires the lock of a scheduled run**
+///
+/// returns the reason to skip the run if the lock couldn't be acquired
+pub(crate) fn acquire_run_lock(
+    lock: &dyn LockProvider,
+    task: &str,
+    occurrence: DateTime<Local>,
+) -> Result<(), String> {
+    match lock.try_acquire(task, occurrence) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err("the occurrence is locked by another holder".to_string()),
+        Err(err) => Err(format!("failed to acquire the lock: {}", err)),
+    }
+}
+
+/// **releases the lock of a scheduled run**
+pub(crate) fn release_run_lock(lock: &dyn LockProvider, task: &str, occurrence: DateTime<Local>) {
+    if let Err(err) = lock.release(task, occurrence) {
+        debug!("task {}: {}", task, err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, chrono::TimeZone};
+
+    fn lock_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lool-lock-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_only_one_holder_per_occurrence() -> Result<()> {
+        let dir = lock_dir("one-holder");
+        let occurrence = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let first = FileLockProvider::new(&dir)?;
+        let second = FileLockProvider::new(&dir)?;
+
+        assert!(first.try_acquire("my task", occurrence)?);
+        assert!(!second.try_acquire("my task", occurrence)?);
+
+        // a released occurrence is not run again
+        first.release("my task", occurrence)?;
+        assert!(!second.try_acquire("my task", occurrence)?);
+
+        // other occurrences and other tasks have their own locks
+        assert!(second.try_acquire("my task", occurrence + chrono::Duration::hours(1))?);
+        assert!(second.try_acquire("my other task", occurrence)?);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_abandoned_lock_is_taken_over_once() -> Result<()> {
+        let dir = lock_dir("single-takeover");
+        let occurrence = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+
+        let crashed = FileLockProvider::new(&dir)?.with_lease(Duration::ZERO);
+        assert!(crashed.try_acquire("my-task", occurrence)?);
+        std::thread::sleep(Duration::from_millis(5));
+
+        let takeovers: Vec<_> = (0..8)
+            .map(|_| {
+                let dir = dir.clone();
+                std::thread::spawn(move || {
+                    let other = FileLockProvider::new(dir).unwrap();
+                    other.try_acquire("my-task", occurrence).unwrap()
+                })
+            })
+            .collect();
+
+        let acquired = takeovers
+            .into_iter()
+            .filter_map(|takeover| takeover.join().unwrap().then_some(()))
+            .count();
+        assert_eq!(acquired, 1);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_lease_can_be_taken_over() -> Result<()> {
+        let dir = lock_dir("expired-lease");
+        let occurrence = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let crashed = FileLockProvider::new(&dir)?.with_lease(Duration::ZERO);
+        let other = FileLockProvider::new(&dir)?;
+
+        assert!(crashed.try_acquire("my-task", occurrence)?);
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(other.try_acquire("my-task", occurrence)?);
+        assert!(!crashed.try_acquire("my-task", occurrence)?);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,417 @@
+#[cfg(feature = "sched.metrics-http")]
+mod http;
+
+#[cfg(feature = "sched.metrics-http")]
+pub use http::{handle_metrics_request, serve_metrics, MetricsServer};
+
+use {
+    super::{
+        history::{RunOutcome, RunRecord, RunTrigger},
+        task_handler::TaskId,
+    },
+    chrono::{DateTime, Local},
+    std::{fmt::Write, time::Duration},
+};
+
+/// 🧉 » upper bounds of the buckets of the duration histograms, in seconds
+pub const DURATION_BUCKETS: [f64; 11] =
+    [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// 🧉 » content type of the prometheus text exposition format
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// 🧉 » duration histogram
+///
+/// distribution of the durations of the runs of a task, using the `DURATION_BUCKETS`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DurationHistogram {
+    /// `(upper bound, count)` of each bucket, where the bound is in seconds and the count is
+    /// cumulative: it includes every run that took at most the bound
+    pub buckets: Vec<(f64, u64)>,
+    /// sum of the durations of every run, in seconds
+    pub sum: f64,
+    /// number of runs
+    pub count: u64,
+}
+
+impl Default for DurationHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: DURATION_BUCKETS.iter().map(|bound| (*bound, 0)).collect(),
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl DurationHistogram {
+    /// adds a duration to the histogram
+    pub(crate) fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+
+        for (bound, count) in self.buckets.iter_mut() {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// **metrics of the runs of a task**
+///
+/// accumulated by the `RunHistory` of the task each time a run is recorded.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RunMetrics {
+    runs: u64,
+    failures: u64,
+    skipped: u64,
+    last_duration: Option<Duration>,
+    lateness: Option<chrono::Duration>,
+    durations: DurationHistogram,
+}
+
+impl RunMetrics {
+    /// updates the metrics with a finished run
+    pub(crate) fn observe(&mut self, record: &RunRecord) {
+        if let RunOutcome::Skipped(_) = record.outcome {
+            self.skipped += 1;
+            return;
+        }
+
+        self.runs += 1;
+        if let RunOutcome::TimedOut | RunOutcome::Failed(_) = record.outcome {
+            self.failures += 1;
+        }
+
+        let duration = (record.finished_at - record.started_at).to_std().unwrap_or_default();
+        self.last_duration = Some(duration);
+        self.durations.observe(duration);
+
+        // manual runs start when they are requested, so they are never late
+        if record.trigger == RunTrigger::Scheduled {
+            self.lateness = Some(record.started_at - record.planned_at);
+        }
+    }
+}
+
+/// 🧉 » task metrics
+///
+/// metrics of a task at the moment they were taken (see `TaskHandler::metrics`).
+///
+/// only runs where the action was started are counted as runs; runs that were skipped (because
+/// of constraints or locks) are counted apart.
+#[derive(Clone, Debug)]
+pub struct TaskMetrics {
+    pub id: TaskId,
+    pub name: String,
+    /// number of runs where the action was started
+    pub runs: u64,
+    /// number of runs that didn't complete (i.e. timed out or panicked)
+    pub failures: u64,
+    /// number of runs that were skipped
+    pub skipped: u64,
+    /// duration of the latest run
+    pub last_duration: Option<Duration>,
+    /// distribution of the durations of the runs
+    pub durations: DurationHistogram,
+    /// actual start minus planned date of the latest scheduled run
+    pub lateness: Option<chrono::Duration>,
+    /// next date the task is scheduled to run
+    pub next_run: Option<DateTime<Local>>,
+}
+
+impl TaskMetrics {
+    pub(crate) fn new(
+        id: TaskId,
+        name: &str,
+        metrics: RunMetrics,
+        next_run: Option<DateTime<Local>>,
+    ) -> Self {
+        Self {
+            id,
+            name: name.to_string(),
+            runs: metrics.runs,
+            failures: metrics.failures,
+            skipped: metrics.skipped,
+            last_duration: metrics.last_duration,
+            durations: metrics.durations,
+            lateness: metrics.lateness,
+            next_run,
+        }
+    }
+
+    /// labels identifying the task in the prometheus format
+    fn labels(&self) -> String {
+        format!(
+            "task=\"{}\",id=\"{}\"",
+            escape_label(&self.name),
+            self.id.value()
+        )
+    }
+}
+
+/// 🧉 » metrics snapshot
+///
+/// metrics of every task of a scheduler, as returned by `Scheduler::metrics`.
+#[derive(Clone, Debug)]
+pub struct MetricsSnapshot {
+    /// the date the metrics were taken
+    pub taken_at: DateTime<Local>,
+    pub tasks: Vec<TaskMetrics>,
+}
+
+impl MetricsSnapshot {
+    /// 🧉 » create a snapshot from the metrics of some tasks
+    pub fn new(tasks: Vec<TaskMetrics>) -> Self {
+        Self {
+            taken_at: Local::now(),
+            tasks,
+        }
+    }
+
+    /// 🧉 » render the snapshot in the prometheus text exposition format
+    ///
+    /// every metric is prefixed with `lool_task_` and labeled with the `task` name and its `id`.
+    /// Metrics without a value (like the duration of a task that never ran) are left out.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        self.counter(
+            &mut out,
+            "runs_total",
+            "runs where the action was started",
+            |task| task.runs,
+        );
+        self.counter(
+            &mut out,
+            "failures_total",
+            "runs that timed out or panicked",
+            |task| task.failures,
+        );
+        self.counter(
+            &mut out,
+            "skipped_total",
+            "runs that were skipped",
+            |task| task.skipped,
+        );
+
+        self.gauge(
+            &mut out,
+            "last_duration_seconds",
+            "duration of the latest run",
+            |task| task.last_duration.map(|duration| duration.as_secs_f64()),
+        );
+        self.histogram(&mut out);
+        self.gauge(
+            &mut out,
+            "lateness_seconds",
+            "actual start minus planned date of the latest scheduled run",
+            |task| task.lateness.map(seconds),
+        );
+        self.gauge(
+            &mut out,
+            "next_run_timestamp_seconds",
+            "unix timestamp of the next run",
+            |task| task.next_run.map(|date| date.timestamp_millis() as f64 / 1000.0),
+        );
+
+        out
+    }
+
+    fn counter<F>(&self, out: &mut String, name: &str, help: &str, value: F)
+    where
+        F: Fn(&TaskMetrics) -> u64,
+    {
+        header(out, name, "counter", help);
+        for task in &self.tasks {
+            let _ = writeln!(
+                out,
+                "lool_task_{}{{{}}} {}",
+                name,
+                task.labels(),
+                value(task)
+            );
+        }
+    }
+
+    fn gauge<F>(&self, out: &mut String, name: &str, help: &str, value: F)
+    where
+        F: Fn(&TaskMetrics) -> Option<f64>,
+    {
+        header(out, name, "gauge", help);
+        for task in &self.tasks {
+            if let Some(value) = value(task) {
+                let _ = writeln!(out, "lool_task_{}{{{}}} {}", name, task.labels(), value);
+            }
+        }
+    }
+
+    fn histogram(&self, out: &mut String) {
+        let name = "lool_task_duration_seconds";
+        header(out, "duration_seconds", "histogram", "duration of the runs");
+
+        for task in &self.tasks {
+            let labels = task.labels();
+            let histogram = &task.durations;
+
+            for (bound, count) in &histogram.buckets {
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    name, labels, bound, count
+                );
+            }
+
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"+Inf\"}} {}",
+                name, labels, histogram.count
+            );
+            let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, histogram.sum);
+            let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, histogram.count);
+        }
+    }
+}
+
+/// writes the `HELP` and `TYPE` lines of a metric
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP lool_task_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE lool_task_{} {}", name, kind);
+}
+
+/// escapes a label value (backslashes, quotes and line feeds)
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// converts a (possibly negative) duration to seconds
+fn seconds(duration: chrono::Duration) -> f64 {
+    duration.num_microseconds().map_or(duration.num_seconds() as f64, |micros| {
+        micros as f64 / 1_000_000.0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, chrono::Duration as ChronoDuration};
+
+    fn record(
+        trigger: RunTrigger,
+        lateness_ms: i64,
+        duration_ms: i64,
+        outcome: RunOutcome,
+    ) -> RunRecord {
+        let planned_at = Local::now();
+        let started_at = planned_at + ChronoDuration::milliseconds(lateness_ms);
+
+        RunRecord {
+            trigger,
+            planned_at,
+            started_at,
+            finished_at: started_at + ChronoDuration::milliseconds(duration_ms),
+            outcome,
+        }
+    }
+
+    #[test]
+    fn test_run_metrics() {
+        let mut metrics = RunMetrics::default();
+        metrics.observe(&record(
+            RunTrigger::Scheduled,
+            20,
+            30,
+            RunOutcome::Completed,
+        ));
+        metrics.observe(&record(
+            RunTrigger::Scheduled,
+            5,
+            2_000,
+            RunOutcome::TimedOut,
+        ));
+        metrics.observe(&record(
+            RunTrigger::Scheduled,
+            10,
+            3,
+            RunOutcome::Failed("boom".to_string()),
+        ));
+        metrics.observe(&record(RunTrigger::Manual, 0, 1, RunOutcome::Completed));
+        metrics.observe(&record(
+            RunTrigger::Scheduled,
+            100,
+            0,
+            RunOutcome::Skipped("locked".to_string()),
+        ));
+
+        assert_eq!(metrics.runs, 4);
+        assert_eq!(metrics.failures, 2);
+        assert_eq!(metrics.skipped, 1);
+        assert_eq!(metrics.last_duration, Some(Duration::from_millis(1)));
+        // manual and skipped runs don't change the lateness
+        assert_eq!(metrics.lateness, Some(ChronoDuration::milliseconds(10)));
+
+        let durations = &metrics.durations;
+        assert_eq!(durations.count, 4);
+        assert_eq!(durations.buckets[0], (0.005, 2));
+        assert_eq!(durations.buckets[4], (0.1, 3));
+        assert_eq!(durations.buckets[8], (2.5, 4));
+        assert!((durations.sum - 2.034).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_to_prometheus() {
+        let mut metrics = RunMetrics::default();
+        metrics.observe(&record(
+            RunTrigger::Scheduled,
+            250,
+            50,
+            RunOutcome::Completed,
+        ));
+
+        let next_run = DateTime::from_timestamp(1_700_000_000, 0).unwrap().with_timezone(&Local);
+        let id = TaskId::generate();
+        let task = TaskMetrics::new(id, "say \"hi\"", metrics, Some(next_run));
+        let idle = TaskMetrics::new(TaskId::generate(), "idle", RunMetrics::default(), None);
+        let idle_labels = format!("task=\"idle\",id=\"{}\"", idle.id.value());
+
+        let text = MetricsSnapshot::new(vec![task, idle]).to_prometheus();
+        let labels = format!(r#"task="say \"hi\"",id="{}""#, id.value());
+
+        assert!(text.contains("# TYPE lool_task_runs_total counter\n"));
+        assert!(text.contains(&format!("lool_task_runs_total{{{}}} 1\n", labels)));
+        assert!(text.contains(&format!("lool_task_runs_total{{{}}} 0\n", idle_labels)));
+        assert!(text.contains(&format!("lool_task_failures_total{{{}}} 0\n", labels)));
+        assert!(text.contains(&format!(
+            "lool_task_last_duration_seconds{{{}}} 0.05\n",
+            labels
+        )));
+        assert!(text.contains(&format!("lool_task_lateness_seconds{{{}}} 0.25\n", labels)));
+        assert!(text.contains(&format!(
+            "lool_task_next_run_timestamp_seconds{{{}}} 1700000000\n",
+            labels
+        )));
+
+        assert!(text.contains("# TYPE lool_task_duration_seconds histogram\n"));
+        assert!(text.contains(&format!(
+            "lool_task_duration_seconds_bucket{{{},le=\"0.025\"}} 0\n",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "lool_task_duration_seconds_bucket{{{},le=\"0.05\"}} 1\n",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "lool_task_duration_seconds_bucket{{{},le=\"+Inf\"}} 1\n",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "lool_task_duration_seconds_count{{{}}} 1\n",
+            labels
+        )));
+
+        // tasks without a value are left out of the gauge
+        assert!(!text.contains(&format!("lool_task_lateness_seconds{{{}}}", idle_labels)));
+    }
+}
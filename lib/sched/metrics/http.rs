@@ -0,0 +1,251 @@
+use {
+    super::{MetricsSnapshot, PROMETHEUS_CONTENT_TYPE},
+    eyre::{eyre, Result},
+    log::debug,
+    std::{
+        io::{self, BufRead, BufReader, ErrorKind, Write},
+        net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread::{self, JoinHandle},
+        time::Duration,
+    },
+};
+
+/// how long the server waits between two checks for new connections
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// how long a client has to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// path the metrics are served at
+const METRICS_PATH: &str = "/metrics";
+
+/// 🧉 » metrics server
+///
+/// tiny http server answering `GET /metrics` with a snapshot in the prometheus text format.
+/// Returned by `serve_metrics`.
+///
+/// the server runs in its own thread until it's shut down or dropped.
+pub struct MetricsServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// 🧉 » returns the address the server is listening at
+    ///
+    /// useful when binding to port `0`, to get the port picked by the OS.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 🧉 » stop the server
+    ///
+    /// stops accepting connections. The requests being answered finish in their own threads.
+    pub fn shutdown(mut self) {
+        self.stop_thread();
+    }
+
+    fn stop_thread(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+/// 🧉 » serve the metrics over http
+///
+/// binds to `addr` and answers `GET /metrics` requests with the snapshot returned by `snapshot`,
+/// which is called once per request. Each connection is answered in a thread of its own, so a
+/// slow or idle client doesn't hold back the other scrapers.
+///
+/// **Example**
+///
+/// ```rust,ignore
+/// let sched = Arc::new(Mutex::new(Scheduler::new()));
+/// let metrics = sched.clone();
+/// let server = serve_metrics("127.0.0.1:9090", move || metrics.lock().unwrap().metrics())?;
+/// ```
+pub fn serve_metrics<A, F>(addr: A, snapshot: F) -> Result<MetricsServer>
+where
+    A: ToSocketAddrs,
+    F: Fn() -> MetricsSnapshot + Send + 'static,
+{
+    let listener =
+        TcpListener::bind(addr).map_err(|err| eyre!("error binding metrics server: {}", err))?;
+    let addr = listener.local_addr()?;
+
+    // a non-blocking listener lets the thread check the stop flag between connections
+    listener.set_nonblocking(true)?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let snapshot = Arc::new(Mutex::new(snapshot));
+
+    let thread = thread::spawn(move || {
+        while !thread_stop.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((mut stream, _)) => {
+                    let snapshot = snapshot.clone();
+                    thread::spawn(move || {
+                        let snapshot = || (snapshot.lock().unwrap())();
+                        if let Err(err) = handle_metrics_request(&mut stream, snapshot) {
+                            debug!("error answering metrics request: {}", err);
+                        }
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(err) => debug!("error accepting metrics connection: {}", err),
+            }
+        }
+
+        debug!("metrics server at {} has stopped", addr);
+    });
+
+    debug!("metrics server listening at {}", addr);
+
+    Ok(MetricsServer {
+        addr,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+/// 🧉 » answer a metrics request
+///
+/// reads an http request from `stream` and answers it:
+/// - `GET` (or `HEAD`) `/metrics`: `200` with the snapshot in the prometheus text format.
+/// - other paths: `404`.
+/// - other methods: `405`.
+///
+/// useful to serve the metrics from an existing server loop. `snapshot` is only called for
+/// metrics requests.
+pub fn handle_metrics_request<F>(stream: &mut TcpStream, snapshot: F) -> io::Result<()>
+where
+    F: FnOnce() -> MetricsSnapshot,
+{
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let mut reader = BufReader::new(&*stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // the headers are not used, but they must be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    let (status, content_type, body) = match (method, path) {
+        ("GET" | "HEAD", METRICS_PATH) => (
+            "200 OK",
+            PROMETHEUS_CONTENT_TYPE,
+            snapshot().to_prometheus(),
+        ),
+        ("GET" | "HEAD", _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+
+    if status.starts_with("405") {
+        response.push_str("Allow: GET, HEAD\r\n");
+    }
+
+    response.push_str("\r\n");
+    if method != "HEAD" {
+        response.push_str(&body);
+    }
+
+    stream.write_all(response.as_bytes())?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::sched::{
+            metrics::{RunMetrics, TaskMetrics},
+            task_handler::TaskId,
+        },
+        std::io::Read,
+    };
+
+    fn request(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serve_metrics_on_loopback() {
+        let server = serve_metrics("127.0.0.1:0", || {
+            let task = TaskMetrics::new(TaskId::generate(), "sampler", RunMetrics::default(), None);
+            MetricsSnapshot::new(vec![task])
+        })
+        .unwrap();
+        let addr = server.local_addr();
+
+        let response = request(addr, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(PROMETHEUS_CONTENT_TYPE));
+        assert!(response.contains("lool_task_runs_total{task=\"sampler\""));
+
+        let response = request(addr, "GET /nope HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let response = request(addr, "POST /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+        server.shutdown();
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    #[test]
+    fn test_idle_connections_dont_block_other_requests() {
+        let server = serve_metrics("127.0.0.1:0", || MetricsSnapshot::new(vec![])).unwrap();
+        let addr = server.local_addr();
+
+        // a client that connects and never sends its request
+        let _idle = TcpStream::connect(addr).unwrap();
+        thread::sleep(POLL_INTERVAL * 5);
+
+        let started = std::time::Instant::now();
+        let response = request(addr, "GET /metrics HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(started.elapsed() < READ_TIMEOUT / 2);
+
+        server.shutdown();
+    }
+}
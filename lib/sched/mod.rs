@@ -1,6 +1,15 @@
 mod rules;
 pub use rules::*;
 
+pub mod cancellation;
+pub mod constraints;
+#[cfg(feature = "sched.control")]
+pub mod control;
+pub mod history;
+pub mod jitter;
+pub mod lock;
+pub mod metrics;
+pub mod options;
 pub mod scheduler;
 pub mod task_handler;
 pub mod utils;
@@ -0,0 +1,120 @@
+use {
+    super::{
+        constraints::{BlockedRunPolicy, Constraint},
+        jitter::Jitter,
+        rules::SchedulingRule,
+    },
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        time::Duration,
+    },
+};
+
+/// 🧉 » task options
+///
+/// optional settings of a scheduled task, used by `Scheduler::schedule_with_options`.
+///
+/// **Example**
+///
+/// ```rust,ignore
+/// use {
+///     lool::sched::{cron, jitter::Jitter, options::TaskOptions},
+///     std::time::Duration,
+/// };
+///
+/// let options = TaskOptions::new()
+///     .with_jitter(Jitter::hashed(Duration::from_secs(300)))
+///     .with_tag("reports")
+///     .with_metadata("owner", "billing");
+/// sched.schedule_with_options("my-task", action, vec![cron("0 * * * *")?], options);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TaskOptions {
+    pub(crate) jitter: Option<Jitter>,
+    pub(crate) tags: BTreeSet<String>,
+    pub(crate) metadata: BTreeMap<String, String>,
+    pub(crate) constraints: Vec<Constraint>,
+    pub(crate) blocked_runs: BlockedRunPolicy,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl TaskOptions {
+    /// 🧉 » create a new `TaskOptions` with the default settings
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 🧉 » delay each run of the task by an amount of time within the jitter window
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// 🧉 » add a tag to the task
+    ///
+    /// tags can be used to select tasks in bulk operations like `Scheduler::stop_where`.
+    pub fn with_tag<S: AsRef<str>>(mut self, tag: S) -> Self {
+        self.tags.insert(tag.as_ref().to_string());
+        self
+    }
+
+    /// 🧉 » add several tags to the task
+    pub fn with_tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.tags.extend(tags.into_iter().map(|tag| tag.as_ref().to_string()));
+        self
+    }
+
+    /// 🧉 » set a metadata entry of the task
+    ///
+    /// metadata is not used by the scheduler, it's just carried by the task handlers.
+    pub fn with_metadata<K: AsRef<str>, V: AsRef<str>>(mut self, key: K, value: V) -> Self {
+        self.metadata.insert(key.as_ref().to_string(), value.as_ref().to_string());
+        self
+    }
+
+    /// 🧉 » add an execution constraint, evaluated before each run of the task
+    pub fn with_constraint(mut self, constraint: Constraint) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    /// 🧉 » only allow runs while `rule` matches
+    pub fn with_allow_window(self, rule: SchedulingRule) -> Self {
+        self.with_constraint(Constraint::AllowWindow(rule))
+    }
+
+    /// 🧉 » don't allow runs while `rule` matches
+    pub fn with_blackout(self, rule: SchedulingRule) -> Self {
+        self.with_constraint(Constraint::Blackout(rule))
+    }
+
+    /// 🧉 » allow at most `max_runs` runs (scheduled or manual) within any `period` of time
+    pub fn with_max_runs(self, max_runs: usize, period: Duration) -> Self {
+        self.with_constraint(Constraint::MaxRuns { max_runs, period })
+    }
+
+    /// 🧉 » set what happens to the runs blocked by a constraint
+    ///
+    /// by default, they are skipped.
+    pub fn on_blocked_run(mut self, policy: BlockedRunPolicy) -> Self {
+        self.blocked_runs = policy;
+        self
+    }
+
+    /// 🧉 » limit how long each run of the task can take
+    ///
+    /// runs that exceed the timeout are recorded as `RunOutcome::TimedOut`.
+    ///
+    /// in the tokio scheduler, the run is aborted once the timeout expires. In the threads
+    /// scheduler, the `CancellationToken` of the run is cancelled and the task stops waiting for
+    /// the action; if the action keeps running anyway, the following runs are skipped until it
+    /// returns.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
@@ -1,23 +1,40 @@
+#[cfg(any(feature = "sched.rule-recurrence", feature = "sched.rule-cron"))]
+mod calendar;
+#[cfg(any(feature = "sched.rule-recurrence", feature = "sched.rule-cron"))]
+mod cron_field;
 #[cfg(feature = "sched.rule-recurrence")]
 mod recurrent;
 #[cfg(feature = "sched.rule-recurrence")]
-pub use self::recurrent::{many, range, ranges, ruleset, val, RecurrenceRuleSet, Rule};
+pub use self::recurrent::{
+    many, range, ranges, ruleset, val, RecurrenceField, RecurrenceRuleSet, Rule, RuleError,
+    RuleSetError,
+};
 
 #[cfg(feature = "sched.rule-cron")]
 mod cron;
 #[cfg(feature = "sched.rule-cron")]
-pub use self::cron::Cron;
+pub use self::{
+    cron::{Cron, CronBuilder, CronValidationError, DayMatching, FieldPresence},
+    cron_field::CronField,
+};
+
+#[cfg(any(feature = "sched.rule-recurrence", feature = "sched.rule-cron"))]
+pub(crate) use self::calendar::CalendarUnit;
 
 use {
-    chrono::{DateTime, Local},
-    eyre::Result,
-    std::fmt::Debug,
+    chrono::{DateTime, Local, TimeDelta, Timelike},
+    eyre::{eyre, Result},
+    std::{fmt::Debug, time::Duration},
 };
 
+/// nanoseconds in a millisecond
+const NANOS_PER_MILLI: i64 = 1_000_000;
+
 /// 🧉 » a scheduling rule
 ///
 /// can be:
 ///   - `Once`: runs only at a specific `chrono::DateTime`
+///   - `Interval`: runs every given period, aligned to the wall clock
 ///   - `Repeat`: runs at specific intervals defined by a `RecurrenceRule`
 ///   - `Cron`: runs at specific intervals defined by a cron expression
 #[derive(Clone, Debug)]
@@ -25,6 +42,14 @@ pub enum SchedulingRule {
     /// 🧉 » a scheduling rule that makes the task run only once at a specific `chrono::DateTime`
     Once(chrono::DateTime<Local>),
 
+    /// 🧉 » a scheduling rule that makes the task run every given period
+    ///
+    /// the ticks are aligned to the unix epoch instead of to the date the task was scheduled, so
+    /// periods that divide a minute (like `250ms`) always tick at the same marks of the wall
+    /// clock (`.000`, `.250`, `.500` and `.750`). Periods can be shorter than a second. A zero
+    /// period never ticks.
+    Interval(Duration),
+
     /// 🧉 » a scheduling rule expressed with a `RecurrenceRule` structure
     #[cfg(feature = "sched.rule-recurrence")]
     Repeat(RecurrenceRuleSet),
@@ -40,6 +65,27 @@ impl SchedulingRule {
         self.next_from(Local::now())
     }
 
+    /// 🧉 » check if a given `DateTime` matches the rule
+    ///
+    /// - `Once` rules match the same second as their date.
+    /// - `Interval` rules match the milliseconds in which they tick.
+    /// - `Repeat` rules match when every field set in the rule set matches the date.
+    /// - `Cron` rules match when the date matches the expression. Expressions without a seconds
+    ///   field match whole minutes.
+    pub fn matches(&self, date: DateTime<Local>) -> bool {
+        match self {
+            SchedulingRule::Once(once) => once.timestamp() == date.timestamp(),
+
+            SchedulingRule::Interval(period) => interval_matches(*period, date),
+
+            #[cfg(feature = "sched.rule-recurrence")]
+            SchedulingRule::Repeat(rule) => rule.matches(date),
+
+            #[cfg(feature = "sched.rule-cron")]
+            SchedulingRule::Cron(pattern) => pattern.matches(date),
+        }
+    }
+
     /// 🧉 » get the next execution time from now
     pub fn next_from(&self, base: DateTime<Local>) -> Option<DateTime<Local>> {
         match self {
@@ -51,13 +97,40 @@ impl SchedulingRule {
                 }
             }
 
+            SchedulingRule::Interval(period) => next_interval_tick(*period, base),
+
             #[cfg(feature = "sched.rule-recurrence")]
             SchedulingRule::Repeat(rule) => rule.next_match_from(base),
 
+            #[cfg(feature = "sched.rule-cron")]
+            SchedulingRule::Cron(pattern) => pattern.next_from(base),
+        }
+    }
+
+    /// returns the first date after `at` that the rule doesn't match, if found
+    pub(crate) fn end_of_match(&self, at: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            SchedulingRule::Once(_) => {
+                let second = at.with_nanosecond(0)?;
+                second.checked_add_signed(TimeDelta::seconds(1))
+            }
+
+            // periods of less than 2ms can match several milliseconds in a row
+            SchedulingRule::Interval(_) => {
+                let millisecond = at.with_nanosecond(at.nanosecond() / 1_000_000 * 1_000_000)?;
+                (1..=1000)
+                    .map(|millis| millisecond + TimeDelta::milliseconds(millis))
+                    .find(|date| !self.matches(*date))
+            }
+
+            #[cfg(feature = "sched.rule-recurrence")]
+            SchedulingRule::Repeat(rule) => {
+                calendar::end_of_match(at, |unit, date| rule.unit_matches(unit, date))
+            }
+
             #[cfg(feature = "sched.rule-cron")]
             SchedulingRule::Cron(pattern) => {
-                let next = pattern.find_next_occurrence(&base, false);
-                next.ok()
+                calendar::end_of_match(at, |unit, date| pattern.unit_matches(unit, date))
             }
         }
     }
@@ -80,3 +153,94 @@ pub fn recur(rule: &RecurrenceRuleSet) -> SchedulingRule {
 pub fn once(datetime: DateTime<Local>) -> SchedulingRule {
     SchedulingRule::Once(datetime)
 }
+
+/// 🧉 » create a new `SchedulingRule` that runs every `period`, aligned to the wall clock
+///
+/// fails if the period is zero. See `SchedulingRule::Interval` for the alignment of the ticks.
+pub fn every(period: Duration) -> Result<SchedulingRule> {
+    if period.is_zero() {
+        return Err(eyre!("the period of an interval rule can't be zero"));
+    }
+
+    Ok(SchedulingRule::Interval(period))
+}
+
+/// returns the period in nanoseconds, or `None` if it's zero or too long to tick
+fn period_nanos(period: Duration) -> Option<i64> {
+    i64::try_from(period.as_nanos()).ok().filter(|nanos| *nanos > 0)
+}
+
+/// returns the first tick of an interval after `base`
+fn next_interval_tick(period: Duration, base: DateTime<Local>) -> Option<DateTime<Local>> {
+    let period = period_nanos(period)?;
+    let nanos = base.timestamp_nanos_opt()?;
+    let next = nanos.div_euclid(period).checked_add(1)?.checked_mul(period)?;
+
+    Some(DateTime::from_timestamp_nanos(next).with_timezone(&Local))
+}
+
+/// returns true if an interval ticks within the millisecond of `date`
+fn interval_matches(period: Duration, date: DateTime<Local>) -> bool {
+    let (period, nanos) = match (period_nanos(period), date.timestamp_nanos_opt()) {
+        (Some(period), Some(nanos)) => (period, nanos),
+        _ => return false,
+    };
+
+    let millisecond = nanos - nanos.rem_euclid(NANOS_PER_MILLI);
+    let until_tick = (period - millisecond.rem_euclid(period)) % period;
+    until_tick < NANOS_PER_MILLI
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        chrono::TimeZone,
+        eyre::{set_hook, DefaultHandler},
+    };
+
+    fn setup_eyre() {
+        let _ = set_hook(Box::new(DefaultHandler::default_with));
+    }
+
+    fn date(second: u32, millis: i64) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 4, 7, 16, 0, second).unwrap()
+            + chrono::Duration::milliseconds(millis)
+    }
+
+    #[test]
+    fn test_interval_ticks_are_aligned() {
+        let rule = every(Duration::from_millis(250)).unwrap();
+
+        assert_eq!(rule.next_from(date(0, 0)), Some(date(0, 250)));
+        assert_eq!(rule.next_from(date(0, 249)), Some(date(0, 250)));
+        assert_eq!(rule.next_from(date(0, 251)), Some(date(0, 500)));
+        assert_eq!(rule.next_from(date(0, 999)), Some(date(1, 0)));
+
+        assert!(rule.matches(date(0, 750)));
+        assert!(rule.matches(date(0, 750) + chrono::Duration::microseconds(500)));
+        assert!(!rule.matches(date(0, 751)));
+    }
+
+    #[test]
+    fn test_interval_sub_millisecond_periods() {
+        let rule = SchedulingRule::Interval(Duration::from_micros(100));
+        let base = date(0, 0);
+
+        assert_eq!(
+            rule.next_from(base),
+            Some(base + chrono::Duration::microseconds(100))
+        );
+        assert!(rule.matches(date(0, 1)));
+    }
+
+    #[test]
+    fn test_zero_interval() {
+        setup_eyre();
+        assert!(every(Duration::ZERO).is_err());
+
+        let rule = SchedulingRule::Interval(Duration::ZERO);
+        assert_eq!(rule.next_from(date(0, 0)), None);
+        assert!(!rule.matches(date(0, 0)));
+    }
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Datelike, Local, Months, NaiveDateTime, TimeDelta, TimeZone, Timelike};
+
+/// years searched for the end of a match, before giving up
+const YEARS_SEARCH_LIMIT: u32 = 400;
+
+/// **a unit of the calendar**
+///
+/// the span of time in which a field of a date (its millisecond, second, ..., year) doesn't
+/// change. Calendar rules (`Repeat` and `Cron`) match a date when each of their fields matches
+/// the corresponding unit of the date.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CalendarUnit {
+    Millisecond,
+    Second,
+    Minute,
+    Hour,
+    /// both the day of the month and the day of the week
+    Day,
+    Month,
+    Year,
+}
+
+impl CalendarUnit {
+    pub(crate) const ALL: [CalendarUnit; 7] = [
+        CalendarUnit::Millisecond,
+        CalendarUnit::Second,
+        CalendarUnit::Minute,
+        CalendarUnit::Hour,
+        CalendarUnit::Day,
+        CalendarUnit::Month,
+        CalendarUnit::Year,
+    ];
+
+    /// returns the number of units after which every value of the field has been seen
+    fn cycle(&self) -> u32 {
+        match self {
+            CalendarUnit::Millisecond => 1000,
+            CalendarUnit::Second | CalendarUnit::Minute => 60,
+            // one more for the repeated hour of a daylight saving time change
+            CalendarUnit::Hour => 25,
+            // every day of the month and every day of the week, in any month
+            CalendarUnit::Day => 366,
+            CalendarUnit::Month => 12,
+            CalendarUnit::Year => YEARS_SEARCH_LIMIT,
+        }
+    }
+
+    /// returns the start of the unit that contains `date`
+    fn start(&self, date: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            CalendarUnit::Millisecond => {
+                date.with_nanosecond(date.nanosecond() / 1_000_000 * 1_000_000)
+            }
+            CalendarUnit::Second => date.with_nanosecond(0),
+            CalendarUnit::Minute => CalendarUnit::Second.start(date)?.with_second(0),
+            CalendarUnit::Hour => CalendarUnit::Minute.start(date)?.with_minute(0),
+            CalendarUnit::Day => date.date().and_hms_opt(0, 0, 0),
+            CalendarUnit::Month => CalendarUnit::Day.start(date)?.with_day(1),
+            CalendarUnit::Year => CalendarUnit::Month.start(date)?.with_month(1),
+        }
+    }
+
+    /// returns the start of the unit that follows the one starting at `start`
+    fn next(&self, start: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            CalendarUnit::Millisecond => start.checked_add_signed(TimeDelta::milliseconds(1)),
+            CalendarUnit::Second => start.checked_add_signed(TimeDelta::seconds(1)),
+            CalendarUnit::Minute => start.checked_add_signed(TimeDelta::minutes(1)),
+            CalendarUnit::Hour => start.checked_add_signed(TimeDelta::hours(1)),
+            CalendarUnit::Day => start.checked_add_signed(TimeDelta::days(1)),
+            CalendarUnit::Month => start.checked_add_months(Months::new(1)),
+            CalendarUnit::Year => start.checked_add_months(Months::new(12)),
+        }
+    }
+}
+
+/// **returns the first date after `at` that a calendar rule doesn't match**
+///
+/// `unit_matches` tells if the field of the rule for a unit matches a date. As the rule matches
+/// only while all of its fields do, its match ends when the first of them stops matching, and
+/// each field is searched on its own, a whole unit at a time.
+///
+/// returns `None` if the rule keeps matching for longer than the searched years.
+pub(crate) fn end_of_match<F>(at: DateTime<Local>, unit_matches: F) -> Option<DateTime<Local>>
+where
+    F: Fn(CalendarUnit, DateTime<Local>) -> bool,
+{
+    CalendarUnit::ALL
+        .iter()
+        .filter_map(|unit| {
+            let mut start = unit.start(at.naive_local())?;
+
+            (0..unit.cycle()).find_map(|_| {
+                start = unit.next(start)?;
+                // dates skipped by a daylight saving time change don't exist, so they can't end it
+                let date = Local.from_local_datetime(&start).earliest()?;
+                (!unit_matches(*unit, date)).then_some(date)
+            })
+        })
+        .min()
+}
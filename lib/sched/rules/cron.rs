@@ -1,27 +1,119 @@
+mod builder;
+mod validation;
+
+pub use {
+    builder::{CronBuilder, DayMatching, FieldPresence},
+    validation::CronValidationError,
+};
+
 use {
+    super::CalendarUnit,
+    chrono::{DateTime, Datelike, Local, TimeZone, Timelike},
     core::fmt,
     croner::Cron as Croner,
     eyre::Result,
     std::{
+        collections::BTreeSet,
         fmt::Debug,
         ops::{Deref, DerefMut},
     },
 };
 
 #[derive(Clone)]
-pub struct Cron(Croner);
+pub struct Cron {
+    pub(crate) cron: Croner,
+    pub(crate) expression: String,
+    pub(crate) has_seconds: bool,
+    pub(crate) years: Option<BTreeSet<i32>>,
+}
 
 impl Cron {
     /// 🧉 » create a new `Cron` scheduling rule
+    ///
+    /// uses the default options of `CronBuilder` (5-field patterns). Use `CronBuilder` to parse
+    /// patterns with seconds or years.
     pub fn new(pattern: &str) -> Result<Self> {
-        let cron = Croner::new(pattern).parse()?;
-        Ok(Self(cron))
+        CronBuilder::new(pattern).build()
+    }
+
+    /// 🧉 » create a new `CronBuilder` for the given pattern
+    pub fn builder(pattern: &str) -> CronBuilder {
+        CronBuilder::new(pattern)
+    }
+
+    /// 🧉 » validate a pattern
+    ///
+    /// validates a pattern with the default options of `CronBuilder`, returning every problem
+    /// found in it.
+    pub fn validate(pattern: &str) -> Result<(), Vec<CronValidationError>> {
+        CronBuilder::new(pattern).validate()
+    }
+
+    /// 🧉 » returns the pattern the rule was created from
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// 🧉 » get the next date matching the pattern after `base`
+    pub fn next_from(&self, base: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut from = base;
+        let mut inclusive = false;
+
+        loop {
+            let next = self.cron.find_next_occurrence(&from, inclusive).ok()?;
+
+            if self.is_year_allowed(next.year()) {
+                return Some(next);
+            }
+
+            // jump to the start of the next allowed year
+            let year = *self.years.as_ref()?.range(next.year() + 1..).next()?;
+            from = Local.with_ymd_and_hms(year, 1, 1, 0, 0, 0).earliest()?;
+            inclusive = true;
+        }
+    }
+
+    /// 🧉 » check if a date matches the pattern
+    ///
+    /// patterns without a seconds field match whole minutes.
+    pub fn matches(&self, date: DateTime<Local>) -> bool {
+        if !self.is_year_allowed(date.year()) {
+            return false;
+        }
+
+        let date = match self.has_seconds {
+            true => date,
+            false => date.with_second(0).unwrap_or(date),
+        };
+
+        self.cron.is_time_matching(&date).unwrap_or(false)
+    }
+
+    /// checks if the fields of the pattern in a unit of the calendar match a date
+    pub(crate) fn unit_matches(&self, unit: CalendarUnit, date: DateTime<Local>) -> bool {
+        let pattern = &self.cron.pattern;
+        let matches = match unit {
+            CalendarUnit::Millisecond => Ok(true),
+            CalendarUnit::Second if !self.has_seconds => Ok(true),
+            CalendarUnit::Second => pattern.second_match(date.second()),
+            CalendarUnit::Minute => pattern.minute_match(date.minute()),
+            CalendarUnit::Hour => pattern.hour_match(date.hour()),
+            CalendarUnit::Day => pattern.day_match(date.year(), date.month(), date.day()),
+            CalendarUnit::Month => pattern.month_match(date.month()),
+            CalendarUnit::Year => Ok(self.is_year_allowed(date.year())),
+        };
+
+        matches.unwrap_or(false)
+    }
+
+    fn is_year_allowed(&self, year: i32) -> bool {
+        self.years.as_ref().is_none_or(|years| years.contains(&year))
     }
 }
 
 impl Debug for Cron {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Cron").field("expression", &self.0.pattern).finish()
+        f.debug_struct("Cron").field("expression", &self.expression).finish()
     }
 }
 
@@ -29,12 +121,145 @@ impl Deref for Cron {
     type Target = Croner;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.cron
     }
 }
 
 impl DerefMut for Cron {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.cron
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::sched::rules::CronField,
+        eyre::{set_hook, DefaultHandler},
+    };
+
+    fn setup_eyre() {
+        let _ = set_hook(Box::new(DefaultHandler::default_with));
+    }
+
+    fn date(year: i32, month: u32, day: u32, hour: u32, min: u32, sec: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, hour, min, sec).unwrap()
+    }
+
+    #[test]
+    fn test_seconds_field() {
+        setup_eyre();
+        assert!(Cron::new("30 * * * * *").is_err());
+
+        let cron =
+            CronBuilder::new("30 * * * * *").with_seconds(FieldPresence::Optional).build().unwrap();
+        let base = date(2024, 4, 7, 16, 0, 0);
+        assert_eq!(cron.next_from(base), Some(date(2024, 4, 7, 16, 0, 30)));
+        assert!(cron.matches(date(2024, 4, 7, 16, 0, 30)));
+        assert!(!cron.matches(date(2024, 4, 7, 16, 0, 0)));
+
+        // 5-field patterns are still accepted when seconds are optional
+        let cron = Cron::builder("* * * * *").with_seconds(FieldPresence::Optional).build();
+        assert!(cron.is_ok());
+
+        let cron = Cron::builder("* * * * *").with_seconds(FieldPresence::Required).build();
+        assert!(cron.is_err());
+    }
+
+    #[test]
+    fn test_year_field() {
+        setup_eyre();
+        let cron = CronBuilder::new("0 0 1 1 * 2030,2032")
+            .with_year(FieldPresence::Optional)
+            .build()
+            .unwrap();
+
+        let base = date(2024, 4, 7, 16, 0, 0);
+        assert_eq!(cron.next_from(base), Some(date(2030, 1, 1, 0, 0, 0)));
+        assert_eq!(
+            cron.next_from(date(2030, 1, 1, 0, 0, 0)),
+            Some(date(2032, 1, 1, 0, 0, 0))
+        );
+        assert_eq!(cron.next_from(date(2032, 1, 1, 0, 0, 0)), None);
+        assert!(!cron.matches(date(2031, 1, 1, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_quartz_extensions() {
+        setup_eyre();
+        let base = date(2024, 2, 10, 12, 0, 0);
+
+        let last_day = Cron::new("0 0 L * *").unwrap();
+        assert_eq!(last_day.next_from(base), Some(date(2024, 2, 29, 0, 0, 0)));
+
+        let second_friday = Cron::new("0 0 * * FRI#2").unwrap();
+        assert_eq!(
+            second_friday.next_from(base),
+            Some(date(2024, 3, 8, 0, 0, 0))
+        );
+
+        // the 17th is a saturday, so the closest weekday is friday 16th
+        let closest_weekday = Cron::new("0 0 17W * *").unwrap();
+        assert_eq!(
+            closest_weekday.next_from(base),
+            Some(date(2024, 2, 16, 0, 0, 0))
+        );
+
+        let disabled = CronBuilder::new("0 0 L * *").with_quartz_extensions(false).build();
+        assert!(disabled.is_err());
+    }
+
+    #[test]
+    fn test_aliases() {
+        setup_eyre();
+        let base = date(2024, 4, 7, 16, 0, 0);
+        let daily = Cron::new("@daily").unwrap();
+        assert_eq!(daily.next_from(base), Some(date(2024, 4, 8, 0, 0, 0)));
+
+        assert!(CronBuilder::new("@daily").with_aliases(false).build().is_err());
+        assert!(Cron::new("@sometimes").is_err());
+    }
+
+    #[test]
+    fn test_day_matching() {
+        setup_eyre();
+        let base = date(2024, 1, 1, 0, 0, 0);
+
+        // friday 13th
+        let and =
+            CronBuilder::new("0 0 13 * 5").with_day_matching(DayMatching::And).build().unwrap();
+        assert_eq!(and.next_from(base), Some(date(2024, 9, 13, 0, 0, 0)));
+
+        // any 13th or any friday
+        let or = Cron::new("0 0 13 * 5").unwrap();
+        assert_eq!(or.next_from(base), Some(date(2024, 1, 5, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_validate() {
+        setup_eyre();
+        assert!(Cron::validate("*/5 * * * *").is_ok());
+
+        let errors = Cron::validate("0 25 32 * MON").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].position, Some(1));
+        assert_eq!(errors[0].field, Some(CronField::Hour));
+        assert_eq!(errors[0].value, "25");
+        assert_eq!(errors[1].position, Some(2));
+        assert_eq!(errors[1].field, Some(CronField::DayOfMonth));
+
+        let errors = Cron::validate("* * *").unwrap_err();
+        assert_eq!(errors[0].position, None);
+        assert_eq!(errors[0].reason, "expected 5 fields, found 3");
+
+        let errors = CronBuilder::new("0 0 * * * 1969")
+            .with_year(FieldPresence::Required)
+            .validate()
+            .unwrap_err();
+        assert_eq!(errors[0].field, Some(CronField::Year));
+
+        let err = Cron::new("0 25 * * *").unwrap_err().to_string();
+        assert!(err.contains("field 1 (hours) `25`"));
     }
 }
@@ -0,0 +1,291 @@
+use {
+    super::{
+        validation::{normalize_field, parse_years, CronValidationError},
+        Cron,
+    },
+    crate::sched::rules::CronField,
+    croner::Cron as Croner,
+    eyre::{eyre, Result},
+    std::collections::BTreeSet,
+};
+
+/// 🧉 » field presence
+///
+/// whether an optional field (seconds or year) can, or must, be part of a cron pattern.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldPresence {
+    /// the field is not allowed
+    #[default]
+    Forbidden,
+    /// the field can be omitted
+    Optional,
+    /// the field must be present
+    Required,
+}
+
+impl FieldPresence {
+    fn is_allowed(&self) -> bool {
+        *self != FieldPresence::Forbidden
+    }
+
+    fn is_required(&self) -> bool {
+        *self == FieldPresence::Required
+    }
+}
+
+/// 🧉 » day matching
+///
+/// how the day of month and day of week fields are combined when both are restricted (i.e. none
+/// of them is `*` or `?`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DayMatching {
+    /// a day matches if it matches any of the fields (like vixie cron)
+    #[default]
+    Or,
+    /// a day matches if it matches both fields
+    And,
+}
+
+/// 🧉 » cron builder
+///
+/// parses cron patterns with custom options. The defaults are the same as `Cron::new`: 5-field
+/// patterns, with aliases and quartz extensions enabled and `DayMatching::Or`.
+///
+/// the fields of a pattern are, in order: `[seconds] minutes hours day-of-month month
+/// day-of-week [year]`. When both seconds and year are optional, 6-field patterns are read as
+/// having seconds.
+///
+/// **Example**
+///
+/// ```rust,ignore
+/// use lool::sched::{CronBuilder, FieldPresence, SchedulingRule};
+///
+/// let cron = CronBuilder::new("30 0 12 L * ? 2030")
+///     .with_seconds(FieldPresence::Required)
+///     .with_year(FieldPresence::Optional)
+///     .build()?;
+/// sched.schedule("my-task", action, SchedulingRule::Cron(cron));
+/// ```
+#[derive(Clone, Debug)]
+pub struct CronBuilder {
+    pattern: String,
+    seconds: FieldPresence,
+    year: FieldPresence,
+    aliases: bool,
+    quartz: bool,
+    day_matching: DayMatching,
+}
+
+/// the pieces of a valid pattern
+pub(crate) struct ParsedPattern {
+    /// the pattern in the 6-field format understood by croner
+    pub(crate) croner_pattern: String,
+    pub(crate) has_seconds: bool,
+    pub(crate) years: Option<BTreeSet<i32>>,
+}
+
+impl CronBuilder {
+    /// 🧉 » create a new `CronBuilder` for the given pattern
+    pub fn new<S: AsRef<str>>(pattern: S) -> Self {
+        Self {
+            pattern: pattern.as_ref().to_string(),
+            seconds: FieldPresence::Forbidden,
+            year: FieldPresence::Forbidden,
+            aliases: true,
+            quartz: true,
+            day_matching: DayMatching::Or,
+        }
+    }
+
+    /// 🧉 » set whether the pattern has a leading seconds field
+    ///
+    /// without it, the tasks run at second `0`.
+    pub fn with_seconds(mut self, seconds: FieldPresence) -> Self {
+        self.seconds = seconds;
+        self
+    }
+
+    /// 🧉 » set whether the pattern has a trailing year field (from `1970` to `2099`)
+    pub fn with_year(mut self, year: FieldPresence) -> Self {
+        self.year = year;
+        self
+    }
+
+    /// 🧉 » enable or disable aliases
+    ///
+    /// `@yearly` (or `@annually`), `@monthly`, `@weekly`, `@daily` (or `@midnight`) and `@hourly`.
+    pub fn with_aliases(mut self, enabled: bool) -> Self {
+        self.aliases = enabled;
+        self
+    }
+
+    /// 🧉 » enable or disable the quartz-style extensions
+    ///
+    /// `L` (last day of the month, or last given weekday of the month, like `5L`), `W` (closest
+    /// weekday to a day of the month, like `15W`) and `#` (nth weekday of the month, like `5#2`).
+    pub fn with_quartz_extensions(mut self, enabled: bool) -> Self {
+        self.quartz = enabled;
+        self
+    }
+
+    /// 🧉 » set how the day of month and day of week fields are combined
+    pub fn with_day_matching(mut self, day_matching: DayMatching) -> Self {
+        self.day_matching = day_matching;
+        self
+    }
+
+    /// 🧉 » validate the pattern
+    ///
+    /// returns every problem found in the pattern, with the position of the field it was found
+    /// in.
+    pub fn validate(&self) -> Result<(), Vec<CronValidationError>> {
+        self.parse().map(|_| ())
+    }
+
+    /// 🧉 » build the `Cron` rule
+    pub fn build(&self) -> Result<Cron> {
+        let parsed = self.parse().map_err(|errors| {
+            let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+            eyre!(
+                "invalid cron pattern `{}`: {}",
+                self.pattern,
+                errors.join("; ")
+            )
+        })?;
+
+        let mut croner = Croner::new(&parsed.croner_pattern);
+        croner.with_seconds_required();
+        if self.day_matching == DayMatching::And {
+            croner.with_dom_and_dow();
+        }
+
+        let croner = croner
+            .parse()
+            .map_err(|err| eyre!("invalid cron pattern `{}`: {}", self.pattern, err))?;
+
+        Ok(Cron {
+            cron: croner,
+            expression: self.pattern.clone(),
+            has_seconds: parsed.has_seconds,
+            years: parsed.years,
+        })
+    }
+
+    /// splits the pattern in fields and checks each of them
+    pub(crate) fn parse(&self) -> Result<ParsedPattern, Vec<CronValidationError>> {
+        let pattern = self.pattern.trim();
+
+        if pattern.is_empty() {
+            return Err(vec![CronValidationError::pattern(
+                pattern,
+                "the pattern is empty",
+            )]);
+        }
+
+        if pattern.starts_with('@') {
+            return self.parse_alias(pattern).map_err(|err| vec![err]);
+        }
+
+        let values: Vec<&str> = pattern.split_whitespace().collect();
+        let fields = self
+            .layout(values.len())
+            .map_err(|reason| vec![CronValidationError::pattern(pattern, reason)])?;
+
+        // omitted seconds are always `0`
+        let mut croner_fields = ["0", "*", "*", "*", "*", "*"].map(String::from);
+        let mut years = None;
+        let mut errors = vec![];
+
+        for (position, (field, value)) in fields.iter().zip(&values).enumerate() {
+            match normalize_field(*field, value, self.quartz) {
+                Ok(normalized) => match field.croner_index() {
+                    Some(index) => croner_fields[index] = normalized,
+                    None => years = parse_years(&normalized).unwrap_or_default(),
+                },
+                Err(reason) => {
+                    errors.push(CronValidationError::field(position, *field, value, reason))
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(ParsedPattern {
+            croner_pattern: croner_fields.join(" "),
+            has_seconds: fields.contains(&CronField::Second),
+            years,
+        })
+    }
+
+    /// returns the fields of a pattern with `count` fields
+    fn layout(&self, count: usize) -> Result<Vec<CronField>, String> {
+        let (seconds, year) = match count {
+            5 => (false, false),
+            6 if self.seconds.is_allowed() && !self.year.is_required() => (true, false),
+            6 if self.year.is_allowed() && !self.seconds.is_required() => (false, true),
+            7 => (true, true),
+            _ => (self.seconds.is_required(), self.year.is_required()),
+        };
+
+        let is_valid = count == 5 + seconds as usize + year as usize
+            && (seconds || !self.seconds.is_required())
+            && (year || !self.year.is_required())
+            && (!seconds || self.seconds.is_allowed())
+            && (!year || self.year.is_allowed());
+
+        if !is_valid {
+            let min = 5 + self.seconds.is_required() as usize + self.year.is_required() as usize;
+            let max = 5 + self.seconds.is_allowed() as usize + self.year.is_allowed() as usize;
+            let expected = match min == max {
+                true => min.to_string(),
+                false => format!("{} to {}", min, max),
+            };
+
+            return Err(format!("expected {} fields, found {}", expected, count));
+        }
+
+        let mut fields = vec![];
+        if seconds {
+            fields.push(CronField::Second);
+        }
+        fields.extend([
+            CronField::Minute,
+            CronField::Hour,
+            CronField::DayOfMonth,
+            CronField::Month,
+            CronField::DayOfWeek,
+        ]);
+        if year {
+            fields.push(CronField::Year);
+        }
+
+        Ok(fields)
+    }
+
+    /// parses an alias like `@daily`
+    fn parse_alias(&self, pattern: &str) -> Result<ParsedPattern, CronValidationError> {
+        if !self.aliases {
+            return Err(CronValidationError::pattern(
+                pattern,
+                "aliases are not enabled",
+            ));
+        }
+
+        let croner_pattern = match pattern.to_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 0 1 1 *",
+            "@monthly" => "0 0 0 1 * *",
+            "@weekly" => "0 0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 0 * * *",
+            "@hourly" => "0 0 * * * *",
+            _ => return Err(CronValidationError::pattern(pattern, "unknown alias")),
+        };
+
+        Ok(ParsedPattern {
+            croner_pattern: croner_pattern.to_string(),
+            has_seconds: false,
+            years: None,
+        })
+    }
+}
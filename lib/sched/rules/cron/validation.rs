@@ -0,0 +1,196 @@
+use {
+    crate::sched::rules::cron_field::{parse_ranges, replace_names, CronField},
+    croner::{errors::CronError as CronerError, Cron as Croner},
+    std::{collections::BTreeSet, fmt},
+};
+
+/// 🧉 » cron validation error
+///
+/// a problem found in a cron pattern by `Cron::validate` or `CronBuilder::validate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronValidationError {
+    /// position of the field in the pattern (starting at `0`), or `None` if the error is about
+    /// the whole pattern (e.g. a wrong number of fields)
+    pub position: Option<usize>,
+    /// the field at `position`, if any
+    pub field: Option<CronField>,
+    /// the offending part of the pattern
+    pub value: String,
+    /// why the value is not valid
+    pub reason: String,
+}
+
+impl CronValidationError {
+    /// creates an error about the whole pattern
+    pub(crate) fn pattern<V: AsRef<str>, R: AsRef<str>>(value: V, reason: R) -> Self {
+        Self {
+            position: None,
+            field: None,
+            value: value.as_ref().to_string(),
+            reason: reason.as_ref().to_string(),
+        }
+    }
+
+    /// creates an error about a field of the pattern
+    pub(crate) fn field<V: AsRef<str>, R: AsRef<str>>(
+        position: usize,
+        field: CronField,
+        value: V,
+        reason: R,
+    ) -> Self {
+        Self {
+            position: Some(position),
+            field: Some(field),
+            value: value.as_ref().to_string(),
+            reason: reason.as_ref().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CronValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.position, self.field) {
+            (Some(position), Some(field)) => write!(
+                f,
+                "field {} ({}) `{}`: {}",
+                position, field, self.value, self.reason
+            ),
+            _ => write!(f, "`{}`: {}", self.value, self.reason),
+        }
+    }
+}
+
+impl std::error::Error for CronValidationError {}
+
+/// **normalizes a field of a cron pattern**
+///
+/// lowercases the field, replaces month and weekday names by their numbers and checks the field
+/// with croner. Returns the field as understood by croner, or why it's not valid.
+pub(crate) fn normalize_field(
+    field: CronField,
+    value: &str,
+    quartz: bool,
+) -> Result<String, String> {
+    let value = replace_names(field, &value.to_lowercase())?;
+
+    for c in value.chars() {
+        let allowed = c.is_ascii_digit()
+            || matches!(c, '*' | ',' | '-' | '/')
+            || (field == CronField::DayOfMonth && matches!(c, 'l' | 'w' | '?'))
+            || (field == CronField::DayOfWeek && matches!(c, 'l' | '#' | '?'));
+
+        if !allowed {
+            return Err(format!("character `{}` is not allowed in this field", c));
+        }
+
+        if !quartz && matches!(c, 'l' | 'w' | '#') {
+            return Err("`L`, `W` and `#` are not enabled".to_string());
+        }
+    }
+
+    if value.contains('?') && value != "?" {
+        return Err("`?` can't be combined with other values".to_string());
+    }
+
+    if field == CronField::DayOfWeek && value.split(',').any(|part| part == "l") {
+        return Err("`L` needs a weekday in this field (e.g. `5L`)".to_string());
+    }
+
+    let value = value.replace('?', "*");
+    if field == CronField::Year {
+        parse_years(&value)?;
+        return Ok(value);
+    }
+
+    // check the field alone, with every other field matching anything
+    let mut probe = ["0", "*", "*", "*", "*", "*"];
+    if let Some(index) = field.croner_index() {
+        probe[index] = &value;
+    }
+
+    let mut probe = Croner::new(&probe.join(" "));
+    probe.with_seconds_required().parse().map(|_| value.clone()).map_err(|err| {
+        let (min, max) = field.bounds();
+        format!("{} (allowed values: {}-{})", croner_reason(err), min, max)
+    })
+}
+
+/// **parses the year field of a cron pattern**
+///
+/// returns the allowed years, or `None` if every year is allowed.
+pub(crate) fn parse_years(value: &str) -> Result<Option<BTreeSet<i32>>, String> {
+    let ranges = match parse_ranges(CronField::Year, value)? {
+        Some(ranges) => ranges,
+        None => return Ok(None),
+    };
+
+    let years = ranges
+        .into_iter()
+        .flat_map(|(start, end, step)| (start..=end).step_by(step as usize))
+        .map(|year| year as i32)
+        .collect();
+
+    Ok(Some(years))
+}
+
+/// returns the message of a croner error
+fn croner_reason(err: CronerError) -> String {
+    let message = match err {
+        CronerError::ComponentError(message)
+        | CronerError::InvalidPattern(message)
+        | CronerError::IllegalCharacters(message) => message,
+        err => err.to_string(),
+    };
+
+    message.trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_field() {
+        assert_eq!(
+            normalize_field(CronField::Month, "JAN-Mar", true).unwrap(),
+            "1-3"
+        );
+        assert_eq!(
+            normalize_field(CronField::DayOfWeek, "fri-sun", true).unwrap(),
+            "5-7"
+        );
+        assert_eq!(
+            normalize_field(CronField::DayOfWeek, "5#2", true).unwrap(),
+            "5#2"
+        );
+        assert_eq!(
+            normalize_field(CronField::DayOfMonth, "?", true).unwrap(),
+            "*"
+        );
+        assert_eq!(
+            normalize_field(CronField::DayOfMonth, "15W", true).unwrap(),
+            "15w"
+        );
+
+        assert!(normalize_field(CronField::DayOfMonth, "L", false).is_err());
+        assert!(normalize_field(CronField::Hour, "?", true).is_err());
+        assert!(normalize_field(CronField::DayOfWeek, "L", true).is_err());
+        assert!(normalize_field(CronField::Month, "foo", true).is_err());
+
+        let reason = normalize_field(CronField::DayOfMonth, "32", true).unwrap_err();
+        assert!(reason.ends_with("(allowed values: 1-31)"));
+    }
+
+    #[test]
+    fn test_parse_years() {
+        assert_eq!(parse_years("*").unwrap(), None);
+
+        let years = parse_years("2030,2040-2042,2090/5").unwrap().unwrap();
+        let years: Vec<i32> = years.into_iter().collect();
+        assert_eq!(years, vec![2030, 2040, 2041, 2042, 2090, 2095]);
+
+        assert!(parse_years("1969").is_err());
+        assert!(parse_years("2030-2020").is_err());
+        assert!(parse_years("*/0").is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+
+/// min and max years accepted by the year field
+const YEARS: (u32, u32) = (1970, 2099);
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// `(start, end, step)` ranges of a cron field
+pub(crate) type CronRanges = Vec<(u32, u32, u32)>;
+
+/// 🧉 » cron field
+///
+/// a field of a cron pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CronField {
+    Second,
+    Minute,
+    Hour,
+    DayOfMonth,
+    Month,
+    DayOfWeek,
+    Year,
+}
+
+impl CronField {
+    /// 🧉 » returns the min and max values of the field
+    ///
+    /// the day of week goes from `0` to `7`, both being sunday.
+    pub fn bounds(&self) -> (u32, u32) {
+        match self {
+            CronField::Second | CronField::Minute => (0, 59),
+            CronField::Hour => (0, 23),
+            CronField::DayOfMonth => (1, 31),
+            CronField::Month => (1, 12),
+            CronField::DayOfWeek => (0, 7),
+            CronField::Year => YEARS,
+        }
+    }
+
+    /// returns the index of the field in the patterns understood by croner
+    #[cfg(feature = "sched.rule-cron")]
+    pub(crate) fn croner_index(&self) -> Option<usize> {
+        match self {
+            CronField::Second => Some(0),
+            CronField::Minute => Some(1),
+            CronField::Hour => Some(2),
+            CronField::DayOfMonth => Some(3),
+            CronField::Month => Some(4),
+            CronField::DayOfWeek => Some(5),
+            CronField::Year => None,
+        }
+    }
+}
+
+impl fmt::Display for CronField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CronField::Second => "seconds",
+            CronField::Minute => "minutes",
+            CronField::Hour => "hours",
+            CronField::DayOfMonth => "day of month",
+            CronField::Month => "month",
+            CronField::DayOfWeek => "day of week",
+            CronField::Year => "year",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// **replaces month and weekday names by their numbers**
+///
+/// expects a lowercase field. Sunday closes ranges like `fri-sun` as `7`.
+pub(crate) fn replace_names(field: CronField, value: &str) -> Result<String, String> {
+    let names: &[&str] = match field {
+        CronField::Month => &MONTH_NAMES,
+        CronField::DayOfWeek => &WEEKDAY_NAMES,
+        _ => return Ok(value.to_string()),
+    };
+
+    let mut result = String::new();
+    let mut word = String::new();
+
+    // the trailing space flushes the last word
+    for c in value.chars().chain([' ']) {
+        if c.is_ascii_alphabetic() {
+            word.push(c);
+            continue;
+        }
+
+        if word.len() == 3 {
+            let index = names
+                .iter()
+                .position(|name| *name == word)
+                .ok_or_else(|| format!("unknown name `{}`", word))?;
+
+            let number = match field {
+                CronField::Month => index + 1,
+                _ if index == 0 && result.ends_with('-') => 7,
+                _ => index,
+            };
+
+            result.push_str(&number.to_string());
+        } else {
+            result.push_str(&word);
+        }
+
+        word.clear();
+        if c != ' ' {
+            result.push(c);
+        }
+    }
+
+    Ok(result)
+}
+
+/// **parses a field of a cron pattern as a list of `(start, end, step)` ranges**
+///
+/// each part of the field can be `*`, a value, a range (`a-b`) or a stepped one (`a-b/s`, `*/s`
+/// or `a/s`, which goes until the max of the field). Returns `None` for `*`.
+pub(crate) fn parse_ranges(field: CronField, value: &str) -> Result<Option<CronRanges>, String> {
+    if value == "*" {
+        return Ok(None);
+    }
+
+    let (min, max) = field.bounds();
+    let parse = |number: &str| -> Result<u32, String> {
+        let parsed = number.parse::<u32>().map_err(|_| format!("invalid value `{}`", number))?;
+
+        if parsed < min || parsed > max {
+            Err(format!(
+                "{} out of bounds (allowed values: {}-{})",
+                parsed, min, max
+            ))
+        } else {
+            Ok(parsed)
+        }
+    };
+
+    let mut ranges = vec![];
+    for part in value.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step = step.parse::<u32>().map_err(|_| format!("invalid step `{}`", step))?;
+                (range, Some(step))
+            }
+            None => (part, None),
+        };
+
+        if step == Some(0) {
+            return Err("step can't be zero".to_string());
+        }
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None if step.is_some() => (parse(range)?, max),
+            None => (parse(range)?, parse(range)?),
+        };
+
+        if start > end {
+            return Err(format!(
+                "range start {} is greater than its end {}",
+                start, end
+            ));
+        }
+
+        ranges.push((start, end, step.unwrap_or(1)));
+    }
+
+    Ok(Some(ranges))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_names() {
+        assert_eq!(
+            replace_names(CronField::Month, "jan-mar,dec").unwrap(),
+            "1-3,12"
+        );
+        assert_eq!(
+            replace_names(CronField::DayOfWeek, "sun,fri-sun").unwrap(),
+            "0,5-7"
+        );
+        assert_eq!(replace_names(CronField::Hour, "1-5").unwrap(), "1-5");
+        assert!(replace_names(CronField::Month, "foo").is_err());
+    }
+
+    #[test]
+    fn test_parse_ranges() {
+        assert_eq!(parse_ranges(CronField::Hour, "*").unwrap(), None);
+        assert_eq!(
+            parse_ranges(CronField::Minute, "5,10-20/5,*/30,45/5").unwrap(),
+            Some(vec![(5, 5, 1), (10, 20, 5), (0, 59, 30), (45, 59, 5)])
+        );
+        assert_eq!(
+            parse_ranges(CronField::DayOfWeek, "5-7").unwrap(),
+            Some(vec![(5, 7, 1)])
+        );
+
+        assert!(parse_ranges(CronField::Hour, "24").is_err());
+        assert!(parse_ranges(CronField::Hour, "10-5").is_err());
+        assert!(parse_ranges(CronField::Hour, "*/0").is_err());
+        assert!(parse_ranges(CronField::Year, "1969").is_err());
+    }
+}
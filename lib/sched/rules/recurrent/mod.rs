@@ -5,6 +5,6 @@ mod rule_unit;
 mod ruleset;
 
 pub use {
-    rule_unit::{many, range, ranges, val, Rule},
-    ruleset::{builder::ruleset, RecurrenceRuleSet},
+    rule_unit::{many, range, ranges, val, Rule, RuleError},
+    ruleset::{builder::ruleset, RecurrenceField, RecurrenceRuleSet, RuleSetError},
 };
@@ -1,4 +1,4 @@
-use num_traits::PrimInt;
+use {num_traits::PrimInt, std::fmt};
 
 /// 🧉 » a recurrence rule unit
 ///
@@ -55,18 +55,106 @@ where
         }
     }
 
-    pub(crate) fn value_is_between(&self, min: T, max: T) -> bool {
+    /// 🧉 » check if the value matches this `Rule` Unit, within the `[min, max]` domain
+    ///
+    /// unlike `matches`, wrap-around ranges (where `start > end`) and their steps are computed
+    /// modulo the domain. E.g. for a weekday (`0..=6`), `range(5, 2, 1)` matches `5, 6, 0, 1, 2`,
+    /// and `range(5, 2, 2)` matches `5, 0, 2`. Values outside of the domain never match.
+    pub fn matches_within(&self, value: T, min: T, max: T) -> bool {
+        if value < min || value > max {
+            return false;
+        }
+
+        match self {
+            Rule::Val(v) => value == *v,
+            Rule::Range(start, end, step) => {
+                range_matches_within(*start, *end, *step, value, min, max)
+            }
+            Rule::Many(values) => values.contains(&value),
+            Rule::Ranges(ranges) => ranges.iter().any(|(start, end, step)| {
+                range_matches_within(*start, *end, *step, value, min, max)
+            }),
+        }
+    }
+
+    /// checks that every value of the rule is within `[min, max]`
+    pub(crate) fn value_is_between(&self, min: T, max: T) -> Result<(), RuleError> {
+        let check = |value: T| {
+            if value >= min && value <= max {
+                Ok(())
+            } else {
+                Err(RuleError::OutOfBounds {
+                    value: to_i64(value),
+                    min: to_i64(min),
+                    max: to_i64(max),
+                })
+            }
+        };
+
+        match self {
+            Rule::Val(v) => check(*v),
+            Rule::Range(start, end, _) => check(*start).and_then(|_| check(*end)),
+            Rule::Many(values) if values.is_empty() => Err(RuleError::Empty),
+            Rule::Many(values) => values.iter().try_for_each(|v| check(*v)),
+            Rule::Ranges(ranges) if ranges.is_empty() => Err(RuleError::Empty),
+            Rule::Ranges(ranges) => ranges
+                .iter()
+                .try_for_each(|(start, end, _)| check(*start).and_then(|_| check(*end))),
+        }
+    }
+}
+
+/// 🧉 » rule error
+///
+/// why a `Rule` is not valid for its field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    /// a value of the rule is outside of the domain of the field
+    OutOfBounds { value: i64, min: i64, max: i64 },
+    /// the rule is an empty `Many` or `Ranges`, so it can't match anything
+    Empty,
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Rule::Val(v) => *v >= min && *v <= max,
-            Rule::Range(start, end, _) => *start >= min && *end <= max,
-            Rule::Many(values) => values.iter().all(|v| *v >= min && *v <= max),
-            Rule::Ranges(ranges) => {
-                ranges.iter().all(|(start, end, _)| *start >= min && *end <= max)
+            RuleError::OutOfBounds { value, min, max } => {
+                write!(
+                    f,
+                    "value {} is out of bounds (allowed values: {}-{})",
+                    value, min, max
+                )
             }
+            RuleError::Empty => write!(f, "the rule has no values"),
         }
     }
 }
 
+impl std::error::Error for RuleError {}
+
+/// checks if a value matches a range within the `[min, max]` domain, wrapping around it
+fn range_matches_within<T: PrimInt>(start: T, end: T, step: T, value: T, min: T, max: T) -> bool {
+    let (start, end, value, min, max) = (
+        to_i64(start),
+        to_i64(end),
+        to_i64(value),
+        to_i64(min),
+        to_i64(max),
+    );
+    let step = to_i64(step).max(1);
+    let size = max - min + 1;
+
+    // distance from the start of the range, walking up and wrapping at the end of the domain
+    let offset = (value - start).rem_euclid(size);
+    let length = (end - start).rem_euclid(size);
+
+    offset <= length && offset % step == 0
+}
+
+fn to_i64<T: PrimInt>(value: T) -> i64 {
+    value.to_i64().unwrap_or(i64::MAX)
+}
+
 /// 🧉 » create a `Rule` that will match a single value
 pub fn val<T: PrimInt>(value: T) -> Rule<T> {
     Rule::Val(value)
@@ -129,11 +217,8 @@ mod tests {
         // since our rule doesn't have max and min values, it should match any value
         // >= 5 and any value <= 2, (well, taking step into account, obviously)
 
-        // TODO: maybe in the future we could add another rule type "WrappingRange" that would
-        //       handle this case more explicitly and taking into account the min-max wrapping
-        //       limits (like 0 and 6 in this case). It obviously would need its WrappingRanges
-        //       analogs just like we have Ranges for Range... for now we can just use the
-        //       Range rule and be happy with it
+        // `matches_within` handles this case taking into account the min-max wrapping limits
+        // (like 0 and 6 in this case), see `test_rule_wrapping_range_within`
 
         let rule = range(5, 2, 1);
         assert!(rule.matches(5));
@@ -148,6 +233,51 @@ mod tests {
         assert!(rule.matches(7));
     }
 
+    #[test]
+    fn test_rule_wrapping_range_within() {
+        let rule = range(5, 2, 1);
+        for value in [5, 6, 0, 1, 2] {
+            assert!(rule.matches_within(value, 0, 6));
+        }
+        assert!(!rule.matches_within(3, 0, 6));
+        assert!(!rule.matches_within(4, 0, 6));
+        assert!(!rule.matches_within(7, 0, 6));
+
+        // steps continue across the end of the domain: 22, 0, 2, 4
+        let rule = range(22, 4, 2);
+        for value in [22, 0, 2, 4] {
+            assert!(rule.matches_within(value, 0, 23));
+        }
+        for value in [23, 1, 3, 5, 21] {
+            assert!(!rule.matches_within(value, 0, 23));
+        }
+
+        // the same applies to domains that don't start at 0 (e.g. months)
+        let rule = ranges(vec![(11, 2, 2)]);
+        assert!(rule.matches_within(11, 1, 12));
+        assert!(rule.matches_within(1, 1, 12));
+        assert!(!rule.matches_within(12, 1, 12));
+        assert!(!rule.matches_within(2, 1, 12));
+    }
+
+    #[test]
+    fn test_value_is_between() {
+        assert!(range(1, 5, 1).value_is_between(0, 6).is_ok());
+        assert!(range(5, 2, 1).value_is_between(0, 6).is_ok());
+        assert_eq!(
+            range(9, 2, 1).value_is_between(0, 6),
+            Err(RuleError::OutOfBounds {
+                value: 9,
+                min: 0,
+                max: 6
+            })
+        );
+        assert_eq!(
+            many::<u32>(vec![]).value_is_between(0, 6),
+            Err(RuleError::Empty)
+        );
+    }
+
     #[test]
     fn test_rule_many() {
         let rule = many(vec![5, 10, 15]);
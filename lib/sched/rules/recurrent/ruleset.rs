@@ -1,11 +1,93 @@
 pub mod builder;
+mod cron;
 
 use {
-    super::Rule,
-    crate::sched::utils::cron_date::LoolDate,
-    chrono::{DateTime, Datelike, Local},
+    super::{Rule, RuleError},
+    crate::sched::{rules::CalendarUnit, utils::cron_date::LoolDate},
+    chrono::{DateTime, Datelike, Local, Timelike},
+    std::fmt,
 };
 
+/// 🧉 » recurrence field
+///
+/// a field of a `RecurrenceRuleSet`, which knows the domain of its values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecurrenceField {
+    Millisecond,
+    Second,
+    Minute,
+    Hour,
+    DayOfWeek,
+    Day,
+    Month,
+    Year,
+}
+
+impl RecurrenceField {
+    /// 🧉 » returns the min and max values of the field
+    ///
+    /// years are not bounded, so their domain is the whole `i32` range.
+    pub fn bounds(&self) -> (i64, i64) {
+        match self {
+            RecurrenceField::Millisecond => (0, 999),
+            RecurrenceField::Second | RecurrenceField::Minute => (0, 59),
+            RecurrenceField::Hour => (0, 23),
+            RecurrenceField::DayOfWeek => (0, 6),
+            RecurrenceField::Day => (1, 31),
+            RecurrenceField::Month => (1, 12),
+            RecurrenceField::Year => (i32::MIN as i64, i32::MAX as i64),
+        }
+    }
+
+    /// returns true if `rule` is not set, or matches `value` within the domain of the field
+    fn matches(&self, rule: &Option<Rule<u32>>, value: u32) -> bool {
+        let (min, max) = self.bounds();
+        rule.as_ref().is_none_or(|rule| rule.matches_within(value, min as u32, max as u32))
+    }
+}
+
+impl fmt::Display for RecurrenceField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RecurrenceField::Millisecond => "millisecond",
+            RecurrenceField::Second => "second",
+            RecurrenceField::Minute => "minute",
+            RecurrenceField::Hour => "hour",
+            RecurrenceField::DayOfWeek => "day of week",
+            RecurrenceField::Day => "day",
+            RecurrenceField::Month => "month",
+            RecurrenceField::Year => "year",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// 🧉 » rule set error
+///
+/// why a `RecurrenceRuleSet` is not valid, as returned by `RecurrenceRuleSet::is_valid`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleSetError {
+    /// none of the rules is set
+    NoRules,
+    /// the rule of a field is not valid
+    InvalidRule {
+        field: RecurrenceField,
+        error: RuleError,
+    },
+}
+
+impl fmt::Display for RuleSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleSetError::NoRules => write!(f, "no rule is set"),
+            RuleSetError::InvalidRule { field, error } => write!(f, "{} rule: {}", field, error),
+        }
+    }
+}
+
+impl std::error::Error for RuleSetError {}
+
 /// 🧉 » a recurrence rule-set
 ///
 /// sets rules that define a certain recurrence behavior
@@ -13,6 +95,8 @@ use {
 /// use the builder pattern to create a new `RecurrenceRuleSet`
 #[derive(Clone, Debug)]
 pub struct RecurrenceRuleSet {
+    /// millisecond of the second (0..999)
+    millisecond: Option<Rule<u32>>,
     /// second of the minute (0..59)
     second: Option<Rule<u32>>,
     /// minute of the hour (0..59)
@@ -41,9 +125,46 @@ impl RecurrenceRuleSet {
         next.map(|date| date.date())
     }
 
+    /// 🧉 » check if a given `DateTime` matches the rule set
+    ///
+    /// a date matches when every rule that is set matches the corresponding field of the date.
+    /// Without a millisecond rule, any millisecond of a matching second matches.
+    pub fn matches(&self, date: DateTime<Local>) -> bool {
+        if self.is_valid().is_err() {
+            return false;
+        }
+
+        CalendarUnit::ALL.iter().all(|unit| self.unit_matches(*unit, date))
+    }
+
+    /// 🚧 internal
+    ///
+    /// checks if the rules of the fields in a unit of the calendar match a date
+    pub(crate) fn unit_matches(&self, unit: CalendarUnit, date: DateTime<Local>) -> bool {
+        match unit {
+            CalendarUnit::Millisecond => RecurrenceField::Millisecond
+                .matches(&self.millisecond, date.timestamp_subsec_millis()),
+            CalendarUnit::Second => RecurrenceField::Second.matches(&self.second, date.second()),
+            CalendarUnit::Minute => RecurrenceField::Minute.matches(&self.minute, date.minute()),
+            CalendarUnit::Hour => RecurrenceField::Hour.matches(&self.hour, date.hour()),
+            CalendarUnit::Day => {
+                RecurrenceField::Day.matches(&self.day, date.day())
+                    && RecurrenceField::DayOfWeek
+                        .matches(&self.dow, date.weekday().num_days_from_sunday())
+            }
+            CalendarUnit::Month => RecurrenceField::Month.matches(&self.month, date.month()),
+            CalendarUnit::Year => self.year_matches(date.year()),
+        }
+    }
+
+    /// 🚧 internal
+    fn year_matches(&self, year: i32) -> bool {
+        self.year.as_ref().is_none_or(|rule| rule.matches_within(year, i32::MIN, i32::MAX))
+    }
+
     /// 🚧 internal
     fn _next_match(&self, from: DateTime<Local>) -> Option<LoolDate<Local>> {
-        if !self.is_valid() {
+        if self.is_valid().is_err() {
             return None;
         }
 
@@ -54,8 +175,12 @@ impl RecurrenceRuleSet {
             }
         }
 
+        // rule sets resolve to seconds, unless they have a millisecond rule
         let mut next = LoolDate::new(from);
-        next.add_second();
+        match self.millisecond {
+            Some(_) => next.add_millisecond(),
+            None => next.add_second(),
+        }
 
         loop {
             // check other possible year values
@@ -66,7 +191,7 @@ impl RecurrenceRuleSet {
                     }
                 }
 
-                if !year_unit.matches(next.year()) {
+                if !self.year_matches(next.year()) {
                     next.add_year();
                     next.set_md(1, 1);
                     next.set_hms(0, 0, 0);
@@ -74,46 +199,39 @@ impl RecurrenceRuleSet {
                 }
             }
 
-            if let Some(month_unit) = &self.month {
-                if !month_unit.matches(next.month()) {
-                    next.add_month();
-                    continue;
-                }
+            if !RecurrenceField::Month.matches(&self.month, next.month()) {
+                next.add_month();
+                continue;
             }
 
-            if let Some(day_unit) = &self.day {
-                if !day_unit.matches(next.day()) {
-                    next.add_day();
-                    continue;
-                }
+            if !RecurrenceField::Day.matches(&self.day, next.day()) {
+                next.add_day();
+                continue;
             }
 
-            if let Some(dow_unit) = &self.dow {
-                if !dow_unit.matches(next.weekday_from_sunday()) {
-                    next.add_day();
-                    continue;
-                }
+            if !RecurrenceField::DayOfWeek.matches(&self.dow, next.weekday_from_sunday()) {
+                next.add_day();
+                continue;
             }
 
-            if let Some(hour_unit) = &self.hour {
-                if !hour_unit.matches(next.hour()) {
-                    next.add_hour();
-                    continue;
-                }
+            if !RecurrenceField::Hour.matches(&self.hour, next.hour()) {
+                next.add_hour();
+                continue;
             }
 
-            if let Some(minute_unit) = &self.minute {
-                if !minute_unit.matches(next.minute()) {
-                    next.add_minute();
-                    continue;
-                }
+            if !RecurrenceField::Minute.matches(&self.minute, next.minute()) {
+                next.add_minute();
+                continue;
             }
 
-            if let Some(second_unit) = &self.second {
-                if !second_unit.matches(next.second()) {
-                    next.add_second();
-                    continue;
-                }
+            if !RecurrenceField::Second.matches(&self.second, next.second()) {
+                next.add_second();
+                continue;
+            }
+
+            if !RecurrenceField::Millisecond.matches(&self.millisecond, next.millis() as u32) {
+                next.add_millisecond();
+                continue;
             }
 
             // finally, everything matches, so we get out of the loop
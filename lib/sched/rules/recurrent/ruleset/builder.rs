@@ -1,6 +1,9 @@
 use chrono::Weekday;
 
-use {super::RecurrenceRuleSet, crate::sched::rules::Rule};
+use {
+    super::{RecurrenceField, RecurrenceRuleSet, RuleSetError},
+    crate::sched::rules::Rule,
+};
 
 pub fn ruleset() -> RecurrenceRuleSet {
     RecurrenceRuleSet::recurring()
@@ -10,6 +13,7 @@ impl RecurrenceRuleSet {
     /// 🧉 » create a new `RecurrenceRuleSet
     pub fn recurring() -> Self {
         Self {
+            millisecond: None,
             second: None,
             minute: None,
             hour: None,
@@ -20,6 +24,14 @@ impl RecurrenceRuleSet {
         }
     }
 
+    /// 🧉 » set the millisecond rule
+    ///
+    /// rule sets without a millisecond rule run at the start of each matching second.
+    pub fn milliseconds_rule(&mut self, rule: Rule<u32>) -> &mut Self {
+        self.millisecond = Some(rule);
+        self
+    }
+
     /// 🧉 » set the second rule
     pub fn seconds_rule(&mut self, rule: Rule<u32>) -> &mut Self {
         self.second = Some(rule);
@@ -72,6 +84,11 @@ impl RecurrenceRuleSet {
         self
     }
 
+    /// 🧉 » set the millisecond rule as a single value from primitive
+    pub fn at_millisecond(&mut self, value: u32) -> &mut Self {
+        self.milliseconds_rule(Rule::Val(value))
+    }
+
     /// 🧉 » set the second rule as a single value from primitive
     pub fn at_second(&mut self, value: u32) -> &mut Self {
         self.seconds_rule(Rule::Val(value))
@@ -158,9 +175,14 @@ impl RecurrenceRuleSet {
     }
 
     /// 🧉 » check if the rule set is valid
-    pub fn is_valid(&self) -> bool {
+    ///
+    /// a rule set is valid when at least one of its rules is set, and every value of its rules
+    /// is within the domain of the field (see `RecurrenceField::bounds`). Otherwise, returns the
+    /// first problem found.
+    pub fn is_valid(&self) -> Result<(), RuleSetError> {
         // at least one of the rules must be set
-        let mut valid = self.second.is_some()
+        let any_rule = self.millisecond.is_some()
+            || self.second.is_some()
             || self.minute.is_some()
             || self.hour.is_some()
             || self.dow.is_some()
@@ -168,56 +190,53 @@ impl RecurrenceRuleSet {
             || self.month.is_some()
             || self.year.is_some();
 
-        // month/s should be between 1 and 12
-        if let Some(month) = &self.month {
-            valid = valid && month.value_is_between(1, 12);
+        if !any_rule {
+            return Err(RuleSetError::NoRules);
         }
 
-        // day/s of week should be between 0 and 6
-        if let Some(dow) = &self.dow {
-            valid = valid && dow.value_is_between(0, 6);
-        }
+        let check = |field: RecurrenceField, rule: &Option<Rule<u32>>, max: Option<u32>| {
+            let (min, field_max) = field.bounds();
+            let max = max.unwrap_or(field_max as u32);
 
-        // day/s of month should be between 1 and 31
-        if let Some(day) = &self.day {
-            // check month overflows if month is also set
-            // HACK: I'm already handling day overflows and even leap years at `CronDate`
-            //       this might not be necessary anymore... we should test it and see how it goes
-            //       without this check (we might want to check for 1..31 as a minimum and that's
-            //       all)
-            match &self.month {
-                Some(month) => {
-                    if month.matches(2) {
-                        valid = valid && day.value_is_between(1, 29);
-                    } else if month.matches(4)
-                        || month.matches(6)
-                        || month.matches(9)
-                        || month.matches(11)
-                    {
-                        valid = valid && day.value_is_between(1, 30);
-                    } else {
-                        valid = valid && day.value_is_between(1, 31);
-                    }
-                }
-                None => valid = valid && day.value_is_between(1, 31),
+            match rule {
+                Some(rule) => rule
+                    .value_is_between(min as u32, max)
+                    .map_err(|error| RuleSetError::InvalidRule { field, error }),
+                None => Ok(()),
             }
+        };
+
+        check(RecurrenceField::Month, &self.month, None)?;
+        check(RecurrenceField::DayOfWeek, &self.dow, None)?;
+
+        // check month overflows if month is also set
+        // HACK: I'm already handling day overflows and even leap years at `CronDate`
+        //       this might not be necessary anymore... we should test it and see how it goes
+        //       without this check (we might want to check for 1..31 as a minimum and that's
+        //       all)
+        let max_day = match &self.month {
+            Some(month) if month.matches_within(2, 1, 12) => Some(29),
+            Some(month) if [4, 6, 9, 11].iter().any(|m| month.matches_within(*m, 1, 12)) => {
+                Some(30)
+            }
+            _ => None,
+        };
+        check(RecurrenceField::Day, &self.day, max_day)?;
+
+        check(RecurrenceField::Hour, &self.hour, None)?;
+        check(RecurrenceField::Minute, &self.minute, None)?;
+        check(RecurrenceField::Second, &self.second, None)?;
+        check(RecurrenceField::Millisecond, &self.millisecond, None)?;
+
+        if let Some(year) = &self.year {
+            year.value_is_between(i32::MIN, i32::MAX).map_err(|error| {
+                RuleSetError::InvalidRule {
+                    field: RecurrenceField::Year,
+                    error,
+                }
+            })?;
         }
 
-        // hour/s should be between 0 and 23
-        if let Some(hour) = &self.hour {
-            valid = valid && hour.value_is_between(0, 23);
-        }
-
-        // minute/s should be between 0 and 59
-        if let Some(minute) = &self.minute {
-            valid = valid && minute.value_is_between(0, 59);
-        }
-
-        // second/s should be between 0 and 59
-        if let Some(second) = &self.second {
-            valid = valid && second.value_is_between(0, 59);
-        }
-
-        valid
+        Ok(())
     }
 }
@@ -0,0 +1,249 @@
+use {
+    super::RecurrenceRuleSet,
+    crate::sched::rules::{
+        cron_field::{parse_ranges, replace_names, CronField, CronRanges},
+        Rule,
+    },
+    eyre::{eyre, Result},
+    num_traits::PrimInt,
+    std::fmt::Display,
+};
+
+impl RecurrenceRuleSet {
+    /// 🧉 » convert the rule set to a cron expression
+    ///
+    /// returns a 6-field expression (`second minute hour day month dow`), or a 7-field one when
+    /// the year rule is set. Rules that are not set become `*`.
+    ///
+    /// fails for rule sets that can't be expressed in cron:
+    /// - invalid rule sets (see `is_valid`).
+    /// - rule sets with a millisecond rule, since cron resolves to seconds.
+    /// - stepped wrap-around ranges (like `range(5, 2, 2)`). Wrap-around ranges without a step
+    ///   are split in two (`range(5, 2, 1)` on the day of week becomes `5-6,0-2`).
+    /// - rule sets with both the day of month and day of week rules, since cron matches dates
+    ///   where *any* of them matches, while rule sets need both to match.
+    /// - empty `Many` or `Ranges` rules, and years outside of `1970..=2099`.
+    pub fn to_cron(&self) -> Result<String> {
+        self.is_valid().map_err(|err| eyre!("the rule set is not valid: {}", err))?;
+
+        if self.day.is_some() && self.dow.is_some() {
+            return Err(eyre!(
+                "the day of month and day of week rules can't be combined in a cron expression"
+            ));
+        }
+
+        if self.millisecond.is_some() {
+            return Err(eyre!(
+                "the millisecond rule can't be expressed in a cron expression"
+            ));
+        }
+
+        let mut fields = vec![
+            field_to_cron(&self.second, CronField::Second)?,
+            field_to_cron(&self.minute, CronField::Minute)?,
+            field_to_cron(&self.hour, CronField::Hour)?,
+            field_to_cron(&self.day, CronField::DayOfMonth)?,
+            field_to_cron(&self.month, CronField::Month)?,
+            field_to_cron(&self.dow, CronField::DayOfWeek)?,
+        ];
+
+        if self.year.is_some() {
+            fields.push(field_to_cron(&self.year, CronField::Year)?);
+        }
+
+        Ok(fields.join(" "))
+    }
+
+    /// 🧉 » create a rule set from a cron expression
+    ///
+    /// accepts 5-field expressions (`minute hour day month dow`, at second `0`), 6-field ones
+    /// with a leading second field, and 7-field ones with a trailing year field.
+    ///
+    /// each field can be `*` (or `?` for days), a value, a range (`a-b`), a stepped range
+    /// (`a-b/s`, `*/s` or `a/s`) or a list of them, and becomes a `Rule::Val`, `Rule::Range`,
+    /// `Rule::Many` or `Rule::Ranges`. Month and weekday names are accepted too.
+    ///
+    /// fails for expressions with both the day of month and day of week fields set, since rule
+    /// sets can't express cron's "any of them" semantics, and for extensions like `L`, `W`, `#`
+    /// or `@daily`.
+    pub fn from_cron(pattern: &str) -> Result<Self> {
+        let values: Vec<&str> = pattern.split_whitespace().collect();
+        let (second, rest) = match values.len() {
+            5 => ("0", &values[..]),
+            6 | 7 => (values[0], &values[1..]),
+            count => {
+                return Err(eyre!(
+                    "invalid cron pattern `{}`: expected 5 to 7 fields, found {}",
+                    pattern,
+                    count
+                ))
+            }
+        };
+
+        let parse = |field: CronField, value: &str| {
+            parse_field(field, value).map_err(|err| {
+                eyre!(
+                    "invalid cron pattern `{}`: {} field `{}`: {}",
+                    pattern,
+                    field,
+                    value,
+                    err
+                )
+            })
+        };
+
+        let mut rules = Self::recurring();
+        rules.second = parse(CronField::Second, second)?.map(to_rule);
+        rules.minute = parse(CronField::Minute, rest[0])?.map(to_rule);
+        rules.hour = parse(CronField::Hour, rest[1])?.map(to_rule);
+        rules.day = parse(CronField::DayOfMonth, rest[2])?.map(to_rule);
+        rules.month = parse(CronField::Month, rest[3])?.map(to_rule);
+        rules.dow = parse(CronField::DayOfWeek, rest[4])?.map(dow_rule);
+
+        if let Some(year) = rest.get(5) {
+            let years = parse(CronField::Year, year)?;
+            rules.year = years.map(|ranges| {
+                let ranges = ranges.into_iter();
+                to_rule(ranges.map(|(start, end, step)| (start as i32, end as i32, step as i32)))
+            });
+        }
+
+        if rules.day.is_some() && rules.dow.is_some() {
+            return Err(eyre!(
+                "invalid cron pattern `{}`: the day of month and day of week fields can't be \
+                 both set in a rule set",
+                pattern
+            ));
+        }
+
+        Ok(rules)
+    }
+}
+
+/// converts the rule of a field to cron
+fn field_to_cron<T: PrimInt + Display>(rule: &Option<Rule<T>>, field: CronField) -> Result<String> {
+    let rule = match rule {
+        Some(rule) => rule,
+        None => return Ok("*".to_string()),
+    };
+
+    let parts = match rule {
+        Rule::Val(value) => vec![range_to_cron(*value, *value, T::one(), field)?],
+        Rule::Range(start, end, step) => vec![range_to_cron(*start, *end, *step, field)?],
+        Rule::Many(values) => values
+            .iter()
+            .map(|value| range_to_cron(*value, *value, T::one(), field))
+            .collect::<Result<_>>()?,
+        Rule::Ranges(ranges) => ranges
+            .iter()
+            .map(|(start, end, step)| range_to_cron(*start, *end, *step, field))
+            .collect::<Result<_>>()?,
+    };
+
+    if parts.is_empty() {
+        return Err(eyre!("the {} rule doesn't match any value", field));
+    }
+
+    Ok(parts.join(","))
+}
+
+/// converts a range (or a single value, if `start == end`) to cron
+fn range_to_cron<T: PrimInt + Display>(
+    start: T,
+    end: T,
+    step: T,
+    field: CronField,
+) -> Result<String> {
+    // sunday is `0` in rule sets, `7` is only accepted when parsing
+    let (min, max) = match field {
+        CronField::DayOfWeek => (0, 6),
+        field => field.bounds(),
+    };
+    // the bounds of every field fit in the type of its rules (`u32`, or `i32` for years)
+    let bound = |value: u32| T::from(value).expect("field bounds fit in the rule type");
+    let (min, max) = (bound(min), bound(max));
+
+    for value in [start, end] {
+        if value < min || value > max {
+            return Err(eyre!(
+                "the {} rule has a value out of bounds ({}, allowed values: {}-{})",
+                field,
+                value,
+                min,
+                max
+            ));
+        }
+    }
+
+    let stepped = step > T::one();
+
+    if start == end {
+        Ok(start.to_string())
+    } else if start < end && stepped {
+        Ok(format!("{}-{}/{}", start, end, step))
+    } else if start < end {
+        Ok(format!("{}-{}", start, end))
+    } else if stepped {
+        Err(eyre!(
+            "the {} rule has a stepped wrap-around range ({} to {} every {}), which can't be \
+             expressed in cron",
+            field,
+            start,
+            end,
+            step
+        ))
+    } else {
+        // a wrap-around range is split in two ranges, up to the max and from the min
+        let upper = range_to_cron(start, max, T::one(), field)?;
+        let lower = range_to_cron(min, end, T::one(), field)?;
+        Ok(format!("{},{}", upper, lower))
+    }
+}
+
+/// parses a field of a cron expression, returning `None` for `*`
+///
+/// `?` is the same as `*` for the days, and month and weekday names are accepted.
+fn parse_field(field: CronField, value: &str) -> Result<Option<CronRanges>, String> {
+    let value = match field {
+        CronField::DayOfMonth | CronField::DayOfWeek => value.replace('?', "*"),
+        _ => value.to_string(),
+    };
+
+    parse_ranges(field, &replace_names(field, &value.to_lowercase())?)
+}
+
+/// creates the rule of the day of week field, where sunday can be both `0` and `7`
+fn dow_rule(ranges: CronRanges) -> Rule<u32> {
+    let mut dow_ranges = vec![];
+    for (start, end, step) in ranges {
+        if start == 7 {
+            dow_ranges.push((0, 0, 1));
+            continue;
+        }
+
+        if end == 7 {
+            dow_ranges.push((start, 6, step));
+            if (7 - start) % step == 0 {
+                dow_ranges.push((0, 0, 1));
+            }
+        } else {
+            dow_ranges.push((start, end, step));
+        }
+    }
+
+    to_rule(dow_ranges)
+}
+
+/// creates the simplest rule matching a list of ranges
+fn to_rule<T: PrimInt, R: IntoIterator<Item = (T, T, T)>>(ranges: R) -> Rule<T> {
+    let ranges: Vec<(T, T, T)> = ranges.into_iter().collect();
+
+    match ranges.as_slice() {
+        [(start, end, _)] if start == end => Rule::Val(*start),
+        [(start, end, step)] => Rule::Range(*start, *end, *step),
+        _ if ranges.iter().all(|(start, end, _)| start == end) => {
+            Rule::Many(ranges.iter().map(|(start, _, _)| *start).collect())
+        }
+        _ => Rule::Ranges(ranges),
+    }
+}
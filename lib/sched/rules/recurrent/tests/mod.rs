@@ -1,3 +1,5 @@
 mod recurrence_rules_by_many;
 mod recurrence_rules_by_range;
 mod recurrence_rules_by_val;
+mod recurrence_rules_cron;
+mod recurrence_rules_millis;
@@ -1,6 +1,6 @@
 use chrono::{Datelike, Local, TimeZone, Timelike};
 
-use crate::sched::rules::{range, ruleset};
+use crate::sched::rules::{range, ruleset, RecurrenceField, RuleError, RuleSetError};
 
 #[test]
 fn between_10_and_20_seconds() {
@@ -51,3 +51,59 @@ fn each_day_between_9_and_17_at_hour_start() {
         );
     }
 }
+
+#[test]
+fn wrap_around_hours_with_step() {
+    // 22:00, 00:00, 02:00 and 04:00
+    let date = Local.with_ymd_and_hms(2024, 4, 7, 21, 15, 5).unwrap();
+
+    let mut rules = ruleset();
+    rules.hours_rule(range(22, 4, 2)).at_minute(0).at_second(0);
+
+    let mut next = date;
+    let expected = [(7, 22), (8, 0), (8, 2), (8, 4), (8, 22)];
+
+    for (day, hour) in expected {
+        next = rules.next_match_from(next).unwrap();
+        assert_eq!(
+            next,
+            Local.with_ymd_and_hms(2024, 4, day, hour, 0, 0).unwrap()
+        );
+    }
+}
+
+#[test]
+fn wrap_around_weekdays() {
+    // from friday to tuesday, starting on wednesday 2024-04-03
+    let date = Local.with_ymd_and_hms(2024, 4, 3, 12, 0, 0).unwrap();
+
+    let mut rules = ruleset();
+    rules.dow_rule(range(5, 2, 1)).at_time(9, 0, 0);
+
+    let mut next = date;
+    for day in [5, 6, 7, 8, 9, 12] {
+        next = rules.next_match_from(next).unwrap();
+        assert_eq!(next, Local.with_ymd_and_hms(2024, 4, day, 9, 0, 0).unwrap());
+    }
+}
+
+#[test]
+fn out_of_bounds_range_is_not_valid() {
+    let mut rules = ruleset();
+    rules.dow_rule(range(5, 7, 1));
+
+    assert_eq!(
+        rules.is_valid(),
+        Err(RuleSetError::InvalidRule {
+            field: RecurrenceField::DayOfWeek,
+            error: RuleError::OutOfBounds {
+                value: 7,
+                min: 0,
+                max: 6
+            },
+        })
+    );
+    assert!(rules.next_match_from(Local::now()).is_none());
+
+    assert_eq!(ruleset().is_valid(), Err(RuleSetError::NoRules));
+}
@@ -0,0 +1,85 @@
+use {
+    crate::sched::rules::{many, range, ranges, ruleset, RecurrenceRuleSet},
+    chrono::{Local, TimeZone},
+    eyre::{set_hook, DefaultHandler},
+};
+
+fn setup_eyre() {
+    let _ = set_hook(Box::new(DefaultHandler::default_with));
+}
+
+#[test]
+fn to_cron_with_every_rule_kind() {
+    let mut rules = ruleset();
+    rules
+        .at_second(0)
+        .minutes_rule(range(0, 30, 15))
+        .hours_rule(many(vec![8, 12, 18]))
+        .month_rule(ranges(vec![(1, 3, 1), (6, 12, 2)]))
+        .from_to_dow(1, 5);
+
+    assert_eq!(
+        rules.to_cron().unwrap(),
+        "0 0-30/15 8,12,18 * 1-3,6-12/2 1-5"
+    );
+}
+
+#[test]
+fn to_cron_with_year() {
+    let mut rules = ruleset();
+    rules.on_datetime(2030, 1, 1, 0, 0, 0);
+
+    assert_eq!(rules.to_cron().unwrap(), "0 0 0 1 1 * 2030");
+}
+
+#[test]
+fn to_cron_splits_wrap_around_ranges() {
+    let mut rules = ruleset();
+    rules.at_time(9, 0, 0).dow_rule(range(5, 2, 1));
+
+    assert_eq!(rules.to_cron().unwrap(), "0 0 9 * * 5-6,0-2");
+}
+
+#[test]
+fn to_cron_fails_for_unrepresentable_rules() {
+    setup_eyre();
+    let mut stepped_wrap = ruleset();
+    stepped_wrap.hours_rule(range(22, 4, 2));
+    assert!(stepped_wrap.to_cron().is_err());
+
+    let mut day_and_dow = ruleset();
+    day_and_dow.on_day(13).on_dow(5);
+    assert!(day_and_dow.to_cron().is_err());
+
+    assert!(ruleset().to_cron().is_err());
+}
+
+#[test]
+fn from_cron_uses_rule_semantics() {
+    let rules = RecurrenceRuleSet::from_cron("*/15 9-17 * * MON-FRI").unwrap();
+    assert_eq!(rules.to_cron().unwrap(), "0 0-59/15 9-17 * * 1-5");
+
+    let date = Local.with_ymd_and_hms(2024, 4, 6, 12, 0, 0).unwrap(); // saturday
+    let next = rules.next_match_from(date).unwrap();
+    assert_eq!(next, Local.with_ymd_and_hms(2024, 4, 8, 9, 0, 0).unwrap());
+}
+
+#[test]
+fn from_cron_with_seconds_and_year() {
+    let rules = RecurrenceRuleSet::from_cron("30 0 12 1 JAN ? 2030").unwrap();
+    assert_eq!(rules.to_cron().unwrap(), "30 0 12 1 1 * 2030");
+
+    // sunday can be written as 7
+    let rules = RecurrenceRuleSet::from_cron("0 0 0 * * fri-sun").unwrap();
+    assert_eq!(rules.to_cron().unwrap(), "0 0 0 * * 5-6,0");
+}
+
+#[test]
+fn from_cron_fails_for_unsupported_expressions() {
+    setup_eyre();
+    assert!(RecurrenceRuleSet::from_cron("* * *").is_err());
+    assert!(RecurrenceRuleSet::from_cron("0 0 13 * 5").is_err());
+    assert!(RecurrenceRuleSet::from_cron("0 0 L * *").is_err());
+    assert!(RecurrenceRuleSet::from_cron("0 25 * * *").is_err());
+    assert!(RecurrenceRuleSet::from_cron("0 10-5 * * *").is_err());
+}
@@ -0,0 +1,97 @@
+use {
+    crate::sched::rules::{range, ruleset, RecurrenceField, Rule, RuleError, RuleSetError},
+    chrono::{DateTime, Duration, Local, TimeZone},
+    eyre::{set_hook, DefaultHandler},
+};
+
+fn setup_eyre() {
+    let _ = set_hook(Box::new(DefaultHandler::default_with));
+}
+
+fn date(second: u32, millis: i64) -> DateTime<Local> {
+    Local.with_ymd_and_hms(2024, 4, 7, 16, 15, second).unwrap() + Duration::milliseconds(millis)
+}
+
+#[test]
+fn at_millisecond_250_of_each_second() {
+    let mut rules = ruleset();
+    rules.at_millisecond(250);
+
+    let mut next = date(5, 600);
+    for i in 0..10 {
+        next = rules.next_match_from(next).unwrap();
+        assert_eq!(next, date(6 + i, 250));
+    }
+}
+
+#[test]
+fn every_250_milliseconds() {
+    let mut rules = ruleset();
+    rules.milliseconds_rule(range(0, 999, 250));
+
+    // sub-millisecond precision of the base date is dropped
+    let mut next = date(5, 100) + Duration::microseconds(300);
+    for i in 0..8 {
+        next = rules.next_match_from(next).unwrap();
+        assert_eq!(next, date(5, 250 * (i + 1)));
+    }
+}
+
+#[test]
+fn at_millisecond_of_a_second() {
+    let mut rules = ruleset();
+    rules.at_second(30).at_millisecond(500);
+
+    assert_eq!(rules.next_match_from(date(5, 0)), Some(date(30, 500)));
+    assert_eq!(
+        rules.next_match_from(date(30, 500)),
+        Some(date(30, 500) + Duration::minutes(1))
+    );
+}
+
+#[test]
+fn without_millisecond_rule_runs_at_start_of_second() {
+    let mut rules = ruleset();
+    rules.at_second(30);
+
+    assert_eq!(rules.next_match_from(date(5, 700)), Some(date(30, 0)));
+    assert!(rules.matches(date(30, 700)));
+}
+
+#[test]
+fn matches_milliseconds() {
+    let mut rules = ruleset();
+    rules.milliseconds_rule(Rule::Many(vec![0, 500]));
+
+    assert!(rules.matches(date(1, 0)));
+    assert!(rules.matches(date(1, 500) + Duration::microseconds(999)));
+    assert!(!rules.matches(date(1, 501)));
+}
+
+#[test]
+fn millisecond_rule_out_of_bounds() {
+    let mut rules = ruleset();
+    rules.at_millisecond(1000);
+
+    assert_eq!(
+        rules.is_valid(),
+        Err(RuleSetError::InvalidRule {
+            field: RecurrenceField::Millisecond,
+            error: RuleError::OutOfBounds {
+                value: 1000,
+                min: 0,
+                max: 999
+            },
+        })
+    );
+    assert_eq!(rules.next_match_from(date(0, 0)), None);
+}
+
+#[test]
+fn millisecond_rule_to_cron() {
+    setup_eyre();
+    let mut rules = ruleset();
+    rules.at_second(1).at_millisecond(250);
+
+    assert!(rules.to_cron().is_err());
+}
@@ -6,3 +6,430 @@ pub mod tokio;
 
 #[cfg(feature = "sched.threads")]
 pub mod threads;
+
+mod task;
+
+#[cfg(test)]
+mod tests;
+
+pub use task::ScheduledTask;
+
+#[cfg(feature = "sched.control")]
+use super::control::{ControlRequest, ControlResponse};
+
+use {
+    super::{
+        lock::LockProvider,
+        metrics::MetricsSnapshot,
+        options::TaskOptions,
+        rules::SchedulingRule,
+        task_handler::{TaskHandler, TaskId},
+    },
+    eyre::{eyre, Result},
+    log::debug,
+    std::{
+        collections::BTreeMap,
+        sync::{atomic::Ordering, Arc},
+    },
+};
+
+/// 🧉 » scheduler backend
+///
+/// how a `Scheduler` waits for the next run of its tasks and runs their actions: in a thread per
+/// task (`threads::ThreadsBackend`) or in tokio tasks (`tokio::TokioBackend`).
+///
+/// everything else (task bookkeeping, duplicates, constraints, locks, history and metrics) is
+/// shared by every backend, so code scheduling tasks can be generic over the backend:
+///
+/// ```rust,ignore
+/// fn schedule_cleanup<B: SchedulerBackend>(
+///     sched: &mut Scheduler<B>,
+///     action: impl IntoAction<B>,
+/// ) -> Result<TaskHandler> {
+///     sched.schedule("cleanup", action, recur(ruleset().at_hour(3)))
+/// }
+/// ```
+pub trait SchedulerBackend: Sized + Send + Sync + 'static {
+    /// the action of a task, as run by the backend
+    type Action: Send + 'static;
+
+    /// the backend side of a scheduled task: its action, and whatever is needed to run it
+    type Runner: Send + Sync + 'static;
+
+    /// creates the runner of a new task
+    fn runner(action: Self::Action) -> Self::Runner;
+
+    /// runs the scheduling loop of a task in the background, until the task is removed or has
+    /// no more runs
+    fn spawn(task: Arc<ScheduledTask<Self>>);
+
+    /// runs a task once in the background, because of a manual trigger
+    fn spawn_manual_run(task: Arc<ScheduledTask<Self>>);
+}
+
+/// 🧉 » task action
+///
+/// something that can be scheduled as the action of a task in the backend `B`: `FnMut()` closures
+/// in the threads backend, and closures returning a future in the tokio backend.
+pub trait IntoAction<B: SchedulerBackend> {
+    /// converts the value into an action of the backend
+    fn into_action(self) -> B::Action;
+}
+
+/// 🧉 » duplicate policy
+///
+/// what a scheduler does when a task is scheduled with the name of a task that is already in the
+/// scheduler.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// scheduling the task fails
+    Error,
+    /// the existing tasks with the same name are removed from the scheduler and stopped for good
+    #[default]
+    Replace,
+    /// both tasks are kept, they can be told apart by their `TaskId`
+    Allow,
+}
+
+/// **a task registered in a scheduler, along with its handler**
+pub(crate) struct TaskEntry<T> {
+    pub(crate) task: T,
+    pub(crate) handler: TaskHandler,
+}
+
+/// **applies the duplicate policy before scheduling a task named `name`**
+pub(crate) fn handle_duplicates<T>(
+    tasks: &mut BTreeMap<TaskId, TaskEntry<T>>,
+    name: &str,
+    policy: DuplicatePolicy,
+) -> Result<()> {
+    let duplicates: Vec<TaskId> =
+        tasks.iter().filter(|(_, entry)| entry.handler.name() == name).map(|(id, _)| *id).collect();
+
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+
+    match policy {
+        DuplicatePolicy::Allow => Ok(()),
+        DuplicatePolicy::Error => Err(eyre!("task {} already exists", name)),
+        DuplicatePolicy::Replace => {
+            for id in duplicates {
+                if let Some(entry) = tasks.remove(&id) {
+                    entry.handler.is_removed.store(true, Ordering::Relaxed);
+                    debug!("task {} {} has been replaced", name, id);
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// 🧉 » a task scheduler.
+///
+/// this struct is responsible for scheduling tasks to be executed at specific times, depending on
+/// the rules provided for each task. How the tasks wait and run is decided by its backend (see
+/// `SchedulerBackend`), usually picked through the `threads::Scheduler` or `tokio::Scheduler`
+/// aliases.
+///
+/// Each task can have n rules, and the task will be executed when any of the rules is met.
+///
+/// tasks are identified by their `TaskId`. What happens when two tasks share the same name is
+/// decided by the `DuplicatePolicy` of the scheduler.
+pub struct Scheduler<B: SchedulerBackend> {
+    tasks: BTreeMap<TaskId, TaskEntry<Arc<ScheduledTask<B>>>>,
+    lock: Option<Arc<dyn LockProvider>>,
+    duplicates: DuplicatePolicy,
+}
+
+impl<B: SchedulerBackend> Default for Scheduler<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: SchedulerBackend> Scheduler<B> {
+    /// 🧉 » create a new scheduler
+    pub fn new() -> Self {
+        Self {
+            tasks: BTreeMap::new(),
+            lock: None,
+            duplicates: DuplicatePolicy::default(),
+        }
+    }
+
+    /// 🧉 » set the duplicate policy
+    ///
+    /// decides what happens when a task is scheduled with the name of an existing task. The
+    /// default policy is `DuplicatePolicy::Replace`.
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// 🧉 » set a lock provider
+    ///
+    /// the lock is acquired before each scheduled run of every task scheduled afterwards, so that
+    /// several schedulers (usually, in different processes) can share the same tasks without
+    /// running each occurrence more than once. When the lock of an occurrence can't be acquired,
+    /// the run is recorded as `RunOutcome::Skipped`.
+    ///
+    /// occurrences are identified by the task name and their date, so tasks with random jitter
+    /// can't be shared, since each scheduler would delay them differently (use `Jitter::hashed`
+    /// instead).
+    pub fn with_lock_provider<L: LockProvider + 'static>(mut self, provider: L) -> Self {
+        self.lock = Some(Arc::new(provider));
+        self
+    }
+
+    /// 🧉 » schedule a task
+    ///
+    /// schedules a task to be executed at times determined by the provided rules. The action can
+    /// be anything the backend can run (see `IntoAction`). With the tokio backend, it must be
+    /// called from within a tokio runtime.
+    ///
+    /// fails if a task with the same name exists and the duplicate policy is
+    /// `DuplicatePolicy::Error`.
+    pub fn schedule<A, Str>(
+        &mut self,
+        name: Str,
+        action: A,
+        rules: SchedulingRule,
+    ) -> Result<TaskHandler>
+    where
+        A: IntoAction<B>,
+        Str: AsRef<str>,
+    {
+        let name = name.as_ref();
+        self.schedule_with_options(name, action, vec![rules], TaskOptions::default())
+    }
+
+    /// 🧉 » schedule a task with options
+    ///
+    /// schedules a task to be executed at times determined by the provided rules, using the
+    /// settings from `options`.
+    pub fn schedule_with_options<A>(
+        &mut self,
+        name: &str,
+        action: A,
+        rules: Vec<SchedulingRule>,
+        options: TaskOptions,
+    ) -> Result<TaskHandler>
+    where
+        A: IntoAction<B>,
+    {
+        self.insert_task(name, action.into_action(), rules, options)
+    }
+
+    /// **registers a task and spawns its scheduling loop**
+    pub(crate) fn insert_task(
+        &mut self,
+        name: &str,
+        action: B::Action,
+        rules: Vec<SchedulingRule>,
+        options: TaskOptions,
+    ) -> Result<TaskHandler> {
+        handle_duplicates(&mut self.tasks, name, self.duplicates)?;
+
+        let runner = B::runner(action);
+        let task = Arc::new(ScheduledTask::new(
+            name,
+            rules,
+            options,
+            self.lock.clone(),
+            runner,
+        ));
+        let handler = task.make_handler();
+
+        self.tasks.insert(
+            task.id,
+            TaskEntry {
+                task: task.clone(),
+                handler: handler.clone(),
+            },
+        );
+
+        B::spawn(task);
+
+        Ok(handler)
+    }
+
+    /// 🧉 » list tasks
+    ///
+    /// returns the handlers of all the tasks in the scheduler, in the order they were scheduled.
+    pub fn tasks(&self) -> Vec<TaskHandler> {
+        self.tasks.values().map(|entry| entry.handler.clone()).collect()
+    }
+
+    /// 🧉 » metrics
+    ///
+    /// returns a snapshot of the metrics of every task in the scheduler, which can be rendered
+    /// in the prometheus text format with `MetricsSnapshot::to_prometheus`.
+    pub fn metrics(&self) -> MetricsSnapshot {
+        MetricsSnapshot::new(self.tasks.values().map(|entry| entry.handler.metrics()).collect())
+    }
+
+    /// 🧉 » get a task by name
+    ///
+    /// returns the handler of the task with the given name. If several tasks share the name, the
+    /// latest scheduled one is returned.
+    pub fn get(&self, name: &str) -> Option<TaskHandler> {
+        self.tasks
+            .values()
+            .rev()
+            .find(|entry| entry.handler.name() == name)
+            .map(|entry| entry.handler.clone())
+    }
+
+    /// 🧉 » get a task by id
+    ///
+    /// returns the handler of the task with the given id.
+    pub fn get_by_id(&self, id: TaskId) -> Option<TaskHandler> {
+        self.tasks.get(&id).map(|entry| entry.handler.clone())
+    }
+
+    /// 🧉 » stop a task
+    ///
+    /// the task won't run again until it's resumed. A run in progress is not interrupted.
+    pub fn stop(&mut self, handler: &TaskHandler) -> Result<()> {
+        let task = self.find(handler)?;
+        task.set_stopped(true);
+        debug!("task {} has been stopped", handler.name());
+        Ok(())
+    }
+
+    /// 🧉 » resume a task
+    pub fn resume(&mut self, handler: &TaskHandler) -> Result<()> {
+        let task = self.find(handler)?;
+        task.set_stopped(false);
+        debug!("task {} has been resumed", handler.name());
+        Ok(())
+    }
+
+    /// 🧉 » remove a task
+    ///
+    /// the task is stopped for good and removed from the scheduler. A run in progress is not
+    /// interrupted.
+    pub fn remove(&mut self, handler: &TaskHandler) -> Result<()> {
+        match self.tasks.remove(&handler.id()) {
+            Some(entry) => {
+                entry.task.set_removed();
+                debug!("task {} has been removed", handler.name());
+                Ok(())
+            }
+            None => {
+                handler.is_removed.store(true, Ordering::Relaxed);
+                Err(eyre!("task {} was not found", handler.name()))
+            }
+        }
+    }
+
+    /// 🧉 » execute a control request
+    ///
+    /// maps the request onto `tasks`, `stop`, `resume`, `remove` and `trigger`. Used to answer
+    /// the requests of a control server (see `serve_control`).
+    #[cfg(feature = "sched.control")]
+    pub fn control(&mut self, request: ControlRequest) -> ControlResponse {
+        let tasks = self.tasks();
+
+        let result = match request {
+            ControlRequest::List => return ControlResponse::tasks(&tasks),
+            ControlRequest::Stop { task } => task.find(&tasks).and_then(|h| self.stop(&h)),
+            ControlRequest::Resume { task } => task.find(&tasks).and_then(|h| self.resume(&h)),
+            ControlRequest::Remove { task } => task.find(&tasks).and_then(|h| self.remove(&h)),
+            ControlRequest::Trigger { task } => task.find(&tasks).and_then(|h| self.trigger(&h)),
+        };
+
+        result.into()
+    }
+
+    /// 🧉 » list tasks matching a predicate
+    ///
+    /// returns the handlers of the tasks for which `predicate` returns `true`, e.g.
+    /// `sched.list_where(|task| task.has_tag("reports"))`.
+    pub fn list_where<P>(&self, predicate: P) -> Vec<TaskHandler>
+    where
+        P: Fn(&TaskHandler) -> bool,
+    {
+        self.tasks().into_iter().filter(|handler| predicate(handler)).collect()
+    }
+
+    /// 🧉 » stop tasks matching a predicate
+    ///
+    /// stops the tasks for which `predicate` returns `true`, and returns their handlers.
+    pub fn stop_where<P>(&mut self, predicate: P) -> Vec<TaskHandler>
+    where
+        P: Fn(&TaskHandler) -> bool,
+    {
+        let mut affected = vec![];
+
+        for handler in self.list_where(predicate) {
+            if self.stop(&handler).is_ok() {
+                affected.push(handler);
+            }
+        }
+
+        affected
+    }
+
+    /// 🧉 » resume tasks matching a predicate
+    ///
+    /// resumes the tasks for which `predicate` returns `true`, and returns their handlers.
+    pub fn resume_where<P>(&mut self, predicate: P) -> Vec<TaskHandler>
+    where
+        P: Fn(&TaskHandler) -> bool,
+    {
+        let mut affected = vec![];
+
+        for handler in self.list_where(predicate) {
+            if self.resume(&handler).is_ok() {
+                affected.push(handler);
+            }
+        }
+
+        affected
+    }
+
+    /// 🧉 » remove tasks matching a predicate
+    ///
+    /// removes the tasks for which `predicate` returns `true`, and returns their handlers.
+    pub fn remove_where<P>(&mut self, predicate: P) -> Vec<TaskHandler>
+    where
+        P: Fn(&TaskHandler) -> bool,
+    {
+        let mut affected = vec![];
+
+        for handler in self.list_where(predicate) {
+            if self.remove(&handler).is_ok() {
+                affected.push(handler);
+            }
+        }
+
+        affected
+    }
+
+    /// 🧉 » trigger a task
+    ///
+    /// runs the task right away in the background, without waiting for it to finish.
+    ///
+    /// the run is recorded as `RunTrigger::Manual` in the task history, and the next regular
+    /// occurrence of the task is not affected by it. Runs of the same task never overlap: if the
+    /// task is running at the moment, the manual run starts once the current run finishes.
+    ///
+    /// stopped tasks can be triggered too, removed tasks can't.
+    pub fn trigger(&self, handler: &TaskHandler) -> Result<()> {
+        let task = self.find(handler)?;
+        B::spawn_manual_run(task.clone());
+        debug!("task {} has been triggered", handler.name());
+        Ok(())
+    }
+
+    /// **returns the task of a handler**
+    pub(crate) fn find(&self, handler: &TaskHandler) -> Result<&Arc<ScheduledTask<B>>> {
+        self.tasks
+            .get(&handler.id())
+            .map(|entry| &entry.task)
+            .ok_or_else(|| eyre!("task {} was not found", handler.name()))
+    }
+}
@@ -0,0 +1,256 @@
+use {
+    super::SchedulerBackend,
+    crate::sched::{
+        constraints::{BlockedRunPolicy, TaskConstraints},
+        history::{RunHistory, RunOutcome, RunTrigger},
+        jitter::TaskJitter,
+        lock::{acquire_run_lock, release_run_lock, LockProvider},
+        options::TaskOptions,
+        rules::SchedulingRule,
+        task_handler::{get_next_run_time, TaskHandler, TaskId},
+    },
+    chrono::{DateTime, Local},
+    eyre::eyre,
+    log::debug,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        sync::{
+            atomic::{AtomicBool, AtomicPtr, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    },
+};
+
+/// 🧉 » a scheduled task
+///
+/// this struct represents a task that has been scheduled in a `Scheduler`.
+///
+/// it keeps the state shared by every backend (rules, flags, constraints, locks and history),
+/// while the action of the task, and whatever the backend needs to run it, is kept in the
+/// backend's runner.
+pub struct ScheduledTask<B: SchedulerBackend> {
+    pub(crate) id: TaskId,
+    pub(crate) name: String,
+    pub(crate) runner: B::Runner,
+    pub(crate) timeout: Option<Duration>,
+    rules: Arc<Vec<SchedulingRule>>,
+    jitter: Option<TaskJitter>,
+    tags: Arc<BTreeSet<String>>,
+    metadata: Arc<BTreeMap<String, String>>,
+    is_running: Arc<AtomicBool>,
+    is_stopped: Arc<AtomicBool>,
+    is_removed: Arc<AtomicBool>,
+    last_run: Arc<AtomicPtr<DateTime<Local>>>,
+    history: RunHistory,
+    lock: Option<Arc<dyn LockProvider>>,
+    constraints: Mutex<TaskConstraints>,
+}
+
+/// **a run that passed its checks and is about to run the action**
+pub(crate) struct StartedRun {
+    trigger: RunTrigger,
+    planned_at: DateTime<Local>,
+    started_at: DateTime<Local>,
+    lock: Option<Arc<dyn LockProvider>>,
+}
+
+impl<B: SchedulerBackend> ScheduledTask<B> {
+    pub(crate) fn new(
+        name: &str,
+        rules: Vec<SchedulingRule>,
+        options: TaskOptions,
+        lock: Option<Arc<dyn LockProvider>>,
+        runner: B::Runner,
+    ) -> Self {
+        Self {
+            id: TaskId::generate(),
+            name: name.to_string(),
+            runner,
+            timeout: options.timeout,
+            rules: Arc::new(rules),
+            jitter: options.jitter.map(|jitter| jitter.for_task(name)),
+            tags: Arc::new(options.tags),
+            metadata: Arc::new(options.metadata),
+            is_running: Arc::new(AtomicBool::new(false)),
+            is_stopped: Arc::new(AtomicBool::new(false)),
+            is_removed: Arc::new(AtomicBool::new(false)),
+            last_run: Arc::new(AtomicPtr::new(std::ptr::null_mut())),
+            history: RunHistory::default(),
+            lock,
+            constraints: Mutex::new(TaskConstraints::new(
+                options.constraints,
+                options.blocked_runs,
+            )),
+        }
+    }
+
+    /// **checks whether a run can start, and marks it as started**
+    ///
+    /// returns the outcome of the run if it has to be skipped, which is already recorded in the
+    /// history. Otherwise, the run must be ended with `finish_run` once the action returns.
+    pub(crate) fn start_run(
+        &self,
+        trigger: RunTrigger,
+        planned_at: DateTime<Local>,
+    ) -> Result<StartedRun, RunOutcome> {
+        let started_at = Local::now();
+        let mut constraints = self.constraints.lock().unwrap();
+
+        if let Err(blocked) = constraints.check(started_at) {
+            return Err(self.skip(trigger, planned_at, started_at, blocked.reason));
+        }
+
+        // only scheduled runs are shared with other schedulers, manual runs are always local
+        let lock = match trigger {
+            RunTrigger::Scheduled => self.lock.clone(),
+            RunTrigger::Manual => None,
+        };
+
+        if let Some(lock) = &lock {
+            if let Err(reason) = acquire_run_lock(lock.as_ref(), &self.name, planned_at) {
+                return Err(self.skip(trigger, planned_at, started_at, reason));
+            }
+        }
+
+        constraints.record_run(started_at);
+
+        let run_date_box = Box::new(planned_at);
+        let run_date_raw = Box::into_raw(run_date_box);
+        self.last_run.store(run_date_raw, Ordering::Relaxed);
+
+        self.is_running.store(true, Ordering::SeqCst);
+
+        Ok(StartedRun {
+            trigger,
+            planned_at,
+            started_at,
+            lock,
+        })
+    }
+
+    /// **ends a run started with `start_run`, recording its outcome**
+    pub(crate) fn finish_run(&self, run: StartedRun, outcome: RunOutcome) -> RunOutcome {
+        self.is_running.store(false, Ordering::SeqCst);
+
+        if let Some(lock) = &run.lock {
+            release_run_lock(lock.as_ref(), &self.name, run.planned_at);
+        }
+
+        self.history.record(run.trigger, run.planned_at, run.started_at, outcome)
+    }
+
+    /// **records a run that was skipped, returning its outcome**
+    pub(crate) fn skip(
+        &self,
+        trigger: RunTrigger,
+        planned_at: DateTime<Local>,
+        started_at: DateTime<Local>,
+        reason: String,
+    ) -> RunOutcome {
+        debug!("task {} skipped: {}", self.name, reason);
+        let outcome = RunOutcome::Skipped(reason);
+        self.history.record(trigger, planned_at, started_at, outcome)
+    }
+
+    /// **returns the date the constraints of the task defer its next run to**
+    ///
+    /// returns `None` if the run doesn't have to wait, or if it can't be deferred (in which case
+    /// it will be skipped).
+    pub(crate) fn deferred_until(&self) -> Option<DateTime<Local>> {
+        let mut constraints = self.constraints.lock().unwrap();
+
+        if !self.is_active() || constraints.policy() != BlockedRunPolicy::Defer {
+            return None;
+        }
+
+        match constraints.check(Local::now()) {
+            Ok(()) => None,
+            Err(blocked) => {
+                debug!("task {} deferred: {}", self.name, blocked.reason);
+                blocked.retry_at
+            }
+        }
+    }
+
+    /// **returns the date of the first run of the task**
+    pub(crate) fn first_run(&self) -> Option<DateTime<Local>> {
+        get_next_run_time(&self.rules, self.jitter.as_ref(), None)
+    }
+
+    /// **returns the date of the run following the one planned at `run_date`**
+    ///
+    /// `deferred_until` is the date the run waited for its constraints until, if it did.
+    pub(crate) fn next_run(
+        &self,
+        run_date: DateTime<Local>,
+        deferred_until: Option<DateTime<Local>>,
+    ) -> Option<DateTime<Local>> {
+        if self.is_removed() {
+            return None;
+        }
+
+        // occurrences that fell while the run was deferred are merged into it
+        let base = deferred_until.map_or(run_date, |date| date.max(run_date));
+        get_next_run_time(&self.rules, self.jitter.as_ref(), Some(base))
+    }
+
+    /// **logs how long the task will sleep until its next run**
+    pub(crate) fn log_next_run(&self, run_date: DateTime<Local>) {
+        let now = Local::now();
+
+        if run_date > now {
+            debug!(
+                "task {} will run in {} ms",
+                self.name,
+                (run_date - now).num_milliseconds()
+            );
+        } else {
+            // the next run is in the past, probably missed the run time for a few nanos
+            debug!("task {} will run in 0 ms", self.name);
+        }
+    }
+
+    pub(crate) fn make_handler(self: &Arc<Self>) -> TaskHandler {
+        let task = Arc::downgrade(self);
+        let name = self.name.clone();
+        let is_removed = self.is_removed.clone();
+
+        TaskHandler {
+            id: self.id,
+            name: self.name.clone(),
+            rules: self.rules.clone(),
+            jitter: self.jitter,
+            tags: self.tags.clone(),
+            metadata: self.metadata.clone(),
+            is_running: self.is_running.clone(),
+            is_stopped: self.is_stopped.clone(),
+            is_removed: self.is_removed.clone(),
+            last_run: self.last_run.clone(),
+            history: self.history.clone(),
+            trigger: Arc::new(move || match task.upgrade() {
+                Some(task) if !is_removed.load(Ordering::Relaxed) => {
+                    B::spawn_manual_run(task);
+                    Ok(())
+                }
+                _ => Err(eyre!("task {} was not found", name)),
+            }),
+        }
+    }
+
+    pub(crate) fn set_stopped(&self, stopped: bool) {
+        self.is_stopped.store(stopped, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_removed(&self) {
+        self.is_removed.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        !self.is_stopped.load(Ordering::Relaxed) && !self.is_removed.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn is_removed(&self) -> bool {
+        self.is_removed.load(Ordering::Relaxed)
+    }
+}
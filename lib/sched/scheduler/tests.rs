@@ -0,0 +1,437 @@
+//! tests shared by every backend, run once per enabled backend by `backend_tests!`
+
+use {
+    super::*,
+    crate::sched::{
+        every,
+        history::{RunOutcome, RunRecord, RunTrigger},
+        lock::FileLockProvider,
+        once,
+    },
+    chrono::{Duration, Local},
+    eyre::{set_hook, DefaultHandler},
+    std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    },
+};
+
+fn setup_eyre() {
+    let _ = set_hook(Box::new(DefaultHandler::default_with));
+}
+
+/// a backend the shared tests run against
+trait TestBackend: SchedulerBackend {
+    /// name of the backend, to keep the files of each backend apart
+    const NAME: &'static str;
+
+    /// an action doing nothing
+    fn noop() -> impl IntoAction<Self>;
+
+    /// an action counting its runs
+    fn counter(counter: Arc<AtomicUsize>) -> impl IntoAction<Self>;
+
+    /// runs a test where the backend can spawn tasks
+    fn run_test(test: fn());
+}
+
+#[cfg(feature = "sched.threads")]
+impl TestBackend for threads::ThreadsBackend {
+    const NAME: &'static str = "threads";
+
+    fn noop() -> impl IntoAction<Self> {
+        || {}
+    }
+
+    fn counter(counter: Arc<AtomicUsize>) -> impl IntoAction<Self> {
+        move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn run_test(test: fn()) {
+        test();
+    }
+}
+
+#[cfg(feature = "sched.tokio")]
+impl TestBackend for tokio::TokioBackend {
+    const NAME: &'static str = "tokio";
+
+    fn noop() -> impl IntoAction<Self> {
+        || async {}
+    }
+
+    fn counter(counter: Arc<AtomicUsize>) -> impl IntoAction<Self> {
+        move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    fn run_test(test: fn()) {
+        use ::tokio::{runtime::Builder, sync::oneshot};
+
+        // the runtime is driven by its own thread, so the test can block while tasks run
+        let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+        let handle = runtime.handle().clone();
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+        let driver = thread::spawn(move || {
+            let _ = runtime.block_on(stop_rx);
+        });
+
+        {
+            let _guard = handle.enter();
+            test();
+        }
+
+        let _ = stop_tx.send(());
+        driver.join().unwrap();
+    }
+}
+
+/// generates a `#[test]` per shared test and enabled backend
+macro_rules! backend_tests {
+    ($($(#[$meta:meta])* $test:ident),* $(,)?) => {
+        #[cfg(feature = "sched.threads")]
+        mod threads_backend {
+            use super::*;
+
+            $(
+                $(#[$meta])*
+                #[test]
+                fn $test() {
+                    <threads::ThreadsBackend as TestBackend>::run_test(
+                        super::$test::<threads::ThreadsBackend>,
+                    );
+                }
+            )*
+        }
+
+        #[cfg(feature = "sched.tokio")]
+        mod tokio_backend {
+            use super::*;
+
+            $(
+                $(#[$meta])*
+                #[test]
+                fn $test() {
+                    <tokio::TokioBackend as TestBackend>::run_test(
+                        super::$test::<tokio::TokioBackend>,
+                    );
+                }
+            )*
+        }
+    };
+}
+
+backend_tests!(
+    test_run_now_runs_the_task,
+    test_trigger_removed_task_errs,
+    test_duplicate_policy_replace,
+    test_duplicate_policy_error,
+    test_duplicate_policy_allow,
+    test_bulk_operations_by_tag,
+    test_max_runs_constraint_skips_runs,
+    test_lock_provider_runs_each_occurrence_once,
+    test_interval_ticks_dont_drift,
+    test_metrics_snapshot,
+    #[cfg(feature = "sched.control")]
+    test_control_server,
+);
+
+/// waits up to a second for a task to have at least `runs` records in its history
+fn wait_for_runs(handler: &TaskHandler, runs: usize) {
+    for _ in 0..100 {
+        if handler.history().len() >= runs {
+            break;
+        }
+        thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+fn test_run_now_runs_the_task<B: TestBackend>() {
+    let next_run = Local::now() + Duration::days(1);
+
+    let mut sched = Scheduler::<B>::new();
+    let handler = sched.schedule("run-now", B::noop(), once(next_run)).unwrap();
+
+    handler.run_now().unwrap();
+    wait_for_runs(&handler, 1);
+
+    assert_eq!(handler.history().len(), 1);
+    assert_eq!(handler.last_record().unwrap().trigger, RunTrigger::Manual);
+    // the regular occurrence is left untouched
+    assert_eq!(handler.get_next_run(), Some(next_run));
+}
+
+fn test_trigger_removed_task_errs<B: TestBackend>() {
+    setup_eyre();
+    let mut sched = Scheduler::<B>::new();
+    let handler =
+        sched.schedule("removed", B::noop(), once(Local::now() + Duration::days(1))).unwrap();
+
+    sched.remove(&handler).unwrap();
+
+    assert!(sched.trigger(&handler).is_err());
+    assert!(handler.run_now().is_err());
+}
+
+fn test_duplicate_policy_replace<B: TestBackend>() {
+    let next_run = Local::now() + Duration::days(1);
+
+    let mut sched = Scheduler::<B>::new();
+    let old = sched.schedule("dup", B::noop(), once(next_run)).unwrap();
+    let new = sched.schedule("dup", B::noop(), once(next_run)).unwrap();
+
+    assert_ne!(old.id(), new.id());
+    assert!(old.is_removed());
+    assert!(!new.is_removed());
+    assert_eq!(sched.tasks().len(), 1);
+    assert_eq!(sched.get("dup").unwrap().id(), new.id());
+}
+
+fn test_duplicate_policy_error<B: TestBackend>() {
+    setup_eyre();
+    let next_run = Local::now() + Duration::days(1);
+
+    let mut sched = Scheduler::<B>::new().with_duplicate_policy(DuplicatePolicy::Error);
+    let first = sched.schedule("dup", B::noop(), once(next_run)).unwrap();
+
+    assert!(sched.schedule("dup", B::noop(), once(next_run)).is_err());
+    assert!(!first.is_removed());
+    assert_eq!(sched.tasks().len(), 1);
+}
+
+fn test_duplicate_policy_allow<B: TestBackend>() {
+    let next_run = Local::now() + Duration::days(1);
+
+    let mut sched = Scheduler::<B>::new().with_duplicate_policy(DuplicatePolicy::Allow);
+    let first = sched.schedule("dup", B::noop(), once(next_run)).unwrap();
+    let second = sched.schedule("dup", B::noop(), once(next_run)).unwrap();
+
+    assert_eq!(sched.tasks().len(), 2);
+    assert_eq!(sched.get("dup").unwrap().id(), second.id());
+
+    // each task can be controlled on its own
+    sched.stop(&first).unwrap();
+    assert!(first.is_stopped());
+    assert!(!second.is_stopped());
+
+    sched.remove(&second).unwrap();
+    assert_eq!(sched.get("dup").unwrap().id(), first.id());
+    assert_eq!(sched.get_by_id(first.id()).unwrap().name(), "dup");
+    assert!(sched.get_by_id(second.id()).is_none());
+}
+
+fn test_bulk_operations_by_tag<B: TestBackend>() {
+    let next_run = Local::now() + Duration::days(1);
+    let mut sched = Scheduler::<B>::new();
+
+    for (name, tag) in [("a", "reports"), ("b", "reports"), ("c", "cleanup")] {
+        let options = TaskOptions::new().with_tag(tag).with_metadata("owner", name);
+        sched.schedule_with_options(name, B::noop(), vec![once(next_run)], options).unwrap();
+    }
+
+    let reports = sched.list_where(|task| task.has_tag("reports"));
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0].get_metadata("owner"), Some("a"));
+
+    let stopped = sched.stop_where(|task| task.has_tag("reports"));
+    assert_eq!(stopped.len(), 2);
+    assert!(stopped.iter().all(|task| task.is_stopped()));
+    assert!(!sched.get("c").unwrap().is_stopped());
+
+    let resumed = sched.resume_where(|task| task.has_any_tag(&["reports", "cleanup"]));
+    assert_eq!(resumed.len(), 3);
+    assert!(resumed.iter().all(|task| task.is_active()));
+
+    let removed = sched.remove_where(|task| task.has_tag("cleanup"));
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].name(), "c");
+    assert_eq!(sched.tasks().len(), 2);
+}
+
+fn test_max_runs_constraint_skips_runs<B: TestBackend>() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let next_run = Local::now() + Duration::days(1);
+    let options = TaskOptions::new().with_max_runs(2, std::time::Duration::from_secs(3600));
+
+    let mut sched = Scheduler::<B>::new();
+    let action = B::counter(counter.clone());
+    let handler =
+        sched.schedule_with_options("max-runs", action, vec![once(next_run)], options).unwrap();
+
+    for _ in 0..3 {
+        sched.trigger(&handler).unwrap();
+    }
+    wait_for_runs(&handler, 3);
+
+    let outcomes: Vec<RunOutcome> =
+        handler.history().into_iter().map(|record| record.outcome).collect();
+    assert_eq!(outcomes.len(), 3);
+    assert_eq!(
+        outcomes.iter().filter(|outcome| **outcome == RunOutcome::Completed).count(),
+        2
+    );
+    assert_eq!(counter.load(Ordering::SeqCst), 2);
+}
+
+fn test_lock_provider_runs_each_occurrence_once<B: TestBackend>() {
+    let dir = std::env::temp_dir().join(format!(
+        "lool-sched-lock-{}-{}",
+        B::NAME,
+        std::process::id()
+    ));
+    let run_date = Local::now() + Duration::milliseconds(200);
+
+    // both schedulers must be alive until the occurrence has run
+    let scheds: Vec<(Scheduler<B>, TaskHandler)> = (0..2)
+        .map(|_| {
+            let lock = FileLockProvider::new(&dir).unwrap();
+            let mut sched = Scheduler::<B>::new().with_lock_provider(lock);
+            let handler = sched.schedule("locked-task", B::noop(), once(run_date)).unwrap();
+            (sched, handler)
+        })
+        .collect();
+
+    for (_, handler) in &scheds {
+        wait_for_runs(handler, 1);
+    }
+
+    let mut outcomes: Vec<RunOutcome> =
+        scheds.iter().map(|(_, handler)| handler.last_record().unwrap().outcome).collect();
+    outcomes.sort_by_key(|outcome| *outcome != RunOutcome::Completed);
+
+    assert_eq!(outcomes[0], RunOutcome::Completed);
+    assert!(matches!(outcomes[1], RunOutcome::Skipped(_)));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/// checks that the runs of an interval task are aligned to the period and that none of them was
+/// missed.
+///
+/// a busy machine can start any single run late, so the lateness is only checked as a whole: most
+/// runs start within a few milliseconds of their planned date, and none of them is very late.
+fn assert_ticks_dont_drift(history: &[RunRecord], period: std::time::Duration) {
+    let period = Duration::from_std(period).unwrap();
+    assert!(history.len() >= 20, "only {} ticks ran", history.len());
+
+    for pair in history.windows(2) {
+        assert_eq!(pair[1].planned_at - pair[0].planned_at, period);
+    }
+
+    let mut lateness: Vec<Duration> = history
+        .iter()
+        .map(|record| {
+            let subsec = record.planned_at.timestamp_subsec_nanos() as i64;
+            assert_eq!(subsec % period.num_nanoseconds().unwrap(), 0);
+            record.started_at - record.planned_at
+        })
+        .collect();
+    lateness.sort();
+
+    assert!(
+        lateness[0] >= Duration::zero(),
+        "a run started before its planned date"
+    );
+
+    let median = lateness[lateness.len() / 2];
+    assert!(
+        median < Duration::milliseconds(15),
+        "median lateness of {} ms",
+        median.num_milliseconds()
+    );
+
+    let max = lateness[lateness.len() - 1];
+    assert!(
+        max < Duration::milliseconds(500),
+        "a run started {} ms late",
+        max.num_milliseconds()
+    );
+}
+
+fn test_interval_ticks_dont_drift<B: TestBackend>() {
+    let period = std::time::Duration::from_millis(25);
+
+    let mut sched = Scheduler::<B>::new();
+    let handler = sched.schedule("sampler", B::noop(), every(period).unwrap()).unwrap();
+
+    thread::sleep(period * 40);
+    sched.stop(&handler).unwrap();
+    assert_ticks_dont_drift(&handler.history(), period);
+}
+
+fn test_metrics_snapshot<B: TestBackend>() {
+    let mut sched = Scheduler::<B>::new();
+    let run_date = Local::now() + Duration::milliseconds(50);
+    let handler = sched.schedule("metrics", B::noop(), once(run_date)).unwrap();
+    let idle = sched.schedule("idle", B::noop(), once(Local::now() + Duration::days(1))).unwrap();
+
+    wait_for_runs(&handler, 1);
+
+    let snapshot = sched.metrics();
+    assert_eq!(snapshot.tasks.len(), 2);
+
+    let metrics = &snapshot.tasks[0];
+    assert_eq!(metrics.id, handler.id());
+    assert_eq!(metrics.runs, 1);
+    assert_eq!(metrics.durations.count, 1);
+    assert!(metrics.lateness.unwrap() >= Duration::zero());
+    assert_eq!(metrics.next_run, None);
+
+    assert_eq!(snapshot.tasks[1].runs, 0);
+    assert_eq!(snapshot.tasks[1].next_run, idle.get_next_run());
+    assert!(snapshot.to_prometheus().contains("lool_task_runs_total{task=\"metrics\""));
+}
+
+#[cfg(feature = "sched.control")]
+fn test_control_server<B: TestBackend>() {
+    use {
+        crate::sched::control::{serve_control, ControlClient, ControlEndpoint, TaskState},
+        std::sync::Mutex,
+    };
+
+    setup_eyre();
+    let sched = Arc::new(Mutex::new(Scheduler::<B>::new()));
+    let handler = {
+        let mut sched = sched.lock().unwrap();
+        sched.schedule("backup", B::noop(), once(Local::now() + Duration::days(1))).unwrap()
+    };
+
+    let control = sched.clone();
+    let endpoint = ControlEndpoint::Tcp(([127, 0, 0, 1], 0).into());
+    let server = serve_control(endpoint, move |request| {
+        control.lock().unwrap().control(request)
+    })
+    .unwrap();
+    let mut client = ControlClient::connect(server.endpoint()).unwrap();
+
+    let tasks = client.list().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].name, "backup");
+    assert_eq!(tasks[0].state, TaskState::Active);
+
+    client.stop("backup").unwrap();
+    assert!(handler.is_stopped());
+    assert_eq!(client.list().unwrap()[0].state, TaskState::Stopped);
+
+    client.resume(handler.id().value()).unwrap();
+    assert!(handler.is_active());
+
+    client.trigger("backup").unwrap();
+    wait_for_runs(&handler, 1);
+    assert_eq!(handler.last_record().unwrap().trigger, RunTrigger::Manual);
+
+    client.remove("backup").unwrap();
+    assert!(handler.is_removed());
+    assert!(client.list().unwrap().is_empty());
+
+    let err = client.stop("backup").unwrap_err();
+    assert_eq!(err.to_string(), "task backup was not found");
+
+    server.shutdown();
+}
@@ -1,245 +1,412 @@
 use {
     crate::sched::{
+        cancellation::CancellationToken,
+        history::{RunOutcome, RunTrigger},
+        options::TaskOptions,
         rules::SchedulingRule,
-        task_handler::{get_next_run_time, TaskHandler},
+        scheduler::{self, IntoAction, SchedulerBackend},
+        task_handler::TaskHandler,
     },
     chrono::{DateTime, Local},
     eyre::{eyre, Result},
     log::debug,
     std::{
-        collections::HashMap,
+        panic::{catch_unwind, AssertUnwindSafe},
         sync::{
-            atomic::{AtomicBool, AtomicPtr, Ordering},
-            Arc, Mutex,
+            mpsc::{self, RecvTimeoutError},
+            Arc, Mutex, PoisonError, TryLockError,
         },
-        thread::{self, JoinHandle},
+        thread,
     },
 };
 
-type Action = Box<dyn FnMut() + Send + Sync + 'static>;
+type Action = Box<dyn FnMut(&CancellationToken) + Send + 'static>;
 
-/// 🧉 » a scheduled task
+/// 🧉 » threads backend
 ///
-/// this structs represents a task that has been scheduled in the scheduler.
+/// runs each task in its own thread, which sleeps until the next run of the task.
+pub struct ThreadsBackend;
+
+/// 🧉 » a task scheduler running each task in its own thread
+pub type Scheduler = scheduler::Scheduler<ThreadsBackend>;
+
+/// 🧉 » a task scheduled in a threads scheduler
+pub type ScheduledTask = scheduler::ScheduledTask<ThreadsBackend>;
+
+/// 🧉 » threads runner
 ///
-/// this is returned by the `Scheduler::schedule` method, and can be used to check and control the
-/// status of the task.
-pub struct ScheduledTask {
-    #[allow(dead_code)]
-    name: String,
-    action: Action,
-    rules: Arc<Vec<SchedulingRule>>,
-    is_running: Arc<AtomicBool>,
-    is_stopped: Arc<AtomicBool>,
-    is_removed: Arc<AtomicBool>,
-    last_run: Arc<AtomicPtr<DateTime<Local>>>,
+/// the action of a task, and the lock keeping its runs from overlapping. Only used by the backend.
+pub struct ThreadsRunner {
+    /// held during a whole run, including its checks
+    running: Mutex<()>,
+    /// shared with the thread running the action when the task has a timeout
+    action: Arc<Mutex<Action>>,
 }
 
-impl ScheduledTask {
-    fn run(&mut self) {
-        let action = self.action.as_mut();
-        action();
-    }
+impl SchedulerBackend for ThreadsBackend {
+    type Action = Action;
+    type Runner = ThreadsRunner;
 
-    fn make_handler(&self) -> TaskHandler {
-        TaskHandler {
-            name: self.name.clone(),
-            rules: self.rules.clone(),
-            is_running: self.is_running.clone(),
-            is_stopped: self.is_stopped.clone(),
-            is_removed: self.is_removed.clone(),
-            last_run: self.last_run.clone(),
+    fn runner(action: Action) -> ThreadsRunner {
+        ThreadsRunner {
+            running: Mutex::new(()),
+            action: Arc::new(Mutex::new(action)),
         }
     }
 
-    fn is_active(&self) -> bool {
-        !self.is_stopped.load(Ordering::Relaxed) && !self.is_removed.load(Ordering::Relaxed)
-    }
+    fn spawn(task: Arc<ScheduledTask>) {
+        thread::spawn(move || {
+            let mut maybe_next_run = task.first_run();
+
+            while let Some(run_date) = maybe_next_run {
+                task.log_next_run(run_date);
+                sleep_until(run_date);
+
+                let deferred_until = wait_for_constraints(&task);
+
+                if task.is_active() {
+                    run(&task, RunTrigger::Scheduled, run_date);
+                }
+
+                maybe_next_run = task.next_run(run_date, deferred_until);
+            }
 
-    fn is_removed(&self) -> bool {
-        self.is_removed.load(Ordering::Relaxed)
+            debug!("task {} has finished", task.name);
+        });
     }
-}
 
-/// 🧉 » a task scheduler.
-///
-/// this struct is responsible for scheduling tasks to be executed at specific times, depending on
-/// the rules provided for each task.
-///
-/// Each task can have n rules, and the task will be executed when any of the rules is met.
-pub struct Scheduler {
-    tasks: HashMap<String, Arc<Mutex<ScheduledTask>>>,
+    fn spawn_manual_run(task: Arc<ScheduledTask>) {
+        thread::spawn(move || {
+            if let Err(err) = run_manually(&task) {
+                debug!("{}", err);
+            }
+        });
+    }
 }
 
-impl Default for Scheduler {
-    fn default() -> Self {
-        Self::new()
+impl<F> IntoAction<ThreadsBackend> for F
+where
+    F: FnMut() + Send + 'static,
+{
+    fn into_action(mut self) -> Action {
+        Box::new(move |_: &CancellationToken| self())
     }
 }
 
 impl Scheduler {
-    /// 🧉 » create a new scheduler
-    pub fn new() -> Self {
-        Self {
-            tasks: HashMap::new(),
-        }
-    }
-
     /// 🧉 » schedule a task
     ///
     /// schedules a task to be executed at times determined by the provided rules.
-    pub fn schedule<F, Str>(&mut self, name: Str, action: F, rules: SchedulingRule) -> TaskHandler
+    pub fn schedule_many_rules<F>(
+        &mut self,
+        name: &str,
+        action: F,
+        rules: Vec<SchedulingRule>,
+    ) -> Result<TaskHandler>
     where
-        F: FnMut() + Send + Sync + 'static,
-        Str: AsRef<str>,
+        F: FnMut() + Send + 'static,
     {
-        let name = name.as_ref();
-        self.schedule_many_rules(name, action, vec![rules])
+        self.schedule_with_options(name, action, rules, TaskOptions::default())
     }
 
-    /// 🧉 » schedule a task
+    /// 🧉 » schedule a cancellable task
     ///
-    /// schedules a task to be executed at times determined by the provided rules.
-    pub fn schedule_many_rules<F>(
+    /// same as `schedule_with_options`, but the action gets the `CancellationToken` of each run,
+    /// which is cancelled when the run exceeds the timeout of the task.
+    pub fn schedule_cancellable<F>(
         &mut self,
         name: &str,
         action: F,
         rules: Vec<SchedulingRule>,
-    ) -> TaskHandler
+        options: TaskOptions,
+    ) -> Result<TaskHandler>
     where
-        F: FnMut() + Send + Sync + 'static,
+        F: FnMut(&CancellationToken) + Send + 'static,
     {
-        let task = Arc::new(Mutex::new(ScheduledTask {
-            name: name.to_string(),
-            action: Box::new(action),
-            rules: Arc::new(rules),
-            is_running: Arc::new(AtomicBool::new(false)),
-            is_stopped: Arc::new(AtomicBool::new(false)),
-            is_removed: Arc::new(AtomicBool::new(false)),
-            last_run: Arc::new(AtomicPtr::new(std::ptr::null_mut())),
-        }));
-
-        self.tasks.insert(name.to_string(), task.clone());
-
-        // launch the task in its own thread
-        spawn_task(task.clone());
-
-        let handler: TaskHandler = {
-            let task = task.lock().unwrap();
-            task.make_handler()
-        };
-
-        handler
-    }
-
-    /// 🧉 » stop a task
-    pub fn stop(&mut self, handler: &TaskHandler) -> Result<()> {
-        // get the task from the tasks map
-        let task = self.tasks.get(handler.name());
-
-        if let Some(task) = task {
-            if let Ok(task) = task.lock() {
-                task.is_stopped.store(true, Ordering::Relaxed);
-                debug!("task {} has been stopped", handler.name());
-                Ok(())
-            } else {
-                Err(eyre!("error stopping task {}", handler.name()))
-            }
-        } else {
-            Err(eyre!("task {} was not found", handler.name()))
-        }
+        self.insert_task(name, Box::new(action), rules, options)
+    }
+
+    /// 🧉 » trigger a task and wait for it
+    ///
+    /// same as `trigger`, but the task runs in the current thread, which is blocked until the run
+    /// finishes. Returns the outcome of the run.
+    pub fn trigger_blocking(&self, handler: &TaskHandler) -> Result<RunOutcome> {
+        let task = self.find(handler)?;
+        debug!("task {} has been triggered", handler.name());
+        run_manually(task)
     }
+}
 
-    /// 🧉 » resume a task
-    pub fn resume(&mut self, handler: &TaskHandler) -> Result<()> {
-        // get the task from the tasks map
-        let task = self.tasks.get(handler.name());
+/// **runs a task once, unless its checks skip the run**
+fn run(task: &ScheduledTask, trigger: RunTrigger, planned_at: DateTime<Local>) -> RunOutcome {
+    // a run that panicked must not keep the task from running again
+    let _running = task.runner.running.lock().unwrap_or_else(PoisonError::into_inner);
 
-        if let Some(task) = task {
-            if let Ok(task) = task.lock() {
-                task.is_stopped.store(false, Ordering::Relaxed);
-                debug!("task {} has been resumed", handler.name());
-                Ok(())
-            } else {
-                Err(eyre!("error resuming task {}", handler.name()))
-            }
-        } else {
-            Err(eyre!("task {} was not found", handler.name()))
+    // a run that timed out may still be running in the background
+    if let Err(TryLockError::WouldBlock) = task.runner.action.try_lock() {
+        let reason = "the previous run timed out and is still running".to_string();
+        return task.skip(trigger, planned_at, Local::now(), reason);
+    }
+
+    match task.start_run(trigger, planned_at) {
+        Ok(started) => {
+            let outcome = run_action(task);
+            task.finish_run(started, outcome)
         }
+        Err(outcome) => outcome,
     }
+}
 
-    /// 🧉 » remove a task
-    pub fn remove(&mut self, handler: &TaskHandler) -> Result<()> {
-        // get the task from the tasks map
-        let task = self.tasks.remove(handler.name());
+/// **runs the action, in its own thread if the task has a timeout**
+fn run_action(task: &ScheduledTask) -> RunOutcome {
+    let token = CancellationToken::default();
 
-        if let Some(task) = task {
-            if let Ok(task) = &mut task.lock() {
-                task.is_removed.store(true, Ordering::Relaxed);
-                debug!("task {} has been removed", handler.name());
+    let timeout = match task.timeout {
+        Some(timeout) => timeout,
+        None => return call_action(&task.runner.action, &token),
+    };
 
-                Ok(())
-            } else {
-                Err(eyre!("error removing task {}", handler.name()))
-            }
-        } else {
-            handler.is_removed.store(true, Ordering::Relaxed);
-            Err(eyre!("task {} was not found", handler.name()))
+    let (done_tx, done_rx) = mpsc::channel();
+    let action = task.runner.action.clone();
+    let action_token = token.clone();
+
+    thread::spawn(move || {
+        let _ = done_tx.send(call_action(&action, &action_token));
+    });
+
+    match done_rx.recv_timeout(timeout) {
+        Ok(outcome) => outcome,
+        Err(RecvTimeoutError::Timeout) => {
+            token.cancel();
+            debug!("task {} timed out after {:?}", task.name, timeout);
+            RunOutcome::TimedOut
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+            RunOutcome::Failed("the action thread stopped without an outcome".to_string())
         }
     }
 }
 
-/// **main function to run the task in its own thread**
+/// **calls the action, catching its panics**
 ///
-/// it creates a new thread and runs the task according to its scheduling rules.
-fn spawn_task(task_mutex: Arc<Mutex<ScheduledTask>>) -> JoinHandle<()> {
-    let thread = thread::spawn(move || {
-        let (mut maybe_next_run, name) = {
-            let task = task_mutex.lock().unwrap();
-            let rules = &task.rules;
-
-            (get_next_run_time(rules, None), task.name.clone())
-        };
-
-        while let Some(run_date) = maybe_next_run {
-            let now = Local::now();
-            if run_date > now {
-                // if the next run is in the future, go to bed until then
-                let sleep_until = run_date - now;
-                debug!(
-                    "task {} will run in {} seconds",
-                    name,
-                    sleep_until.num_seconds()
-                );
-
-                std::thread::sleep(sleep_until.to_std().unwrap());
-            } else {
-                // if the next run is in the past, run the task immediately, probably missed the
-                // run time for a few nanos
-                debug!("task will run in 0 seconds");
-            }
+/// a panic poisons the lock of the action, which is ignored so later runs can still call it.
+fn call_action(action: &Mutex<Action>, token: &CancellationToken) -> RunOutcome {
+    let mut action = action.lock().unwrap_or_else(PoisonError::into_inner);
+
+    match catch_unwind(AssertUnwindSafe(|| action(token))) {
+        Ok(()) => RunOutcome::Completed,
+        Err(payload) => RunOutcome::panicked(payload.as_ref()),
+    }
+}
 
-            let mut task = task_mutex.lock().unwrap();
+/// **runs a task once because of a manual trigger**
+fn run_manually(task: &ScheduledTask) -> Result<RunOutcome> {
+    let requested_at = Local::now();
 
-            if task.is_active() {
-                let run_date_box = Box::new(run_date);
-                let run_date_raw = Box::into_raw(run_date_box);
+    if task.is_removed() {
+        return Err(eyre!("task {} has been removed", task.name));
+    }
 
-                task.last_run.store(run_date_raw, Ordering::Relaxed);
-                task.is_running.store(true, Ordering::SeqCst);
-                task.run();
-                task.is_running.store(false, Ordering::SeqCst);
-            }
+    Ok(run(task, RunTrigger::Manual, requested_at))
+}
 
-            if !task.is_removed() {
-                maybe_next_run = get_next_run_time(&task.rules, Some(run_date));
-            } else {
-                maybe_next_run = None;
-            }
+/// **sleeps until the given date**
+///
+/// sleeps again if it wakes up before the date (e.g. after the system clock has been adjusted),
+/// so runs start within a millisecond of their date.
+fn sleep_until(date: DateTime<Local>) {
+    while let Ok(duration) = (date - Local::now()).to_std() {
+        if duration.is_zero() {
+            break;
         }
 
-        debug!("task {} has finished", name);
-    });
+        thread::sleep(duration);
+    }
+}
 
-    thread
+/// **waits while the constraints of a task defer its next run**
+///
+/// returns the date the wait ended, or `None` if the run didn't have to wait.
+fn wait_for_constraints(task: &ScheduledTask) -> Option<DateTime<Local>> {
+    let mut waited_until = None;
+
+    while let Some(retry_at) = task.deferred_until() {
+        sleep_until(retry_at);
+        waited_until = Some(Local::now());
+    }
+
+    waited_until
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::sched::once,
+        chrono::Duration,
+        std::sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    #[test]
+    fn test_trigger_blocking_runs_the_task() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let action_counter = counter.clone();
+        let next_run = Local::now() + Duration::days(1);
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule(
+                "trigger-blocking",
+                move || {
+                    action_counter.fetch_add(1, Ordering::SeqCst);
+                },
+                once(next_run),
+            )
+            .unwrap();
+
+        let outcome = sched.trigger_blocking(&handler).unwrap();
+
+        assert_eq!(outcome, RunOutcome::Completed);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert_eq!(handler.last_record().unwrap().trigger, RunTrigger::Manual);
+        // the regular occurrence is left untouched
+        assert_eq!(handler.get_next_run(), Some(next_run));
+    }
+    #[test]
+    fn test_timeout_cancels_the_run() {
+        let next_run = Local::now() + Duration::days(1);
+        let options = TaskOptions::new().with_timeout(std::time::Duration::from_millis(50));
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule_cancellable(
+                "timeout",
+                |token: &CancellationToken| {
+                    while !token.is_cancelled() {
+                        thread::sleep(std::time::Duration::from_millis(5));
+                    }
+                },
+                vec![once(next_run)],
+                options,
+            )
+            .unwrap();
+
+        assert_eq!(
+            sched.trigger_blocking(&handler).unwrap(),
+            RunOutcome::TimedOut
+        );
+        assert!(!handler.is_running());
+
+        // the action returns once cancelled, so the next run isn't blocked
+        thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(
+            sched.trigger_blocking(&handler).unwrap(),
+            RunOutcome::TimedOut
+        );
+    }
+
+    #[test]
+    fn test_timed_out_action_still_running_skips_runs() {
+        let next_run = Local::now() + Duration::days(1);
+        let options = TaskOptions::new().with_timeout(std::time::Duration::from_millis(20));
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule_with_options(
+                "hung",
+                || thread::sleep(std::time::Duration::from_millis(300)),
+                vec![once(next_run)],
+                options,
+            )
+            .unwrap();
+
+        assert_eq!(
+            sched.trigger_blocking(&handler).unwrap(),
+            RunOutcome::TimedOut
+        );
+
+        let outcome = sched.trigger_blocking(&handler).unwrap();
+        assert!(matches!(outcome, RunOutcome::Skipped(_)));
+    }
+
+    #[test]
+    fn test_panicking_action_fails_the_run() {
+        let next_run = Local::now() + Duration::days(1);
+        let panics = Arc::new(AtomicUsize::new(0));
+        let action_panics = panics.clone();
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule(
+                "panicking",
+                move || {
+                    if action_panics.fetch_add(1, Ordering::SeqCst) == 0 {
+                        panic!("boom");
+                    }
+                },
+                once(next_run),
+            )
+            .unwrap();
+
+        assert_eq!(
+            sched.trigger_blocking(&handler).unwrap(),
+            RunOutcome::Failed("boom".to_string())
+        );
+        assert!(!handler.is_running());
+
+        // the task keeps running after a panic
+        assert_eq!(
+            sched.trigger_blocking(&handler).unwrap(),
+            RunOutcome::Completed
+        );
+    }
+
+    #[test]
+    fn test_panicking_action_with_timeout_fails_the_run() {
+        let options = TaskOptions::new().with_timeout(std::time::Duration::from_secs(5));
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule_with_options(
+                "panicking",
+                || panic!("boom: {}", 42),
+                vec![once(Local::now() + Duration::days(1))],
+                options,
+            )
+            .unwrap();
+
+        for _ in 0..2 {
+            assert_eq!(
+                sched.trigger_blocking(&handler).unwrap(),
+                RunOutcome::Failed("boom: 42".to_string())
+            );
+        }
+        assert!(!handler.is_running());
+    }
+
+    #[cfg(all(feature = "sched.control", unix))]
+    #[test]
+    fn test_control_server_on_unix_socket() {
+        use crate::sched::control::{serve_control, ControlClient, ControlEndpoint};
+
+        let path = std::env::temp_dir().join(format!("lool-control-{}.sock", std::process::id()));
+        let sched = Arc::new(Mutex::new(Scheduler::new()));
+        sched
+            .lock()
+            .unwrap()
+            .schedule("backup", || {}, once(Local::now() + Duration::days(1)))
+            .unwrap();
+
+        let control = sched.clone();
+        let server = serve_control(ControlEndpoint::Unix(path.clone()), move |request| {
+            control.lock().unwrap().control(request)
+        })
+        .unwrap();
+
+        let mut client = ControlClient::connect(&ControlEndpoint::Unix(path.clone())).unwrap();
+        assert_eq!(client.list().unwrap()[0].name, "backup");
+
+        server.shutdown();
+        assert!(!path.exists());
+    }
 }
@@ -1,250 +1,565 @@
 use {
     crate::sched::{
+        history::{RunOutcome, RunTrigger},
+        options::TaskOptions,
         rules::SchedulingRule,
-        task_handler::{get_next_run_time, TaskHandler},
+        scheduler::{self, IntoAction, SchedulerBackend},
+        task_handler::TaskHandler,
     },
     chrono::{DateTime, Local},
     eyre::{eyre, Result},
     log::debug,
     std::{
-        collections::HashMap,
         future::Future,
+        panic::resume_unwind,
         pin::Pin,
-        sync::{
-            atomic::{AtomicBool, AtomicPtr, Ordering},
-            Arc,
-        },
+        sync::{self, Arc, PoisonError, TryLockError},
+    },
+    tokio::{
+        runtime::Handle,
+        sync::Mutex,
+        task::spawn_blocking,
+        time::{sleep, timeout},
     },
-    tokio::{spawn, sync::Mutex, task::JoinHandle, time::sleep},
 };
 
-struct ScheduledTask {
-    name: String,
-    action: Pin<Box<dyn Future<Output = ()> + Send + 'static>>,
-    rules: Arc<Vec<SchedulingRule>>,
-    is_running: Arc<AtomicBool>,
-    is_stopped: Arc<AtomicBool>,
-    is_removed: Arc<AtomicBool>,
-    last_run: Arc<AtomicPtr<DateTime<Local>>>,
+type BoxedFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+type AsyncAction = Box<dyn FnMut() -> BoxedFuture + Send + 'static>;
+type BlockingAction = Arc<sync::Mutex<dyn FnMut() + Send + 'static>>;
+
+/// 🧉 » tokio action
+///
+/// the action of a task in the tokio backend: a closure returning a future, or a blocking closure
+/// run in the blocking thread pool of the runtime (see `Scheduler::schedule_blocking`).
+pub struct TokioAction(ActionKind);
+
+enum ActionKind {
+    Async(AsyncAction),
+    /// a single future, taken by the first run
+    Future(Option<BoxedFuture>),
+    /// shared with the blocking thread running the action
+    Blocking(BlockingAction),
 }
 
-impl ScheduledTask {
-    async fn run(&mut self) {
-        self.action.as_mut().await;
-    }
+impl TokioAction {
+    /// returns a future running the action once
+    fn call(&mut self) -> BoxedFuture {
+        match &mut self.0 {
+            ActionKind::Async(action) => action(),
+            ActionKind::Future(future) => future.take().unwrap_or_else(|| Box::pin(async {})),
+            ActionKind::Blocking(action) => {
+                let action = action.clone();
 
-    fn make_handler(&self) -> TaskHandler {
-        TaskHandler {
-            name: self.name.clone(),
-            rules: self.rules.clone(),
-            is_running: self.is_running.clone(),
-            is_stopped: self.is_stopped.clone(),
-            is_removed: self.is_removed.clone(),
-            last_run: self.last_run.clone(),
+                Box::pin(async move {
+                    // a panic poisons the lock of the action, which is ignored so later runs can
+                    // still call it
+                    let run = spawn_blocking(move || {
+                        (action.lock().unwrap_or_else(PoisonError::into_inner))()
+                    });
+
+                    // the panic is raised again, to be caught like the ones of async actions
+                    if let Err(err) = run.await {
+                        if err.is_panic() {
+                            resume_unwind(err.into_panic());
+                        }
+                    }
+                })
+            }
         }
     }
 
-    fn is_active(&self) -> bool {
-        !self.is_stopped.load(Ordering::Relaxed) && !self.is_removed.load(Ordering::Relaxed)
+    /// whether the action was a future that an earlier run has already taken
+    fn is_spent(&self) -> bool {
+        matches!(self.0, ActionKind::Future(None))
     }
 
-    fn is_removed(&self) -> bool {
-        self.is_removed.load(Ordering::Relaxed)
+    /// whether a blocking run that timed out is still running in the background
+    fn is_still_running(&self) -> bool {
+        match &self.0 {
+            ActionKind::Async(_) | ActionKind::Future(_) => false,
+            ActionKind::Blocking(action) => {
+                matches!(action.try_lock(), Err(TryLockError::WouldBlock))
+            }
+        }
     }
 }
 
-/// 🧉 » a task scheduler.
+/// 🧉 » tokio backend
 ///
-/// this struct is responsible for scheduling tasks to be executed at specific times, depending on
-/// the rules provided for each task.
+/// runs each task in its own tokio task, in the runtime the task was scheduled from.
+pub struct TokioBackend;
+
+/// 🧉 » a task scheduler running each task in a tokio task
 ///
-/// Each task can have n rules, and the task will be executed when any of the rules is met.
-pub struct Scheduler {
-    tasks: HashMap<String, Arc<Mutex<ScheduledTask>>>,
+/// tasks must be scheduled from within a tokio runtime, where they will run.
+pub type Scheduler = scheduler::Scheduler<TokioBackend>;
+
+/// 🧉 » a task scheduled in a tokio scheduler
+pub type ScheduledTask = scheduler::ScheduledTask<TokioBackend>;
+
+/// 🧉 » tokio runner
+///
+/// the action of a task, and the runtime it runs in. Only used by the backend.
+pub struct TokioRunner {
+    /// locked during a whole run, so runs of the same task never overlap
+    action: Mutex<TokioAction>,
+    runtime: Handle,
 }
 
-impl Default for Scheduler {
-    fn default() -> Self {
-        Self::new()
+impl SchedulerBackend for TokioBackend {
+    type Action = TokioAction;
+    type Runner = TokioRunner;
+
+    fn runner(action: TokioAction) -> TokioRunner {
+        TokioRunner {
+            action: Mutex::new(action),
+            runtime: Handle::current(),
+        }
+    }
+
+    fn spawn(task: Arc<ScheduledTask>) {
+        let runtime = task.runner.runtime.clone();
+
+        runtime.spawn(async move {
+            let mut maybe_next_run = task.first_run();
+
+            while let Some(run_date) = maybe_next_run {
+                task.log_next_run(run_date);
+                sleep_until(run_date).await;
+
+                let deferred_until = wait_for_constraints(&task).await;
+
+                if task.is_active() {
+                    run(&task, RunTrigger::Scheduled, run_date).await;
+                }
+
+                maybe_next_run = task.next_run(run_date, deferred_until);
+            }
+
+            debug!("task {} has finished", task.name);
+        });
+    }
+
+    fn spawn_manual_run(task: Arc<ScheduledTask>) {
+        let runtime = task.runner.runtime.clone();
+
+        runtime.spawn(async move {
+            if let Err(err) = run_manually(&task).await {
+                debug!("{}", err);
+            }
+        });
     }
 }
 
-impl Scheduler {
-    /// 🧉 » create a new scheduler
-    pub fn new() -> Self {
-        Self {
-            tasks: HashMap::new(),
-        }
+impl<F, Fut> IntoAction<TokioBackend> for F
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    fn into_action(mut self) -> TokioAction {
+        TokioAction(ActionKind::Async(Box::new(move || Box::pin(self()))))
     }
+}
 
-    /// 🧉 » schedule an async task
+impl Scheduler {
+    /// 🧉 » schedule a future
     ///
-    /// schedules an async function to be executed as a task at time intervals determined by the
-    /// provided     /// rules.
-    pub async fn schedule<F, Fut, Str>(
+    /// schedules a future to be executed as a task at time intervals determined by the provided
+    /// rules.
+    ///
+    /// a future can only be awaited once, so only the first run of the task awaits it and the
+    /// following runs are recorded as `RunOutcome::Skipped`. Use `schedule` to run a new future on
+    /// each occurrence.
+    pub fn schedule_fut<Fut, Str>(
         &mut self,
         name: Str,
-        func: F,
+        future: Fut,
         rules: SchedulingRule,
-    ) -> TaskHandler
+    ) -> Result<TaskHandler>
     where
-        F: FnMut() -> Fut + Send + 'static,
         Fut: Future<Output = ()> + Send + 'static,
         Str: AsRef<str>,
     {
         let name = name.as_ref();
-        let mut func = func;
-        self.schedule_many_rules(name, func(), vec![rules]).await
+        self.schedule_many_rules(name, future, vec![rules])
     }
 
-    /// 🧉 » schedule a future
+    /// 🧉 » schedule a task
     ///
-    /// schedules a future to be executed as a task at time intervals determined by the provided
-    /// rules.
-    pub async fn schedule_fut<Fut, Str>(
+    /// schedules a task to be executed at times determined by the provided rules.
+    ///
+    /// like in `schedule_fut`, the future is only awaited by the first run of the task, and the
+    /// following runs are skipped.
+    pub fn schedule_many_rules<Fut>(
         &mut self,
-        name: Str,
+        name: &str,
         future: Fut,
-        rules: SchedulingRule,
-    ) -> TaskHandler
+        rules: Vec<SchedulingRule>,
+    ) -> Result<TaskHandler>
     where
         Fut: Future<Output = ()> + Send + 'static,
+    {
+        let action = TokioAction(ActionKind::Future(Some(Box::pin(future))));
+        self.insert_task(name, action, rules, TaskOptions::default())
+    }
+
+    /// 🧉 » schedule a blocking task
+    ///
+    /// schedules a sync function to be executed as a task at times determined by the provided
+    /// rules. Each run happens in the blocking thread pool of the runtime (through
+    /// `tokio::task::spawn_blocking`), so CPU-heavy actions or actions calling blocking libraries
+    /// don't starve the async tasks.
+    ///
+    /// blocking tasks are handled like async ones: their runs never overlap, and they are tracked
+    /// by the same `TaskHandler`.
+    pub fn schedule_blocking<F, Str>(
+        &mut self,
+        name: Str,
+        action: F,
+        rules: SchedulingRule,
+    ) -> Result<TaskHandler>
+    where
+        F: FnMut() + Send + 'static,
         Str: AsRef<str>,
     {
         let name = name.as_ref();
-        self.schedule_many_rules(name, future, vec![rules]).await
+        self.schedule_blocking_with_options(name, action, vec![rules], TaskOptions::default())
     }
 
-    /// 🧉 » schedule a task
+    /// 🧉 » schedule a blocking task with options
     ///
-    /// schedules a task to be executed at times determined by the provided rules.
-    pub async fn schedule_many_rules<Fut>(
+    /// same as `schedule_blocking`, using the settings from `options`.
+    ///
+    /// blocking threads can't be cancelled, so a run exceeding the timeout of the task is recorded
+    /// as `RunOutcome::TimedOut` but keeps running in the background. Until it finishes, the
+    /// following runs of the task are skipped.
+    pub fn schedule_blocking_with_options<F>(
         &mut self,
         name: &str,
-        future: Fut,
+        action: F,
         rules: Vec<SchedulingRule>,
-    ) -> TaskHandler
+        options: TaskOptions,
+    ) -> Result<TaskHandler>
     where
-        Fut: Future<Output = ()> + Send + 'static,
+        F: FnMut() + Send + 'static,
     {
-        let task = Arc::new(Mutex::new(ScheduledTask {
-            name: name.to_string(),
-            action: Box::pin(future),
-            rules: Arc::new(rules),
-            is_running: Arc::new(AtomicBool::new(false)),
-            is_stopped: Arc::new(AtomicBool::new(false)),
-            is_removed: Arc::new(AtomicBool::new(false)),
-            last_run: Arc::new(AtomicPtr::new(std::ptr::null_mut())),
-        }));
-
-        self.tasks.insert(name.to_string(), task.clone());
-
-        spawn_task(task.clone());
-
-        let handler: TaskHandler = {
-            let task = task.lock().await;
-            task.make_handler()
-        };
+        let action = TokioAction(ActionKind::Blocking(Arc::new(sync::Mutex::new(action))));
+        self.insert_task(name, action, rules, options)
+    }
 
-        handler
+    /// 🧉 » trigger a task and wait for it
+    ///
+    /// same as `trigger`, but the returned future resolves once the run finishes, with the outcome
+    /// of the run.
+    pub async fn trigger_await(&self, handler: &TaskHandler) -> Result<RunOutcome> {
+        let task = self.find(handler)?;
+        debug!("task {} has been triggered", handler.name());
+        run_manually(task).await
     }
+}
 
-    /// 🧉 » stop a task
-    pub async fn stop(&mut self, handler: &TaskHandler) -> Result<()> {
-        // get the task from the tasks map
-        let task = self.tasks.get(handler.name());
+/// **runs a task once, unless its checks skip the run**
+async fn run(task: &ScheduledTask, trigger: RunTrigger, planned_at: DateTime<Local>) -> RunOutcome {
+    let mut action = task.runner.action.lock().await;
 
-        if let Some(task) = task {
-            let task = task.lock().await;
-            task.is_stopped.store(true, Ordering::Relaxed);
-            debug!("task {} has been stopped", handler.name());
-            Ok(())
-        } else {
-            Err(eyre!("task {} was not found", handler.name()))
-        }
+    if action.is_still_running() {
+        let reason = "the previous run timed out and is still running".to_string();
+        return task.skip(trigger, planned_at, Local::now(), reason);
     }
 
-    /// 🧉 » resume a task
-    pub async fn resume(&mut self, handler: &TaskHandler) -> Result<()> {
-        // get the task from the tasks map
-        let task = self.tasks.get(handler.name());
+    if action.is_spent() {
+        let reason = "the future of the task has already been awaited".to_string();
+        return task.skip(trigger, planned_at, Local::now(), reason);
+    }
 
-        if let Some(task) = task {
-            let task = task.lock().await;
-            task.is_stopped.store(false, Ordering::Relaxed);
-            debug!("task {} has been resumed", handler.name());
-            Ok(())
-        } else {
-            Err(eyre!("task {} was not found", handler.name()))
+    match task.start_run(trigger, planned_at) {
+        Ok(started) => {
+            let outcome = run_action(task, &mut action).await;
+            task.finish_run(started, outcome)
         }
+        Err(outcome) => outcome,
+    }
+}
+
+/// **runs the action, aborting it if the task has a timeout and it expires**
+///
+/// the action runs in its own tokio task, so its panics are caught and recorded as the outcome of
+/// the run.
+async fn run_action(task: &ScheduledTask, action: &mut TokioAction) -> RunOutcome {
+    let mut running = task.runner.runtime.spawn(action.call());
+
+    let result = match task.timeout {
+        None => (&mut running).await,
+        Some(limit) => match timeout(limit, &mut running).await {
+            Ok(result) => result,
+            Err(_) => {
+                running.abort();
+                debug!("task {} timed out after {:?}", task.name, limit);
+                return RunOutcome::TimedOut;
+            }
+        },
+    };
+
+    match result {
+        Ok(()) => RunOutcome::Completed,
+        Err(err) => match err.try_into_panic() {
+            Ok(payload) => RunOutcome::panicked(payload.as_ref()),
+            Err(err) => RunOutcome::Failed(err.to_string()),
+        },
+    }
+}
+
+/// **runs a task once because of a manual trigger**
+async fn run_manually(task: &ScheduledTask) -> Result<RunOutcome> {
+    let requested_at = Local::now();
+
+    if task.is_removed() {
+        return Err(eyre!("task {} has been removed", task.name));
     }
 
-    /// 🧉 » remove a task
-    pub async fn remove(&mut self, handler: &TaskHandler) -> Result<()> {
-        // get the task from the tasks map
-        let task = self.tasks.remove(handler.name());
+    Ok(run(task, RunTrigger::Manual, requested_at).await)
+}
 
-        if let Some(task) = task {
-            let task = task.lock().await;
-            task.is_removed.store(true, Ordering::Relaxed);
-            debug!("task {} has been removed", handler.name());
-            Ok(())
-        } else {
-            handler.is_removed.store(true, Ordering::Relaxed);
-            Err(eyre!("task {} was not found", handler.name()))
+/// **sleeps until the given date**
+///
+/// sleeps again if it wakes up before the date (e.g. after the system clock has been adjusted),
+/// so runs start within a millisecond of their date.
+async fn sleep_until(date: DateTime<Local>) {
+    while let Ok(duration) = (date - Local::now()).to_std() {
+        if duration.is_zero() {
+            break;
         }
+
+        sleep(duration).await;
     }
 }
 
-/// **main function to spawn a task in tokio**
+/// **waits while the constraints of a task defer its next run**
 ///
-/// it spawns a new tokio task and runs the task according to its scheduling rules.
-fn spawn_task(task_mutex: Arc<Mutex<ScheduledTask>>) -> JoinHandle<()> {
-    spawn(async move {
-        let (mut maybe_next_run, name) = {
-            let task = task_mutex.lock().await;
-            let rules = &task.rules;
-
-            (get_next_run_time(rules, None), task.name.clone())
-        };
+/// returns the date the wait ended, or `None` if the run didn't have to wait.
+async fn wait_for_constraints(task: &ScheduledTask) -> Option<DateTime<Local>> {
+    let mut waited_until = None;
 
-        while let Some(run_date) = maybe_next_run {
-            let now = Local::now();
-            if run_date > now {
-                // if the next run is in the future, go to bed until then
-                let sleep_until = run_date - now;
-                debug!(
-                    "task {} will run in {} seconds",
-                    name,
-                    sleep_until.num_seconds()
-                );
-
-                sleep(sleep_until.to_std().unwrap()).await;
-            } else {
-                // if the next run is in the past, run the task immediately, probably missed the
-                // run time for a few nanos
-                debug!("task will run in 0 seconds");
-            }
+    while let Some(retry_at) = task.deferred_until() {
+        sleep_until(retry_at).await;
+        waited_until = Some(Local::now());
+    }
 
-            let mut task = task_mutex.lock().await;
+    waited_until
+}
 
-            if task.is_active() {
-                let run_date_box = Box::new(run_date);
-                let run_date_raw = Box::into_raw(run_date_box);
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::sched::once,
+        chrono::Duration,
+        std::sync::atomic::{AtomicUsize, Ordering},
+    };
 
-                task.last_run.store(run_date_raw, Ordering::Relaxed);
-                task.is_running.store(true, Ordering::SeqCst);
-                task.run().await;
-                task.is_running.store(false, Ordering::SeqCst);
-            }
+    #[tokio::test]
+    async fn test_trigger_await_runs_the_task() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let action_counter = counter.clone();
+        let next_run = Local::now() + Duration::days(1);
 
-            if !task.is_removed() {
-                maybe_next_run = get_next_run_time(&task.rules, Some(run_date));
-            } else {
-                maybe_next_run = None;
-            }
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule(
+                "trigger-await",
+                move || {
+                    let counter = action_counter.clone();
+                    async move {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                },
+                once(next_run),
+            )
+            .unwrap();
+
+        // the action can be run more than once
+        sched.trigger_await(&handler).await.unwrap();
+        let outcome = sched.trigger_await(&handler).await.unwrap();
+
+        assert_eq!(outcome, RunOutcome::Completed);
+        assert_eq!(counter.load(Ordering::SeqCst), 2);
+        assert_eq!(handler.last_record().unwrap().trigger, RunTrigger::Manual);
+        // the regular occurrence is left untouched
+        assert_eq!(handler.get_next_run(), Some(next_run));
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_future_is_only_awaited_once() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let action_counter = counter.clone();
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule_fut(
+                "future",
+                async move {
+                    action_counter.fetch_add(1, Ordering::SeqCst);
+                },
+                once(Local::now() + Duration::days(1)),
+            )
+            .unwrap();
+
+        assert_eq!(
+            sched.trigger_await(&handler).await.unwrap(),
+            RunOutcome::Completed
+        );
+        assert!(matches!(
+            sched.trigger_await(&handler).await.unwrap(),
+            RunOutcome::Skipped(_)
+        ));
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert_eq!(sched.metrics().tasks[0].runs, 1);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_drops_the_run() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let action_counter = counter.clone();
+        let options = TaskOptions::new().with_timeout(std::time::Duration::from_millis(50));
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule_with_options(
+                "timeout",
+                move || {
+                    let counter = action_counter.clone();
+                    async move {
+                        sleep(std::time::Duration::from_secs(10)).await;
+                        counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                },
+                vec![once(Local::now() + Duration::days(1))],
+                options,
+            )
+            .unwrap();
+
+        assert_eq!(
+            sched.trigger_await(&handler).await.unwrap(),
+            RunOutcome::TimedOut
+        );
+        assert_eq!(
+            sched.trigger_await(&handler).await.unwrap(),
+            RunOutcome::TimedOut
+        );
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+        assert!(!handler.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_action_fails_the_run() {
+        let options = TaskOptions::new().with_timeout(std::time::Duration::from_secs(5));
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule(
+                "panicking",
+                || async { panic!("boom") },
+                once(Local::now() + Duration::days(1)),
+            )
+            .unwrap();
+        let blocking = sched
+            .schedule_blocking_with_options(
+                "panicking-blocking",
+                || panic!("blocking boom"),
+                vec![once(Local::now() + Duration::days(1))],
+                options,
+            )
+            .unwrap();
+
+        // the tasks keep running after a panic
+        for _ in 0..2 {
+            assert_eq!(
+                sched.trigger_await(&handler).await.unwrap(),
+                RunOutcome::Failed("boom".to_string())
+            );
+            assert_eq!(
+                sched.trigger_await(&blocking).await.unwrap(),
+                RunOutcome::Failed("blocking boom".to_string())
+            );
         }
+        assert!(!handler.is_running());
+        assert!(!blocking.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_blocking_task_runs_in_the_blocking_pool() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let action_counter = counter.clone();
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule_blocking(
+                "blocking",
+                move || {
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    action_counter.fetch_add(1, Ordering::SeqCst);
+                },
+                once(Local::now() + Duration::days(1)),
+            )
+            .unwrap();
+
+        // the test runtime has a single thread, which must keep serving async tasks
+        let ticks = Arc::new(AtomicUsize::new(0));
+        let seen_running = Arc::new(AtomicUsize::new(0));
+        let ticker = {
+            let (ticks, seen_running, handler) =
+                (ticks.clone(), seen_running.clone(), handler.clone());
+
+            tokio::spawn(async move {
+                loop {
+                    sleep(std::time::Duration::from_millis(10)).await;
+                    ticks.fetch_add(1, Ordering::SeqCst);
+                    if handler.is_running() {
+                        seen_running.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            })
+        };
+
+        let outcome = sched.trigger_await(&handler).await.unwrap();
+        ticker.abort();
 
-        debug!("task {} has finished", name);
-    })
+        assert_eq!(outcome, RunOutcome::Completed);
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert!(ticks.load(Ordering::SeqCst) >= 5);
+        assert!(seen_running.load(Ordering::SeqCst) > 0);
+        assert!(!handler.is_running());
+        assert_eq!(handler.last_record().unwrap().trigger, RunTrigger::Manual);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_blocking_task_skips_runs() {
+        let options = TaskOptions::new().with_timeout(std::time::Duration::from_millis(20));
+
+        let mut sched = Scheduler::new();
+        let handler = sched
+            .schedule_blocking_with_options(
+                "hung",
+                || std::thread::sleep(std::time::Duration::from_millis(300)),
+                vec![once(Local::now() + Duration::days(1))],
+                options,
+            )
+            .unwrap();
+
+        assert_eq!(
+            sched.trigger_await(&handler).await.unwrap(),
+            RunOutcome::TimedOut
+        );
+        assert!(!handler.is_running());
+
+        let outcome = sched.trigger_await(&handler).await.unwrap();
+        assert!(matches!(outcome, RunOutcome::Skipped(_)));
+
+        // once the blocking run is over, the task runs again
+        sleep(std::time::Duration::from_millis(350)).await;
+        assert_eq!(
+            sched.trigger_await(&handler).await.unwrap(),
+            RunOutcome::TimedOut
+        );
+    }
 }
@@ -1,16 +1,50 @@
 use {
-    super::rules::SchedulingRule,
+    super::{
+        history::{RunHistory, RunRecord},
+        jitter::TaskJitter,
+        metrics::TaskMetrics,
+        rules::SchedulingRule,
+    },
     chrono::{DateTime, Local},
     core::fmt,
+    eyre::Result,
     std::{
+        collections::{BTreeMap, BTreeSet},
         fmt::{Debug, Formatter},
         sync::{
-            atomic::{AtomicBool, AtomicPtr, Ordering},
+            atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering},
             Arc,
         },
     },
 };
 
+/// 🧉 » task id
+///
+/// unique identifier of a scheduled task, generated when the task is scheduled.
+///
+/// unlike names, ids are never shared by two tasks, even across schedulers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u64);
+
+impl TaskId {
+    /// generates a new unique id
+    pub(crate) fn generate() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// 🧉 » numeric value of the id
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 /// 🧉 » task handler
 ///
 /// returned by the `Scheduler::schedule` method,
@@ -18,18 +52,26 @@ use {
 /// the status of the task.
 #[derive(Clone)]
 pub struct TaskHandler {
+    pub(crate) id: TaskId,
     pub(crate) name: String,
     pub(crate) rules: Arc<Vec<SchedulingRule>>,
+    pub(crate) jitter: Option<TaskJitter>,
+    pub(crate) tags: Arc<BTreeSet<String>>,
+    pub(crate) metadata: Arc<BTreeMap<String, String>>,
     pub(crate) is_running: Arc<AtomicBool>,
     pub(crate) is_stopped: Arc<AtomicBool>,
     pub(crate) is_removed: Arc<AtomicBool>,
     pub(crate) last_run: Arc<AtomicPtr<DateTime<Local>>>,
+    pub(crate) history: RunHistory,
+    pub(crate) trigger: Arc<dyn Fn() -> Result<()> + Send + Sync>,
 }
 
 impl Debug for TaskHandler {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("TaskHandler")
+            .field("id", &self.id)
             .field("name", &self.name)
+            .field("tags", &self.tags)
             .field("is_running", &self.is_running)
             .field("is_stopped", &self.is_stopped)
             .field("is_removed", &self.is_removed)
@@ -56,9 +98,11 @@ impl TaskHandler {
     /// 🧉 » next run date
     ///
     /// returns a `DateTime<Local>` representing the next time the task is scheduled to run
+    ///
+    /// if the task has a jitter, the returned date already includes the delay of the occurrence.
     pub fn get_next_run(&self) -> Option<DateTime<Local>> {
         if self.is_active() {
-            return get_next_run_time(&self.rules, None);
+            return get_next_run_time(&self.rules, self.jitter.as_ref(), None);
         }
 
         None
@@ -71,6 +115,13 @@ impl TaskHandler {
         self.is_running.load(Ordering::Relaxed)
     }
 
+    /// 🧉 » id
+    ///
+    /// returns the unique id of the task
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
     /// 🧉 » name
     ///
     /// returns the name of the task
@@ -78,6 +129,48 @@ impl TaskHandler {
         &self.name
     }
 
+    /// 🧉 » tags
+    ///
+    /// returns the tags of the task
+    pub fn tags(&self) -> &BTreeSet<String> {
+        &self.tags
+    }
+
+    /// 🧉 » has tag?
+    ///
+    /// returns a `bool` indicating if the task has the given tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// 🧉 » has any tag?
+    ///
+    /// returns a `bool` indicating if the task has at least one of the given tags
+    pub fn has_any_tag(&self, tags: &[&str]) -> bool {
+        tags.iter().any(|tag| self.has_tag(tag))
+    }
+
+    /// 🧉 » has all tags?
+    ///
+    /// returns a `bool` indicating if the task has every one of the given tags
+    pub fn has_all_tags(&self, tags: &[&str]) -> bool {
+        tags.iter().all(|tag| self.has_tag(tag))
+    }
+
+    /// 🧉 » metadata
+    ///
+    /// returns the metadata of the task
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// 🧉 » metadata entry
+    ///
+    /// returns the value of a metadata entry of the task, if present
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+
     /// 🧉 » is active?
     ///
     /// returns a `bool` indicating if the task is active
@@ -103,23 +196,89 @@ impl TaskHandler {
     pub fn is_removed(&self) -> bool {
         self.is_removed.load(Ordering::Relaxed)
     }
+
+    /// 🧉 » run now
+    ///
+    /// requests a manual run of the task, without waiting for it to finish.
+    ///
+    /// this is the same as calling `Scheduler::trigger` with this handler: the run is recorded as
+    /// `RunTrigger::Manual` in the history and the next regular occurrence is left unchanged.
+    pub fn run_now(&self) -> Result<()> {
+        (self.trigger)()
+    }
+
+    /// 🧉 » run history
+    ///
+    /// returns the latest runs of the task, from the oldest to the newest.
+    pub fn history(&self) -> Vec<RunRecord> {
+        self.history.records()
+    }
+
+    /// 🧉 » last run record
+    ///
+    /// returns the record of the latest finished run of the task, if any.
+    pub fn last_record(&self) -> Option<RunRecord> {
+        self.history.last()
+    }
+
+    /// 🧉 » metrics
+    ///
+    /// returns the metrics of every run of the task, along with its next run date.
+    pub fn metrics(&self) -> TaskMetrics {
+        TaskMetrics::new(
+            self.id,
+            &self.name,
+            self.history.metrics(),
+            self.get_next_run(),
+        )
+    }
 }
 
 /// **get next run time**
 ///
-/// this function takes a list of scheduling rules and a base time, and returns the next time the
-/// task should run.
+/// this function takes a list of scheduling rules, the jitter of the task and a base time, and
+/// returns the next time the task should run.
 ///
-/// to determine the next run time, it iterates over the list of rules and calculates the next run
-/// time for each of them, returning the earliest of them all.
+/// without jitter, this is the next occurrence of the rules. With jitter, the occurrences are
+/// delayed by the jitter offset, so an occurrence that happened before `from` might still be
+/// pending. Because of that, the search starts one jitter window before `from`, and returns the
+/// first jittered occurrence that comes after `from`.
 pub(crate) fn get_next_run_time(
     rules: &Vec<SchedulingRule>,
+    jitter: Option<&TaskJitter>,
     from: Option<DateTime<Local>>,
 ) -> Option<DateTime<Local>> {
-    let mut next_run_so_far: Option<DateTime<Local>> = None;
-
     let base = if let Some(from) = from { from } else { Local::now() };
 
+    let jitter = match jitter {
+        Some(jitter) => jitter,
+        None => return get_next_occurrence(rules, base),
+    };
+
+    let mut maybe_occurrence = get_next_occurrence(rules, base - jitter.window());
+
+    while let Some(occurrence) = maybe_occurrence {
+        let run_date = jitter.apply(occurrence);
+        if run_date > base {
+            return Some(run_date);
+        }
+
+        maybe_occurrence = get_next_occurrence(rules, occurrence);
+    }
+
+    None
+}
+
+/// **get next occurrence**
+///
+/// to determine the next occurrence, it iterates over the list of rules and calculates the next
+/// run time for each of them, returning the earliest of them all.
+fn get_next_occurrence(
+    rules: &Vec<SchedulingRule>,
+    base: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    let mut next_run_so_far: Option<DateTime<Local>> = None;
+
     for rule in rules {
         let rule_next_run = rule.next_from(base);
 
@@ -136,3 +295,45 @@ pub(crate) fn get_next_run_time(
 
     next_run_so_far
 }
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::sched::{jitter::Jitter, once},
+        chrono::{Duration, TimeZone},
+    };
+
+    #[test]
+    fn test_next_run_time_without_jitter() {
+        let from = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let first = from + Duration::minutes(10);
+        let second = from + Duration::minutes(5);
+        let rules = vec![once(first), once(second)];
+
+        assert_eq!(get_next_run_time(&rules, None, Some(from)), Some(second));
+        assert_eq!(get_next_run_time(&rules, None, Some(second)), Some(first));
+        assert_eq!(get_next_run_time(&rules, None, Some(first)), None);
+    }
+
+    #[test]
+    fn test_next_run_time_with_jitter() {
+        let from = Local.with_ymd_and_hms(2024, 4, 7, 16, 0, 0).unwrap();
+        let first = from + Duration::minutes(5);
+        let second = from + Duration::minutes(10);
+        let rules = vec![once(first), once(second)];
+        let jitter = Jitter::hashed(std::time::Duration::from_secs(60)).for_task("task");
+        let offset = jitter.offset(first);
+
+        let next = get_next_run_time(&rules, Some(&jitter), Some(from));
+        assert_eq!(next, Some(first + offset));
+
+        // an occurrence that already happened is still pending until its jittered date
+        let next = get_next_run_time(&rules, Some(&jitter), Some(first));
+        assert_eq!(next, Some(first + offset));
+
+        // once the jittered date has passed, the next occurrence is returned
+        let next = get_next_run_time(&rules, Some(&jitter), Some(first + offset));
+        assert_eq!(next, Some(second + offset));
+    }
+}
@@ -17,6 +17,7 @@ pub enum TimeUnit {
     Hour,
     Minute,
     Second,
+    Millisecond,
 }
 
 impl<Tz: TimeZone> LoolDate<Tz> {
@@ -76,6 +77,12 @@ impl<Tz: TimeZone> LoolDate<Tz> {
         self.set_start_of(TimeUnit::Second);
     }
 
+    /// adds `1` millisecond to the current date
+    pub fn add_millisecond(&mut self) {
+        self.date += Duration::milliseconds(1);
+        self.set_start_of(TimeUnit::Millisecond);
+    }
+
     /// subtracts `1` year from the current date
     pub fn subs_year(&mut self) {
         self.date = self.date.clone() - Months::new(12);
@@ -106,6 +113,11 @@ impl<Tz: TimeZone> LoolDate<Tz> {
         self.date -= Duration::seconds(1);
     }
 
+    /// subtracts `1` millisecond from the current date
+    pub fn subs_millisecond(&mut self) {
+        self.date -= Duration::milliseconds(1);
+    }
+
     /// returns the day of the month starting from `1`
     pub fn day(&self) -> u32 {
         self.date.day()
@@ -373,6 +385,9 @@ impl<Tz: TimeZone> LoolDate<Tz> {
             TimeUnit::Second => {
                 self.set_nanos(0);
             }
+            TimeUnit::Millisecond => {
+                self.set_millis(self.millis());
+            }
         };
     }
 }
@@ -544,4 +559,20 @@ mod tests {
         assert_eq!(date.month(), 2);
         assert_eq!(date.day(), 28);
     }
+
+    #[test]
+    fn test_add_millisecond() {
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 23, 59, 59).unwrap();
+        let mut date = LoolDate::new(base + Duration::microseconds(998_700));
+        date.add_millisecond();
+        assert_eq!(date.millis(), 999);
+        assert_eq!(date.micros(), 999_000);
+
+        // crossing the second also crosses the day
+        date.add_millisecond();
+        assert_eq!(
+            date.date(),
+            Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap()
+        );
+    }
 }
@@ -88,6 +88,21 @@ pub fn tz_to_s(offset: &str) -> Result<i32> {
     Ok(sign * hm_to_s(hours as i32, minutes as i32))
 }
 
+/// 🧉 » 64-bit FNV-1a hash
+///
+/// unlike the hashers from `std`, its output is stable across builds and platforms, so it can be
+/// used for values that must survive restarts.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
 #[cfg(test)]
 mod tests {
     use {